@@ -0,0 +1,362 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use panopticon_core::extend::{Attributes, ResultKind, ResultSpec, ScalarType, TypeDef};
+use panopticon_core::prelude::*;
+use std::path::{Path, PathBuf};
+
+/*
+    Types:
+    * Cli - Command line arguments
+    * CliCommand - Subcommands (run, watch, validate, explain, graph, repl, describe-command)
+    * RunArgs - Arguments of `run`, mostly ResultSettings
+    * GraphFormat - Output format of `graph`
+*/
+#[derive(Parser)]
+#[command(
+    name = "panopticon",
+    version,
+    about = "Run and inspect Panopticon pipeline files"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: CliCommand,
+}
+
+#[derive(Subcommand)]
+enum CliCommand {
+    /// Load, compile and execute a pipeline file, then write and summarise its results
+    Run(RunArgs),
+    /// Like run, in a terminal UI showing progress and then browsing the results
+    #[cfg(feature = "tui")]
+    Watch(RunArgs),
+    /// Load and compile a pipeline file without executing it
+    Validate {
+        /// Pipeline file (.yaml, .yml, .json or .toml)
+        file: PathBuf,
+    },
+    /// Print the execution plan of a pipeline file
+    Explain {
+        /// Pipeline file (.yaml, .yml, .json or .toml)
+        file: PathBuf,
+        /// Print the plan as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the namespace and command graph of a pipeline file
+    Graph {
+        /// Pipeline file (.yaml, .yml, .json or .toml)
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = GraphFormat::Dot)]
+        format: GraphFormat,
+    },
+    /// Start an interactive session, optionally loading a pipeline file first
+    Repl {
+        /// Pipeline file (.yaml, .yml, .json or .toml)
+        file: Option<PathBuf>,
+    },
+    /// Describe the attributes and results of a command type
+    DescribeCommand {
+        /// Command type, e.g. FileCommand. Lists the available types when omitted.
+        command_type: Option<String>,
+    },
+}
+
+#[derive(clap::Args)]
+struct RunArgs {
+    /// Pipeline file (.yaml, .yml, .json or .toml)
+    file: PathBuf,
+    /// Directory tabular results are written to [default: ./panopticon_results]
+    #[arg(long, short)]
+    output_dir: Option<PathBuf>,
+    /// Format tabular results are written in (csv, parquet, json or ndjson)
+    #[arg(long, default_value = "json")]
+    format: TabularFormat,
+    /// Leave a command's results out, e.g. --exclude load.raw (repeatable)
+    #[arg(long = "exclude", value_name = "NAMESPACE.COMMAND")]
+    excluded: Vec<String>,
+    /// Namespaces allowed to run at the same time (default 1)
+    #[arg(long)]
+    max_concurrency: Option<usize>,
+    /// Value of a pipeline input, e.g. --input tenant=acme (repeatable). Values of non-string inputs are read as YAML.
+    #[arg(long = "input", value_name = "NAME=VALUE")]
+    inputs: Vec<String>,
+    /// Directory holding one file per secret, checked before environment variables (e.g. /run/secrets)
+    #[arg(long)]
+    secrets_dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    match Cli::parse().command {
+        CliCommand::Run(args) => run(args).await,
+        #[cfg(feature = "tui")]
+        CliCommand::Watch(args) => watch(args).await,
+        CliCommand::Validate { file } => validate(&file).await,
+        CliCommand::Explain { file, json } => {
+            let explanation = load(&file).await?.compile().await?.explain()?;
+            if json {
+                println!("{}", explanation.to_json()?);
+            } else {
+                print!("{}", explanation);
+            }
+            Ok(())
+        }
+        CliCommand::Graph { file, format } => {
+            let graph = load(&file).await?.graph();
+            match format {
+                GraphFormat::Dot => print!("{}", graph.to_dot()),
+                GraphFormat::Mermaid => print!("{}", graph.to_mermaid()),
+            }
+            Ok(())
+        }
+        CliCommand::Repl { file } => {
+            let pipeline = match file {
+                Some(file) => load(&file).await?,
+                None => Pipeline::new(),
+            };
+            println!("Panopticon REPL, type 'help' for commands");
+            Repl::with_pipeline(pipeline)
+                .run(std::io::stdin().lock(), std::io::stdout())
+                .await
+        }
+        CliCommand::DescribeCommand { command_type } => describe_command(command_type.as_deref()),
+    }
+}
+
+async fn load(file: &Path) -> anyhow::Result<Pipeline> {
+    let document = PipelineDocument::from_file(file)?;
+    Pipeline::from_document(&document).await
+}
+
+async fn validate(file: &Path) -> anyhow::Result<()> {
+    let document = PipelineDocument::from_file(file)?;
+    let command_count: usize = document.namespaces.iter().map(|ns| ns.commands.len()).sum();
+    Pipeline::from_document(&document).await?.compile().await?;
+    println!(
+        "{}: valid ({} namespaces, {} commands)",
+        file.display(),
+        document.namespaces.len(),
+        command_count
+    );
+    Ok(())
+}
+
+fn settings(args: &RunArgs) -> (ExecutionSettings, ResultSettings) {
+    let mut settings = ExecutionSettings::new();
+    if let Some(max_concurrency) = args.max_concurrency {
+        settings = settings.with_max_concurrency(max_concurrency);
+    }
+    let mut result_settings = ResultSettings::new()
+        .with_format(args.format.clone())
+        .with_excluded_commands(
+            args.excluded
+                .iter()
+                .map(|path| StorePath::from_dotted(path))
+                .collect(),
+        );
+    if let Some(output_dir) = &args.output_dir {
+        result_settings = result_settings.with_output_path(output_dir.clone());
+    }
+    (settings, result_settings)
+}
+
+async fn run(args: RunArgs) -> anyhow::Result<()> {
+    let (settings, result_settings) = settings(&args);
+    let output_path = result_settings.output_path().clone();
+
+    let ready = load(&args.file).await?.compile().await?;
+    let inputs = input_values(&ready, &args.inputs)?;
+    let completed = with_secret_providers(ready, &args)
+        .with_settings(settings)
+        .with_inputs(inputs)
+        .execute()
+        .await?;
+    let results = completed.results(result_settings).await?;
+
+    print_summary(&results);
+    println!("\nResults written to {}", output_path.display());
+    Ok(())
+}
+
+#[cfg(feature = "tui")]
+async fn watch(args: RunArgs) -> anyhow::Result<()> {
+    let (settings, result_settings) = settings(&args);
+    let ready = load(&args.file).await?.compile().await?;
+    let inputs = input_values(&ready, &args.inputs)?;
+    let ready = with_secret_providers(ready, &args)
+        .with_settings(settings)
+        .with_inputs(inputs);
+    Tui::new(ready)
+        .with_result_settings(result_settings)
+        .run()
+        .await?;
+    Ok(())
+}
+
+// Parses NAME=VALUE pairs. String inputs take the value as written so e.g. a date stays a string, anything
+// else is read as YAML. Unknown names are passed through for the pipeline to reject.
+fn input_values<T>(pipeline: &Pipeline<T>, pairs: &[String]) -> anyhow::Result<Attributes> {
+    let mut inputs = Attributes::new();
+    for pair in pairs {
+        let (name, raw) = pair
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Expected NAME=VALUE for --input, got '{}'", pair))?;
+        let declared = pipeline.inputs().iter().find(|input| input.name() == name);
+        let value = match declared.map(|input| input.type_def()) {
+            Some(TypeDef::Scalar(ScalarType::String)) => ScalarValue::String(raw.to_string()),
+            _ => serde_yaml::from_str(raw)
+                .map_err(|e| anyhow::anyhow!("Invalid value for input '{}': {}", name, e))?,
+        };
+        inputs.insert(name.to_string(), value);
+    }
+    Ok(inputs)
+}
+
+// Secrets are read from --secrets-dir when given, then from environment variables named after the secret
+// in upper case, e.g. API_KEY for `api_key`
+fn with_secret_providers<T>(pipeline: Pipeline<T>, args: &RunArgs) -> Pipeline<T> {
+    let pipeline = match &args.secrets_dir {
+        Some(dir) => pipeline.with_secret_provider(FileSecrets::new(dir.clone())),
+        None => pipeline,
+    };
+    pipeline.with_secret_provider(EnvSecrets::new(""))
+}
+
+// One row per command (per iteration for iterative namespaces) with its status and duration meta
+fn print_summary(results: &ResultStore) {
+    let rows: Vec<[String; 4]> = results
+        .iter()
+        .map(|result| {
+            let meta = |field: &str| {
+                result
+                    .meta_get(&result.source().with_segment(field))
+                    .map(|value| match value.as_str() {
+                        Some(s) => s.to_string(),
+                        None => value.to_string(),
+                    })
+                    .unwrap_or_else(|| "-".to_string())
+            };
+            [
+                result.source().to_dotted(),
+                meta("status"),
+                meta("duration_ms"),
+                result.data_keys().count().to_string(),
+            ]
+        })
+        .collect();
+    print_table(&["COMMAND", "STATUS", "DURATION_MS", "RESULTS"], &rows);
+}
+
+fn describe_command(command_type: Option<&str>) -> anyhow::Result<()> {
+    let registry = CommandRegistry::new();
+    let Some(command_type) = command_type else {
+        for command_type in registry.command_types() {
+            println!("{}", command_type);
+        }
+        return Ok(());
+    };
+    let command = registry.get(command_type).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Unknown command type '{}', available types are: {}",
+            command_type,
+            registry.command_types().join(", ")
+        )
+    })?;
+
+    println!("{}\n\nAttributes:", command.command_type());
+    let rows: Vec<[String; 4]> = command
+        .attributes()
+        .iter()
+        .map(|attr| {
+            [
+                attr.name().to_string(),
+                describe_type(attr.type_def()),
+                if attr.is_required() { "yes" } else { "no" }.to_string(),
+                attr.hint().map(|h| h.to_string()).unwrap_or_default(),
+            ]
+        })
+        .collect();
+    print_table(&["NAME", "TYPE", "REQUIRED", "DESCRIPTION"], &rows);
+
+    println!("\nResults:");
+    let rows: Vec<[String; 4]> = command
+        .results()
+        .iter()
+        .map(|result| {
+            let (name, hint) = match result {
+                ResultSpec::Field { name, hint, .. } => {
+                    (name.to_string(), hint.map(|h| h.to_string()))
+                }
+                // One result per element of the attribute, named by the element's literal field
+                ResultSpec::DerivedFromSingleAttribute {
+                    attribute,
+                    name_field,
+                    ..
+                } => (
+                    format!("<{}.{}>", attribute, name_field.name()),
+                    Some(format!("One per entry of '{}'", attribute)),
+                ),
+            };
+            [
+                name,
+                result
+                    .type_def()
+                    .map(describe_type)
+                    .unwrap_or_else(|| "inferred".to_string()),
+                match result.kind() {
+                    ResultKind::Data => "data",
+                    ResultKind::Meta => "meta",
+                }
+                .to_string(),
+                hint.unwrap_or_default(),
+            ]
+        })
+        .collect();
+    print_table(&["NAME", "TYPE", "KIND", "DESCRIPTION"], &rows);
+    Ok(())
+}
+
+fn describe_type(ty: &TypeDef<&'static str>) -> String {
+    match ty {
+        TypeDef::Scalar(scalar) => format!("{:?}", scalar).to_lowercase(),
+        TypeDef::Tabular => "tabular".to_string(),
+        TypeDef::ArrayOf(inner) => format!("array<{}>", describe_type(inner)),
+        TypeDef::ObjectOf { .. } => "object".to_string(),
+    }
+}
+
+fn print_table<const N: usize>(headers: &[&str; N], rows: &[[String; N]]) {
+    let mut widths = headers.map(str::len);
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let format_row = |cells: [&str; N]| {
+        cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!("{}", format_row(*headers));
+    for row in rows {
+        println!("{}", format_row(row.each_ref().map(String::as_str)));
+    }
+}
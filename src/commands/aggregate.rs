@@ -1,4 +1,5 @@
 use crate::imports::*;
+use crate::values::tabular::count_rows;
 use polars::prelude::*;
 
 static AGGREGATECOMMAND_SPEC: CommandSchema = LazyLock::new(|| {
@@ -97,14 +98,46 @@ impl Executable for AggregateCommand {
     async fn execute(&self, context: &ExecutionContext, output_prefix: &StorePath) -> Result<()> {
         let source_path = StorePath::from_dotted(&self.source);
 
-        let df = context.tabular().get(&source_path).await?.ok_or_else(|| {
-            anyhow::anyhow!("Source '{}' not found in tabular store", self.source)
-        })?;
+        let lf = context
+            .tabular()
+            .get_lazy(&source_path)
+            .await?
+            .ok_or_else(|| {
+                anyhow::anyhow!("Source '{}' not found in tabular store", self.source)
+            })?;
+
+        // Only the aggregated columns are read, which matters when the source is a lazy scan. Missing
+        // columns are left out here and reported by compute_aggregation.
+        let mut columns: Vec<String> = Vec::new();
+        for column in self
+            .aggregations
+            .iter()
+            .filter_map(|agg| agg.column.as_ref())
+        {
+            if !columns.contains(column) {
+                columns.push(column.clone());
+            }
+        }
+        let (df, rows) = tokio::task::spawn_blocking(move || -> Result<(TabularValue, u64)> {
+            let mut lf = lf;
+            let schema = lf.collect_schema()?;
+            columns.retain(|column| schema.contains(column));
+            if columns.is_empty() {
+                return Ok((TabularValue::empty(), count_rows(lf)?));
+            }
+            let df = lf
+                .select(columns.iter().map(|c| col(c.as_str())).collect::<Vec<_>>())
+                .collect()?;
+            let rows = df.height() as u64;
+            Ok((df, rows))
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("Task join error: {}", e))??;
 
         let out = InsertBatch::new(context, output_prefix);
 
         for agg in &self.aggregations {
-            let value = compute_aggregation(&df, agg)?;
+            let value = compute_aggregation(&df, rows, agg)?;
             out.scalar(&agg.name, value).await?;
         }
 
@@ -112,9 +145,9 @@ impl Executable for AggregateCommand {
     }
 }
 
-fn compute_aggregation(df: &TabularValue, agg: &AggregationSpec) -> Result<ScalarValue> {
+fn compute_aggregation(df: &TabularValue, rows: u64, agg: &AggregationSpec) -> Result<ScalarValue> {
     match agg.op {
-        AggregateOp::Count => Ok(to_scalar::i64(df.height() as i64)),
+        AggregateOp::Count => Ok(to_scalar::i64(rows as i64)),
 
         AggregateOp::NUnique => {
             let col_name = agg.column.as_ref().ok_or_else(|| {
@@ -284,6 +317,9 @@ impl Descriptor for AggregateCommand {
     fn command_results() -> &'static [ResultSpec<&'static str>] {
         &AGGREGATECOMMAND_SPEC.1
     }
+    fn cacheable() -> bool {
+        true
+    }
 }
 
 impl FromAttributes for AggregateCommand {
@@ -0,0 +1,205 @@
+use crate::imports::*;
+use crate::pipeline::results::{TabularFormat, TabularWriteOptions, write_tabular_with};
+use polars::prelude::ParquetCompression;
+
+static EXPORTCOMMAND_SPEC: CommandSchema = LazyLock::new(|| {
+    CommandSpecBuilder::new()
+        .attribute(
+            AttributeSpecBuilder::new("source", TypeDef::Scalar(ScalarType::String))
+                .required()
+                .hint("Path to tabular data in store (e.g., 'query.results.data')")
+                .reference(ReferenceKind::StorePath)
+                .build(),
+        )
+        .attribute(
+            AttributeSpecBuilder::new("path", TypeDef::Scalar(ScalarType::String))
+                .required()
+                .hint("File path to write to, parent directories are created (supports Tera substitution)")
+                .reference(ReferenceKind::StaticTeraTemplate)
+                .build(),
+        )
+        .attribute(
+            AttributeSpecBuilder::new("format", TypeDef::Scalar(ScalarType::String))
+                .hint("csv, json, ndjson or parquet. Defaults to the extension of 'path'")
+                .build(),
+        )
+        .attribute(
+            AttributeSpecBuilder::new("delimiter", TypeDef::Scalar(ScalarType::String))
+                .hint("Single character separating CSV fields")
+                .default_value(ScalarValue::String(",".to_string()))
+                .build(),
+        )
+        .attribute(
+            AttributeSpecBuilder::new("header", TypeDef::Scalar(ScalarType::Bool))
+                .hint("Whether CSV output starts with a header row")
+                .default_value(ScalarValue::Bool(true))
+                .build(),
+        )
+        .attribute(
+            AttributeSpecBuilder::new("compression", TypeDef::Scalar(ScalarType::String))
+                .hint("Parquet compression: uncompressed, snappy, gzip, lz4, brotli or zstd (the default)")
+                .build(),
+        )
+        .fixed_result(
+            "rows",
+            TypeDef::Scalar(ScalarType::Number),
+            Some("Number of rows written"),
+            ResultKind::Meta,
+        )
+        .fixed_result(
+            "size",
+            TypeDef::Scalar(ScalarType::Number),
+            Some("Size in bytes of the written file"),
+            ResultKind::Meta,
+        )
+        .fixed_result(
+            "path",
+            TypeDef::Scalar(ScalarType::String),
+            Some("Path of the written file"),
+            ResultKind::Data,
+        )
+        .build()
+});
+
+pub struct ExportCommand {
+    source: String,
+    path: PathBuf,
+    format: TabularFormat,
+    options: TabularWriteOptions,
+}
+
+#[async_trait::async_trait]
+impl Executable for ExportCommand {
+    async fn execute(&self, context: &ExecutionContext, output_prefix: &StorePath) -> Result<()> {
+        let source_path = StorePath::from_dotted(&self.source);
+        let df = context.tabular().get(&source_path).await?.ok_or_else(|| {
+            anyhow::anyhow!("Source '{}' not found in tabular store", self.source)
+        })?;
+
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            tokio::fs::create_dir_all(parent).await.map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to create output directory '{}': {}",
+                    parent.display(),
+                    e
+                )
+            })?;
+        }
+
+        let rows = df.height() as u64;
+        let path = self.path.clone();
+        let format = self.format.clone();
+        let options = self.options.clone();
+        tokio::task::spawn_blocking(move || write_tabular_with(&df, &path, &format, &options))
+            .await
+            .map_err(|e| anyhow::anyhow!("Task join error: {}", e))?
+            .with_context(|| format!("Failed to export '{}'", self.path.display()))?;
+        let size = tokio::fs::metadata(&self.path).await?.len();
+
+        tracing::debug!(
+            source = %self.source,
+            path = %self.path.display(),
+            format = %self.format,
+            rows = rows,
+            size = size,
+            "Exported tabular data"
+        );
+        let out = InsertBatch::new(context, output_prefix);
+        out.u64("rows", rows).await?;
+        out.u64("size", size).await?;
+        out.string("path", self.path.display().to_string()).await?;
+
+        Ok(())
+    }
+}
+
+impl Descriptor for ExportCommand {
+    fn command_type() -> &'static str {
+        "ExportCommand"
+    }
+    fn command_attributes() -> &'static [AttributeSpec<&'static str>] {
+        &EXPORTCOMMAND_SPEC.0
+    }
+    fn command_results() -> &'static [ResultSpec<&'static str>] {
+        &EXPORTCOMMAND_SPEC.1
+    }
+}
+
+impl FromAttributes for ExportCommand {
+    fn from_attributes(attrs: &Attributes) -> Result<Self> {
+        let source = attrs.get_required_string("source")?;
+        let path = PathBuf::from(attrs.get_required_string("path")?);
+
+        let format: TabularFormat = match attrs.get_optional_string("format") {
+            Some(format) => format.parse()?,
+            None => path
+                .extension()
+                .and_then(|e| e.to_str())
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "'format' is required when 'path' has no extension: {}",
+                        path.display()
+                    )
+                })?
+                .to_ascii_lowercase()
+                .parse()
+                .context("Set 'format' to write a file with this extension")?,
+        };
+
+        let delimiter = attrs.get_optional_string("delimiter");
+        let header = attrs.get_optional_bool("header");
+        let compression = attrs.get_optional_string("compression");
+        if delimiter.is_some() {
+            format.check_option("delimiter", TabularFormat::Csv, "exports")?;
+        }
+        if header.is_some() {
+            format.check_option("header", TabularFormat::Csv, "exports")?;
+        }
+        if compression.is_some() {
+            format.check_option("compression", TabularFormat::Parquet, "exports")?;
+        }
+
+        let mut options = TabularWriteOptions::default();
+        if let Some(delimiter) = delimiter {
+            options.delimiter = match delimiter.as_bytes() {
+                [byte] => *byte,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "'delimiter' must be a single ASCII character, got '{}'",
+                        delimiter
+                    ));
+                }
+            };
+        }
+        if let Some(header) = header {
+            options.header = header;
+        }
+        if let Some(compression) = compression {
+            options.compression = parse_compression(&compression)?;
+        }
+
+        Ok(ExportCommand {
+            source,
+            path,
+            format,
+            options,
+        })
+    }
+}
+
+fn parse_compression(name: &str) -> Result<ParquetCompression> {
+    match name.to_lowercase().as_str() {
+        "uncompressed" | "none" => Ok(ParquetCompression::Uncompressed),
+        "snappy" => Ok(ParquetCompression::Snappy),
+        "gzip" => Ok(ParquetCompression::Gzip(None)),
+        "lz4" => Ok(ParquetCompression::Lz4Raw),
+        "brotli" => Ok(ParquetCompression::Brotli(None)),
+        "zstd" => Ok(ParquetCompression::Zstd(None)),
+        other => Err(anyhow::anyhow!(
+            "Unknown compression '{}', expected uncompressed, snappy, gzip, lz4, brotli or zstd",
+            other
+        )),
+    }
+}
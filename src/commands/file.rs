@@ -1,11 +1,37 @@
 use crate::imports::*;
-use polars::prelude::SerReader;
+use crate::pipeline::results::TabularFormat;
+use polars::io::mmap::MmapBytesReader;
+use polars::prelude::{
+    Column, CsvEncoding, CsvReadOptions, DataFrame, DataType, Field, IdxSize, IntoLazy, JsonFormat,
+    JsonReader, LazyCsvReader, LazyFileListReader, LazyFrame, LazyJsonLineReader, NULL, NullValues,
+    ParquetReader, PlPath, PlSmallStr, ScanArgsParquet, Schema, SerReader, TimeUnit, UnionArgs,
+    col, concat_lf_diagonal, lit,
+};
+use std::io::Read;
+use std::path::Path;
+
+/*
+    Consts:
+    * SOURCE_FILE_COLUMN - Column holding the path each row was read from, added with `source_file: true`
+    * CSV_ONLY_OPTIONS - Reader options rejected for formats other than CSV
+    * HIVE_DEFAULT_PARTITION - Directory value hive writers use for a null partition key
+*/
+const SOURCE_FILE_COLUMN: &str = "source_file";
+const CSV_ONLY_OPTIONS: [&str; 6] = [
+    "delimiter",
+    "has_header",
+    "quote_char",
+    "skip_rows",
+    "null_values",
+    "encoding",
+];
+const HIVE_DEFAULT_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
 
 static FILECOMMAND_SPEC: CommandSchema = LazyLock::new(|| {
     let (pending, fields) = CommandSpecBuilder::new().array_of_objects(
         "files",
         true,
-        Some("Array of {name, file, format} objects to read"),
+        Some("Array of {name, file or glob, format} objects to read, with optional reader options"),
     );
 
     let (fields, name_ref) = fields.add_literal(
@@ -17,17 +43,90 @@ static FILECOMMAND_SPEC: CommandSchema = LazyLock::new(|| {
     let fields = fields.add_template(
         "file",
         TypeDef::Scalar(ScalarType::String),
-        true,
-        Some("Path to the file to read (supports tera templates)"),
+        false,
+        Some("Path to the file to read, or a directory of hive-partitioned Parquet files (supports tera templates)"),
+        ReferenceKind::StaticTeraTemplate,
+    );
+    let fields = fields.add_template(
+        "glob",
+        TypeDef::Scalar(ScalarType::String),
+        false,
+        Some("Glob pattern, every matching file is read into one DataFrame. Set this or 'file' (supports tera templates)"),
         ReferenceKind::StaticTeraTemplate,
     );
     let fields = fields.add_template(
         "format",
         TypeDef::Scalar(ScalarType::String),
         true,
-        Some("Format of the file: csv, json, or parquet (supports tera templates)"),
+        Some("Format of the file: csv, json, ndjson, parquet or auto to use the extension (supports tera templates)"),
         ReferenceKind::StaticTeraTemplate,
     );
+    let (fields, _) = fields.add_literal(
+        "delimiter",
+        TypeDef::Scalar(ScalarType::String),
+        false,
+        Some("CSV only. Single character separating fields, defaults to ','"),
+    );
+    let (fields, _) = fields.add_literal(
+        "has_header",
+        TypeDef::Scalar(ScalarType::Bool),
+        false,
+        Some("CSV only. Whether the first row holds column names, defaults to true"),
+    );
+    let (fields, _) = fields.add_literal(
+        "quote_char",
+        TypeDef::Scalar(ScalarType::String),
+        false,
+        Some("CSV only. Single character quoting fields, defaults to '\"'. Empty disables quoting"),
+    );
+    let (fields, _) = fields.add_literal(
+        "skip_rows",
+        TypeDef::Scalar(ScalarType::Number),
+        false,
+        Some("CSV only. Number of rows to skip before the header or first row"),
+    );
+    let (fields, _) = fields.add_literal(
+        "null_values",
+        TypeDef::ArrayOf(Box::new(TypeDef::Scalar(ScalarType::String))),
+        false,
+        Some("CSV only. Values read as null, e.g. ['NA', '-']"),
+    );
+    let (fields, _) = fields.add_literal(
+        "encoding",
+        TypeDef::Scalar(ScalarType::String),
+        false,
+        Some("CSV only. utf8 (the default), utf8-lossy or latin1"),
+    );
+    let (fields, _) = fields.add_literal(
+        "dtypes",
+        TypeDef::Scalar(ScalarType::Object),
+        false,
+        Some("Column name to type: string, int32, int64, uint64, float32, float64, bool, date or datetime"),
+    );
+    let (fields, _) = fields.add_literal(
+        "columns",
+        TypeDef::ArrayOf(Box::new(TypeDef::Scalar(ScalarType::String))),
+        false,
+        Some("Columns to read, in this order. Defaults to all columns"),
+    );
+    let (fields, _) = fields.add_literal(
+        "n_rows",
+        TypeDef::Scalar(ScalarType::Number),
+        false,
+        Some("Maximum number of rows to read"),
+    );
+    let (fields, _) = fields.add_literal(
+        "source_file",
+        TypeDef::Scalar(ScalarType::Bool),
+        false,
+        Some("Adds a source_file column holding the path each row was read from"),
+    );
+    let (fields, _) = fields.add_literal(
+        "lazy",
+        TypeDef::Scalar(ScalarType::Bool),
+        false,
+        Some("Scan csv, ndjson or parquet files instead of reading them, rows are only loaded when needed"),
+    );
 
     pending
         .finalise_attribute(fields)
@@ -40,7 +139,7 @@ static FILECOMMAND_SPEC: CommandSchema = LazyLock::new(|| {
         .fixed_result(
             "total_rows",
             TypeDef::Scalar(ScalarType::Number),
-            Some("The total number of rows across all loaded files."),
+            Some("The total number of rows across all loaded files, null if an entry is lazy."),
             ResultKind::Meta,
         )
         .fixed_result(
@@ -49,101 +148,186 @@ static FILECOMMAND_SPEC: CommandSchema = LazyLock::new(|| {
             Some("The total size in bytes across all loaded files."),
             ResultKind::Meta,
         )
+        .fixed_result(
+            "files_matched",
+            TypeDef::Scalar(ScalarType::Number),
+            Some("The total number of files read across all entries."),
+            ResultKind::Meta,
+        )
         .derived_result("files", name_ref, Some(TypeDef::Tabular), ResultKind::Data)
         .build()
 });
 
-struct FileSpec {
+// An entry as configured, its 'file', 'glob' and 'format' templates are rendered when the command runs
+#[derive(Debug, Clone)]
+struct FileEntry {
     name: String,
-    file: PathBuf,
+    source: FileSource,
     format: String,
+    options: ReadOptions,
+}
+
+#[derive(Debug, Clone)]
+struct FileSpec {
+    name: String,
+    source: FileSource,
+    format: Option<TabularFormat>, // None for 'auto', picked per file from its extension
+    options: ReadOptions,
+}
+
+#[derive(Debug, Clone)]
+enum FileSource {
+    File(PathBuf), // A directory is read as hive-partitioned Parquet
+    Glob(String),
+}
+
+enum Loaded {
+    Eager(DataFrame),
+    Lazy {
+        frame: Box<LazyFrame>,
+        sources: Vec<PathBuf>,
+        columns: Vec<String>,
+    },
+}
+
+struct MatchedFile {
+    path: PathBuf,
+    partitions: Vec<(String, Option<String>)>, // key=value directories above a hive-partitioned file
+}
+
+#[derive(Debug, Clone, Default)]
+struct ReadOptions {
+    csv: CsvOptions,
+    csv_only: Vec<&'static str>, // CSV_ONLY_OPTIONS that were set
+    dtypes: Vec<(String, DataType)>,
+    columns: Option<Vec<String>>,
+    n_rows: Option<usize>,
+    source_file: bool,
+    lazy: bool,
+}
+
+#[derive(Debug, Clone)]
+struct CsvOptions {
+    delimiter: u8,
+    has_header: bool,
+    quote_char: Option<u8>,
+    skip_rows: usize,
+    null_values: Option<Vec<String>>,
+    encoding: Encoding,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Utf8,
+    LossyUtf8,
+    Latin1, // Polars only reads UTF-8, these are transcoded before parsing
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: b',',
+            has_header: true,
+            quote_char: Some(b'"'),
+            skip_rows: 0,
+            null_values: None,
+            encoding: Encoding::Utf8,
+        }
+    }
 }
 
 pub struct FileCommand {
-    files: Vec<FileSpec>,
+    files: Vec<FileEntry>,
+}
+
+impl FileCommand {
+    async fn resolve(&self, context: &ExecutionContext) -> Result<Vec<FileSpec>> {
+        let mut specs = Vec::with_capacity(self.files.len());
+        for (i, entry) in self.files.iter().enumerate() {
+            specs.push(
+                entry
+                    .resolve(context)
+                    .await
+                    .context(format!("files[{}]", i))?,
+            );
+        }
+        Ok(specs)
+    }
 }
 
 #[async_trait::async_trait]
 impl Executable for FileCommand {
+    // Glob matches and partitioned directories are expanded, so added or changed files miss the cache
+    async fn input_files(&self, context: &ExecutionContext) -> Result<Vec<PathBuf>> {
+        Ok(self
+            .resolve(context)
+            .await?
+            .iter()
+            .flat_map(|file_spec| match file_spec.matched_files() {
+                Ok(matched) => matched.into_iter().map(|file| file.path).collect(),
+                Err(_) => match &file_spec.source {
+                    FileSource::File(path) => vec![path.clone()],
+                    FileSource::Glob(_) => Vec::new(),
+                },
+            })
+            .collect())
+    }
+
     async fn execute(&self, context: &ExecutionContext, output_prefix: &StorePath) -> Result<()> {
-        let mut total_rows: u64 = 0;
+        let mut total_rows: Option<u64> = Some(0); // Unknown once an entry is lazy
         let mut total_size: u64 = 0;
+        let mut total_matched: u64 = 0;
 
-        for file_spec in self.files.iter() {
-            if !file_spec.file.exists() {
-                tracing::warn!(missing_file = %file_spec.file.display(), "File does not exist");
-                return Err(anyhow::anyhow!(
-                    "File does not exist: {}",
-                    file_spec.file.display()
-                ));
-            }
-            if file_spec.file.is_dir() {
-                tracing::warn!(directory_path = %file_spec.file.display(), "Path is a directory, not a file");
-                return Err(anyhow::anyhow!(
-                    "Path is a directory, not a file: {}",
-                    file_spec.file.display()
-                ));
-            }
-            let metadata = tokio::fs::metadata(&file_spec.file).await?;
-            let file_size = metadata.len();
-
-            let path = file_spec.file.clone();
-            let format = file_spec.format.clone();
-            let df = tokio::task::spawn_blocking(move || -> Result<polars::prelude::DataFrame> {
-                match format.as_str() {
-                    "csv" => polars::prelude::CsvReadOptions::default()
-                        .with_has_header(true)
-                        .try_into_reader_with_file_path(Some(path.clone()))?
-                        .finish()
-                        .map_err(|e| {
-                            anyhow::anyhow!("Failed to read CSV file {}: {}", path.display(), e)
-                        }),
-                    "json" => {
-                        let file = std::fs::File::open(&path)?;
-                        polars::prelude::JsonReader::new(file)
-                            .finish()
-                            .map_err(|e| {
-                                anyhow::anyhow!(
-                                    "Failed to read JSON file {}: {}",
-                                    path.display(),
-                                    e
-                                )
-                            })
-                    }
-                    "parquet" => {
-                        let file = std::fs::File::open(&path)?;
-                        polars::prelude::ParquetReader::new(file)
-                            .finish()
-                            .map_err(|e| {
-                                anyhow::anyhow!(
-                                    "Failed to read Parquet file {}: {}",
-                                    path.display(),
-                                    e
-                                )
-                            })
-                    }
-                    _ => Err(anyhow::anyhow!("Unsupported file format: {}", format)),
-                }
-            })
-            .await
-            .map_err(|e| anyhow::anyhow!("Task join error: {}", e))??;
+        let file_specs = self.resolve(context).await?;
+        for file_spec in file_specs.iter() {
+            let spec = file_spec.clone();
+            let (loaded, files_matched, file_size) =
+                tokio::task::spawn_blocking(move || match spec.options.lazy {
+                    true => spec.scan(),
+                    false => spec.load(),
+                })
+                .await
+                .map_err(|e| anyhow::anyhow!("Task join error: {}", e))??;
 
-            let row_count = df.height() as u64;
-            let column_names: Vec<ScalarValue> = df
-                .get_column_names()
-                .iter()
-                .map(|name| ScalarValue::String(name.to_string()))
-                .collect();
+            let (row_count, column_names) = match &loaded {
+                Loaded::Eager(df) => (
+                    Some(df.height() as u64),
+                    df.get_column_names()
+                        .iter()
+                        .map(|name| ScalarValue::String(name.to_string()))
+                        .collect::<Vec<_>>(),
+                ),
+                Loaded::Lazy { columns, .. } => (
+                    None,
+                    columns.iter().cloned().map(ScalarValue::String).collect(),
+                ),
+            };
 
-            total_rows += row_count;
+            total_rows = total_rows.zip(row_count).map(|(total, rows)| total + rows);
             total_size += file_size;
+            total_matched += files_matched;
 
             let file_prefix = output_prefix.with_segment(&file_spec.name);
             let out = InsertBatch::new(context, &file_prefix);
 
-            out.tabular("data", df).await?;
-            out.u64("rows", row_count).await?;
+            match loaded {
+                Loaded::Eager(df) => out.tabular("data", df).await?,
+                Loaded::Lazy { frame, sources, .. } => {
+                    out.lazy("data", *frame, sources.into()).await?
+                }
+            }
+            match row_count {
+                Some(rows) => out.u64("rows", rows).await?,
+                None => out.null("rows").await?,
+            }
             out.u64("size", file_size).await?;
+            out.u64("files_matched", files_matched).await?;
             out.scalar("columns", ScalarValue::Array(column_names))
                 .await?;
         }
@@ -151,8 +335,12 @@ impl Executable for FileCommand {
         // Insert summary outputs
         let out = InsertBatch::new(context, output_prefix);
         out.i64("count", self.files.len() as i64).await?;
-        out.u64("total_rows", total_rows).await?;
+        match total_rows {
+            Some(rows) => out.u64("total_rows", rows).await?,
+            None => out.null("total_rows").await?,
+        }
         out.u64("total_size", total_size).await?;
+        out.u64("files_matched", total_matched).await?;
 
         Ok(())
     }
@@ -168,6 +356,9 @@ impl Descriptor for FileCommand {
     fn command_results() -> &'static [ResultSpec<&'static str>] {
         &FILECOMMAND_SPEC.1
     }
+    fn cacheable() -> bool {
+        true
+    }
 }
 
 impl FromAttributes for FileCommand {
@@ -181,17 +372,31 @@ impl FromAttributes for FileCommand {
             let name = file_obj
                 .get_required_string("name")
                 .context(format!("files[{}]", i))?;
-            let file = file_obj
-                .get_required_string("file")
-                .context(format!("files[{}]", i))?;
+            let source = match (
+                file_obj.get_optional_string("file"),
+                file_obj.get_optional_string("glob"),
+            ) {
+                (Some(file), None) => FileSource::File(PathBuf::from(file)),
+                (None, Some(glob)) => FileSource::Glob(glob),
+                (Some(_), Some(_)) => {
+                    return Err(anyhow::anyhow!("'file' and 'glob' can't both be set"))
+                        .context(format!("files[{}]", i));
+                }
+                (None, None) => {
+                    return Err(anyhow::anyhow!("One of 'file' or 'glob' is required"))
+                        .context(format!("files[{}]", i));
+                }
+            };
             let format = file_obj
                 .get_required_string("format")
                 .context(format!("files[{}]", i))?;
+            let options = parse_read_options(file_obj).context(format!("files[{}]", i))?;
 
-            files.push(FileSpec {
+            files.push(FileEntry {
                 name,
-                file: PathBuf::from(file),
+                source,
                 format,
+                options,
             });
         }
 
@@ -199,6 +404,615 @@ impl FromAttributes for FileCommand {
     }
 }
 
+impl FileEntry {
+    async fn resolve(&self, context: &ExecutionContext) -> Result<FileSpec> {
+        let source = match &self.source {
+            FileSource::File(path) => FileSource::File(PathBuf::from(
+                context.substitute(path.to_string_lossy()).await?,
+            )),
+            FileSource::Glob(pattern) => FileSource::Glob(context.substitute(pattern).await?),
+        };
+        let format = context.substitute(&self.format).await?;
+        let format = match format.eq_ignore_ascii_case("auto") {
+            true => None,
+            false => Some(format.to_lowercase().parse()?),
+        };
+        if let Some(format) = &format {
+            check_format_options(format, &self.options)?;
+        }
+        Ok(FileSpec {
+            name: self.name.clone(),
+            source,
+            format,
+            options: self.options.clone(),
+        })
+    }
+}
+
+impl FileSpec {
+    // Blocking, run it on spawn_blocking. Returns the frame, the number of files read and their size on disk
+    fn load(&self) -> Result<(Loaded, u64, u64)> {
+        let matched = self.matched_files()?;
+        let mut frames = Vec::with_capacity(matched.len());
+        let mut size = 0;
+        for file in matched.iter() {
+            size += std::fs::metadata(&file.path)?.len();
+            let format = match &self.format {
+                Some(format) => format.clone(),
+                None => detect_format(&file.path)?,
+            };
+            check_format_options(&format, &self.options)
+                .with_context(|| format!("Can't read {}", file.path.display()))?;
+
+            // Partition keys and the source column aren't in the file, only project what is
+            let mut options = self.options.clone();
+            if let Some(columns) = options.columns.as_mut() {
+                columns.retain(|column| {
+                    column != SOURCE_FILE_COLUMN
+                        && !file.partitions.iter().any(|(key, _)| key == column)
+                });
+            }
+            let mut df = read_file(&file.path, &format, &options)?;
+
+            let height = df.height();
+            for (key, value) in file.partitions.iter() {
+                df.with_column(Column::new(
+                    PlSmallStr::from(key.as_str()),
+                    vec![value.as_deref(); height],
+                ))?;
+            }
+            if self.options.source_file {
+                df.with_column(Column::new(
+                    PlSmallStr::from(SOURCE_FILE_COLUMN),
+                    vec![file.path.display().to_string(); height],
+                ))?;
+            }
+            frames.push(df);
+        }
+        tracing::debug!(
+            name = %self.name,
+            files_matched = matched.len(),
+            "Read matched files"
+        );
+
+        let df = concat_frames(frames)?;
+        let df = finish_frame(df, &self.options)
+            .with_context(|| format!("Failed to load '{}'", self.name))?;
+        Ok((Loaded::Eager(df), matched.len() as u64, size))
+    }
+
+    // Like load, but builds a plan over the files. Only the schema is read, the row count is left unknown
+    // since counting would run the plan.
+    fn scan(&self) -> Result<(Loaded, u64, u64)> {
+        let matched = self.matched_files()?;
+        let mut frames = Vec::with_capacity(matched.len());
+        let mut size = 0;
+        for file in matched.iter() {
+            size += std::fs::metadata(&file.path)?.len();
+            let format = match &self.format {
+                Some(format) => format.clone(),
+                None => detect_format(&file.path)?,
+            };
+            check_format_options(&format, &self.options)
+                .with_context(|| format!("Can't read {}", file.path.display()))?;
+
+            let mut lf = scan_file(&file.path, &format, &self.options)
+                .with_context(|| format!("Can't scan {}", file.path.display()))?;
+            for (key, value) in file.partitions.iter() {
+                let value = match value {
+                    Some(value) => lit(value.as_str()),
+                    None => lit(NULL).cast(DataType::String),
+                };
+                lf = lf.with_column(value.alias(key.as_str()));
+            }
+            if self.options.source_file {
+                lf = lf.with_column(lit(file.path.display().to_string()).alias(SOURCE_FILE_COLUMN));
+            }
+            frames.push(lf);
+        }
+
+        let lf = match frames.len() {
+            1 => frames.remove(0),
+            _ => concat_lf_diagonal(
+                frames,
+                UnionArgs {
+                    to_supertypes: true,
+                    diagonal: true,
+                    ..Default::default()
+                },
+            )?,
+        };
+        let mut lf = finish_lazy(lf, &self.options)
+            .with_context(|| format!("Failed to scan '{}'", self.name))?;
+        let columns = lf
+            .collect_schema()?
+            .iter_names()
+            .map(|name| name.to_string())
+            .collect();
+        let files_matched = matched.len() as u64;
+        let loaded = Loaded::Lazy {
+            frame: Box::new(lf),
+            sources: matched.into_iter().map(|file| file.path).collect(),
+            columns,
+        };
+        Ok((loaded, files_matched, size))
+    }
+
+    fn matched_files(&self) -> Result<Vec<MatchedFile>> {
+        match &self.source {
+            FileSource::File(path) if path.is_dir() => {
+                if !matches!(self.format, Some(TabularFormat::Parquet)) {
+                    tracing::warn!(directory_path = %path.display(), "Path is a directory, not a file");
+                    return Err(anyhow::anyhow!(
+                        "Path is a directory, not a file: {}. Set 'format' to parquet to read hive-partitioned \
+                         files, or use 'glob'",
+                        path.display()
+                    ));
+                }
+                let mut files = Vec::new();
+                collect_partitioned(path, &[], &mut files)?;
+                if files.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "No Parquet files found under {}",
+                        path.display()
+                    ));
+                }
+                Ok(files)
+            }
+            FileSource::File(path) => {
+                if !path.exists() {
+                    tracing::warn!(missing_file = %path.display(), "File does not exist");
+                    return Err(anyhow::anyhow!("File does not exist: {}", path.display()));
+                }
+                Ok(vec![MatchedFile {
+                    path: path.clone(),
+                    partitions: Vec::new(),
+                }])
+            }
+            FileSource::Glob(pattern) => {
+                // glob yields paths in alphabetical order, so rows are concatenated in a stable order
+                let mut files = Vec::new();
+                for entry in glob::glob(pattern)
+                    .with_context(|| format!("Invalid glob pattern '{}'", pattern))?
+                {
+                    let path = entry?;
+                    if path.is_file() {
+                        files.push(MatchedFile {
+                            path,
+                            partitions: Vec::new(),
+                        });
+                    }
+                }
+                if files.is_empty() {
+                    return Err(anyhow::anyhow!("No files match '{}'", pattern));
+                }
+                Ok(files)
+            }
+        }
+    }
+}
+
+// Hidden and underscore-prefixed entries (_SUCCESS, .crc files) are bookkeeping from the writer, not data
+fn collect_partitioned(
+    dir: &Path,
+    partitions: &[(String, Option<String>)],
+    files: &mut Vec<MatchedFile>,
+) -> Result<()> {
+    let mut entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') || name.starts_with('_') {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            let mut nested = partitions.to_vec();
+            if let Some((key, value)) = name.split_once('=') {
+                let value = (value != HIVE_DEFAULT_PARTITION).then(|| value.to_string());
+                nested.push((key.to_string(), value));
+            }
+            collect_partitioned(&path, &nested, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("parquet"))
+        {
+            files.push(MatchedFile {
+                path,
+                partitions: partitions.to_vec(),
+            });
+        }
+    }
+    Ok(())
+}
+
+// Files with different columns are unioned, missing values are null and clashing types use a common supertype
+fn concat_frames(mut frames: Vec<DataFrame>) -> Result<DataFrame> {
+    if frames.len() == 1 {
+        return Ok(frames.remove(0));
+    }
+    let frames: Vec<_> = frames.into_iter().map(IntoLazy::lazy).collect();
+    let args = UnionArgs {
+        to_supertypes: true,
+        diagonal: true,
+        ..Default::default()
+    };
+    concat_lf_diagonal(frames, args)?
+        .collect()
+        .context("Failed to combine the matched files")
+}
+
+// Projection, row limit and dtypes over the combined frame. Readers that can apply them while reading already
+// have, so here they only cover JSON, partition keys and the source column.
+fn finish_frame(df: DataFrame, options: &ReadOptions) -> Result<DataFrame> {
+    let df = match &options.columns {
+        Some(columns) => {
+            let mut columns = columns.clone();
+            if options.source_file && !columns.iter().any(|column| column == SOURCE_FILE_COLUMN) {
+                columns.push(SOURCE_FILE_COLUMN.to_string());
+            }
+            df.select(columns)?
+        }
+        None => df,
+    };
+    // head(None) would cut the frame to 10 rows
+    let df = match options.n_rows {
+        Some(n_rows) => df.head(Some(n_rows)),
+        None => df,
+    };
+    cast_columns(df, &options.dtypes)
+}
+
+// finish_frame for a plan. Names are checked up front so mistakes fail here rather than when collected.
+fn finish_lazy(mut lf: LazyFrame, options: &ReadOptions) -> Result<LazyFrame> {
+    let schema = lf.collect_schema()?;
+    if let Some(columns) = &options.columns {
+        let mut columns = columns.clone();
+        if options.source_file && !columns.iter().any(|column| column == SOURCE_FILE_COLUMN) {
+            columns.push(SOURCE_FILE_COLUMN.to_string());
+        }
+        if let Some(missing) = columns.iter().find(|column| !schema.contains(column)) {
+            return Err(anyhow::anyhow!(
+                "'columns' names column '{}', which wasn't read",
+                missing
+            ));
+        }
+        lf = lf.select(
+            columns
+                .iter()
+                .map(|column| col(column.as_str()))
+                .collect::<Vec<_>>(),
+        );
+    }
+    if let Some(n_rows) = options.n_rows {
+        lf = lf.limit(n_rows as IdxSize);
+    }
+    let schema = lf.collect_schema()?;
+    for (column, dtype) in options.dtypes.iter() {
+        if !schema.contains(column) {
+            return Err(anyhow::anyhow!(
+                "'dtypes' names column '{}', which wasn't read",
+                column
+            ));
+        }
+        lf = lf.with_column(col(column.as_str()).cast(dtype.clone()));
+    }
+    Ok(lf)
+}
+
+fn check_format_options(format: &TabularFormat, options: &ReadOptions) -> Result<()> {
+    match options.csv_only.first() {
+        Some(option) => format.check_option(option, TabularFormat::Csv, "files"),
+        None => Ok(()),
+    }
+}
+
+fn parse_read_options(file_obj: &tera::Map<String, ScalarValue>) -> Result<ReadOptions> {
+    let mut options = ReadOptions {
+        csv_only: CSV_ONLY_OPTIONS
+            .into_iter()
+            .filter(|option| file_obj.contains_key(*option))
+            .collect(),
+        ..Default::default()
+    };
+
+    if let Some(delimiter) = file_obj.get_optional_string("delimiter") {
+        options.csv.delimiter = single_byte("delimiter", &delimiter)?;
+    }
+    if let Some(has_header) = file_obj.get_optional_bool("has_header") {
+        options.csv.has_header = has_header;
+    }
+    if let Some(quote_char) = file_obj.get_optional_string("quote_char") {
+        options.csv.quote_char = match quote_char.as_str() {
+            "" => None,
+            quote_char => Some(single_byte("quote_char", quote_char)?),
+        };
+    }
+    if let Some(skip_rows) = file_obj.get("skip_rows") {
+        options.csv.skip_rows = as_count("skip_rows", skip_rows)?;
+    }
+    if let Some(null_values) = file_obj.get("null_values") {
+        options.csv.null_values = Some(as_strings("null_values", null_values)?);
+    }
+    if let Some(encoding) = file_obj.get_optional_string("encoding") {
+        options.csv.encoding = match encoding.to_lowercase().as_str() {
+            "utf8" | "utf-8" => Encoding::Utf8,
+            "utf8-lossy" | "utf-8-lossy" => Encoding::LossyUtf8,
+            "latin1" | "latin-1" | "iso-8859-1" => Encoding::Latin1,
+            other => {
+                return Err(anyhow::anyhow!(
+                    "Unknown encoding '{}', expected utf8, utf8-lossy or latin1",
+                    other
+                ));
+            }
+        };
+    }
+
+    if let Some(dtypes) = file_obj.get("dtypes") {
+        for (column, dtype) in dtypes.as_object_or_err("dtypes")? {
+            let dtype = dtype.as_str_or_err(&format!("dtypes.{}", column))?;
+            options.dtypes.push((column.clone(), parse_dtype(dtype)?));
+        }
+    }
+    if let Some(columns) = file_obj.get("columns") {
+        options.columns = Some(as_strings("columns", columns)?);
+    }
+    if let Some(n_rows) = file_obj.get("n_rows") {
+        options.n_rows = Some(as_count("n_rows", n_rows)?);
+    }
+    if let Some(source_file) = file_obj.get_optional_bool("source_file") {
+        options.source_file = source_file;
+    }
+    if let Some(lazy) = file_obj.get_optional_bool("lazy") {
+        options.lazy = lazy;
+    }
+
+    Ok(options)
+}
+
+fn single_byte(option: &str, value: &str) -> Result<u8> {
+    match value.as_bytes() {
+        [byte] => Ok(*byte),
+        _ => Err(anyhow::anyhow!(
+            "'{}' must be a single ASCII character, got '{}'",
+            option,
+            value
+        )),
+    }
+}
+
+fn as_count(option: &str, value: &ScalarValue) -> Result<usize> {
+    value
+        .as_u64()
+        .map(|count| count as usize)
+        .ok_or_else(|| anyhow::anyhow!("'{}' must be a non-negative integer", option))
+}
+
+fn as_strings(option: &str, value: &ScalarValue) -> Result<Vec<String>> {
+    value
+        .as_array_or_err(option)?
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            item.as_str_or_err(&format!("{}[{}]", option, i))
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+fn parse_dtype(name: &str) -> Result<DataType> {
+    match name.to_lowercase().as_str() {
+        "string" | "str" | "utf8" => Ok(DataType::String),
+        "int32" | "i32" => Ok(DataType::Int32),
+        "int64" | "i64" | "int" => Ok(DataType::Int64),
+        "uint64" | "u64" => Ok(DataType::UInt64),
+        "float32" | "f32" => Ok(DataType::Float32),
+        "float64" | "f64" | "float" => Ok(DataType::Float64),
+        "bool" | "boolean" => Ok(DataType::Boolean),
+        "date" => Ok(DataType::Date),
+        "datetime" => Ok(DataType::Datetime(TimeUnit::Microseconds, None)),
+        other => Err(anyhow::anyhow!(
+            "Unknown dtype '{}', expected string, int32, int64, uint64, float32, float64, bool, date or datetime",
+            other
+        )),
+    }
+}
+
+// For 'auto', the format is the extension under any compression extension (users.csv.gz -> csv)
+fn detect_format(path: &Path) -> Result<TabularFormat> {
+    let name = match Compression::from_path(path) {
+        Some(_) => path.file_stem().map(Path::new).unwrap_or(path),
+        None => path,
+    };
+    name.extension()
+        .and_then(|e| e.to_str())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Can't detect the format of '{}' without an extension",
+                path.display()
+            )
+        })?
+        .to_ascii_lowercase()
+        .parse()
+        .context("Set 'format' to read a file with this extension")
+}
+
+impl Compression {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "gz" | "gzip" => Some(Compression::Gzip),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+// Compressed files are decompressed into memory, the readers need to seek
+fn open_input(path: &Path) -> Result<Box<dyn MmapBytesReader>> {
+    let file = std::fs::File::open(path)?;
+    let Some(compression) = Compression::from_path(path) else {
+        return Ok(Box::new(file));
+    };
+    let mut bytes = Vec::new();
+    match compression {
+        Compression::Gzip => flate2::read::MultiGzDecoder::new(file).read_to_end(&mut bytes),
+        Compression::Zstd => zstd::stream::read::Decoder::new(file)?.read_to_end(&mut bytes),
+    }
+    .with_context(|| format!("Failed to decompress {:?} file", compression))?;
+    Ok(Box::new(std::io::Cursor::new(bytes)))
+}
+
+// Pushes columns and n_rows into the CSV and Parquet readers, finish_frame applies the rest
+fn read_file(path: &Path, format: &TabularFormat, options: &ReadOptions) -> Result<DataFrame> {
+    let df = match format {
+        TabularFormat::Csv => read_csv(path, options)
+            .map_err(|e| anyhow::anyhow!("Failed to read CSV file {}: {}", path.display(), e))?,
+        TabularFormat::Json | TabularFormat::Ndjson => {
+            let json_format = match format {
+                TabularFormat::Ndjson => JsonFormat::JsonLines,
+                _ => JsonFormat::Json,
+            };
+            JsonReader::new(open_input(path)?)
+                .with_json_format(json_format)
+                .finish()
+                .map_err(|e| {
+                    anyhow::anyhow!(
+                        "Failed to read {} file {}: {}",
+                        format.extension().to_uppercase(),
+                        path.display(),
+                        e
+                    )
+                })?
+        }
+        TabularFormat::Parquet => ParquetReader::new(open_input(path)?)
+            .with_columns(options.columns.clone())
+            .with_slice(options.n_rows.map(|n_rows| (0, n_rows)))
+            .finish()
+            .map_err(|e| {
+                anyhow::anyhow!("Failed to read Parquet file {}: {}", path.display(), e)
+            })?,
+    };
+    Ok(df)
+}
+
+// Polars scans files in place, anything that has to be converted in memory first can't be lazy
+fn scan_file(path: &Path, format: &TabularFormat, options: &ReadOptions) -> Result<LazyFrame> {
+    if Compression::from_path(path).is_some() {
+        return Err(anyhow::anyhow!(
+            "'lazy' can't scan compressed files, decompress them or drop 'lazy'"
+        ));
+    }
+    let source = PlPath::Local(Arc::from(path));
+    let lf = match format {
+        TabularFormat::Csv => {
+            let csv = &options.csv;
+            let encoding = match csv.encoding {
+                Encoding::Utf8 => CsvEncoding::Utf8,
+                Encoding::LossyUtf8 => CsvEncoding::LossyUtf8,
+                Encoding::Latin1 => {
+                    return Err(anyhow::anyhow!("'lazy' can't scan latin1 files"));
+                }
+            };
+            let dtype_overwrite = (!options.dtypes.is_empty()).then(|| {
+                Arc::new(Schema::from_iter(options.dtypes.iter().map(
+                    |(column, dtype)| Field::new(PlSmallStr::from(column.as_str()), dtype.clone()),
+                )))
+            });
+            LazyCsvReader::new(source)
+                .with_has_header(csv.has_header)
+                .with_separator(csv.delimiter)
+                .with_quote_char(csv.quote_char)
+                .with_skip_rows(csv.skip_rows)
+                .with_null_values(csv.null_values.clone().map(|values| {
+                    NullValues::AllColumns(values.into_iter().map(PlSmallStr::from).collect())
+                }))
+                .with_encoding(encoding)
+                .with_n_rows(options.n_rows)
+                .with_dtype_overwrite(dtype_overwrite)
+                .finish()?
+        }
+        TabularFormat::Ndjson => LazyJsonLineReader::new(source)
+            .with_n_rows(options.n_rows)
+            .finish()?,
+        TabularFormat::Parquet => LazyFrame::scan_parquet(
+            source,
+            ScanArgsParquet {
+                n_rows: options.n_rows,
+                ..Default::default()
+            },
+        )?,
+        TabularFormat::Json => {
+            return Err(anyhow::anyhow!(
+                "'lazy' needs csv, ndjson or parquet, json arrays can't be scanned"
+            ));
+        }
+    };
+    Ok(lf)
+}
+
+fn read_csv(path: &Path, options: &ReadOptions) -> Result<DataFrame> {
+    let csv = options.csv.clone();
+    let encoding = match csv.encoding {
+        Encoding::LossyUtf8 => CsvEncoding::LossyUtf8,
+        Encoding::Utf8 | Encoding::Latin1 => CsvEncoding::Utf8,
+    };
+    // Parsing straight to the requested type keeps values like zip codes intact
+    let schema_overwrite = (!options.dtypes.is_empty()).then(|| {
+        Arc::new(Schema::from_iter(options.dtypes.iter().map(
+            |(column, dtype)| Field::new(PlSmallStr::from(column.as_str()), dtype.clone()),
+        )))
+    });
+    let read_options = CsvReadOptions::default()
+        .with_has_header(csv.has_header)
+        .with_skip_rows(csv.skip_rows)
+        .with_n_rows(options.n_rows)
+        .with_columns(options.columns.as_ref().map(|columns| {
+            columns
+                .iter()
+                .map(|column| PlSmallStr::from(column.as_str()))
+                .collect()
+        }))
+        .with_schema_overwrite(schema_overwrite)
+        .map_parse_options(|parse| {
+            parse
+                .with_separator(csv.delimiter)
+                .with_quote_char(csv.quote_char)
+                .with_null_values(csv.null_values.clone().map(|values| {
+                    NullValues::AllColumns(values.into_iter().map(PlSmallStr::from).collect())
+                }))
+                .with_encoding(encoding)
+        });
+
+    let df = if csv.encoding == Encoding::Latin1 {
+        // Every Latin-1 byte is the Unicode code point of the same value
+        let mut bytes = Vec::new();
+        open_input(path)?.read_to_end(&mut bytes)?;
+        let text: String = bytes.into_iter().map(char::from).collect();
+        read_options
+            .into_reader_with_file_handle(std::io::Cursor::new(text.into_bytes()))
+            .finish()?
+    } else {
+        read_options
+            .into_reader_with_file_handle(open_input(path)?)
+            .finish()?
+    };
+    Ok(df)
+}
+
+// CSVs are already parsed to their dtypes, casting them again is a no-op
+fn cast_columns(mut df: DataFrame, dtypes: &[(String, DataType)]) -> Result<DataFrame> {
+    for (column, dtype) in dtypes {
+        let cast = df
+            .column(column)
+            .map_err(|_| anyhow::anyhow!("'dtypes' names column '{}', which wasn't read", column))?
+            .cast(dtype)?;
+        df.with_column(cast)?;
+    }
+    Ok(df)
+}
+
 #[cfg(test)]
 mod tests {
     // Going to redo these.
@@ -1,5 +1,6 @@
 pub mod aggregate;
 pub mod condition;
+pub mod export;
 pub mod file;
 pub mod sql;
 pub mod template;
@@ -1,5 +1,5 @@
 use crate::imports::*;
-use polars::prelude::IntoLazy;
+use polars::prelude::LazyFrame;
 
 static SQLCOMMAND_SPEC: CommandSchema = LazyLock::new(|| {
     let (pending, fields) = CommandSpecBuilder::new().array_of_objects(
@@ -40,7 +40,7 @@ static SQLCOMMAND_SPEC: CommandSchema = LazyLock::new(|| {
         .fixed_result(
             "rows",
             TypeDef::Scalar(ScalarType::Number),
-            Some("Number of rows in the result"),
+            Some("Number of rows in the result, null when a lazy input keeps it lazy"),
             ResultKind::Meta,
         )
         .fixed_result(
@@ -57,6 +57,11 @@ struct TableMapping {
     source: String,
 }
 
+enum SqlOutput {
+    Eager(TabularValue),
+    Lazy(Box<LazyFrame>),
+}
+
 pub struct SqlCommand {
     tables: Vec<TableMapping>,
     query: String,
@@ -65,59 +70,102 @@ pub struct SqlCommand {
 #[async_trait::async_trait]
 impl Executable for SqlCommand {
     async fn execute(&self, context: &ExecutionContext, output_prefix: &StorePath) -> Result<()> {
-        // Collect DataFrames from the tabular store
-        let mut table_data: Vec<(String, TabularValue)> = Vec::with_capacity(self.tables.len());
+        // Tables are registered as LazyFrames, so lazy sources are never collected here
+        let mut table_data: Vec<(String, LazyFrame)> = Vec::with_capacity(self.tables.len());
+        let mut lazy_sources: Option<LazySources> = None; // What the lazy inputs read, if any
+        let mut eager_inputs: Vec<TabularValue> = Vec::new();
         for table in &self.tables {
             let source_path = StorePath::from_dotted(&table.source);
-            let df = context.tabular().get(&source_path).await?.ok_or_else(|| {
-                anyhow::anyhow!("Table source '{}' not found in tabular store", table.source)
-            })?;
-            table_data.push((table.name.clone(), df));
+            let lf = context
+                .tabular()
+                .get_lazy(&source_path)
+                .await?
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Table source '{}' not found in tabular store", table.source)
+                })?;
+            match context.tabular().lazy_sources(&source_path).await {
+                Some(sources) => lazy_sources.get_or_insert_default().extend(sources),
+                // A DataFrame entry, get doesn't collect anything
+                None => eager_inputs.extend(context.tabular().get(&source_path).await?),
+            }
+            table_data.push((table.name.clone(), lf));
         }
 
         // Substitute any Tera expressions in the query
         let query = context.substitute(&self.query).await?;
 
         // Execute the query in a blocking task (SQLContext is not Send-safe across await)
-        let df = tokio::task::spawn_blocking(move || -> Result<TabularValue> {
-            let mut sql_ctx = polars::sql::SQLContext::new();
-
-            // Register all tables
-            for (name, df) in table_data {
-                sql_ctx.register(&name, df.lazy());
-            }
+        let any_lazy = lazy_sources.is_some();
+        let scalars = context.scalar().clone(); // Redacts secrets substituted into the query
+        let (output, row_count, columns) = tokio::task::spawn_blocking(
+            move || -> Result<(SqlOutput, Option<u64>, Vec<String>)> {
+                let mut sql_ctx = polars::sql::SQLContext::new();
+
+                // Register all tables
+                for (name, lf) in table_data {
+                    sql_ctx.register(&name, lf);
+                }
 
-            // Execute the query
-            let lazy_result = match sql_ctx.execute(&query) {
-                Ok(lazy_df) => lazy_df,
-                Err(e) => {
-                    tracing::warn!(
-                        query = %query,
-                        "SQL execution error"
-                    );
-                    return Err(anyhow::anyhow!("SQL execution failed: {}", e));
+                // Execute the query
+                let mut lazy_result = match sql_ctx.execute(&query) {
+                    Ok(lazy_df) => lazy_df,
+                    Err(e) => {
+                        tracing::warn!(
+                            query = %scalars.redact(&query),
+                            "SQL execution error"
+                        );
+                        return Err(anyhow::anyhow!(
+                            "SQL execution failed: {}",
+                            scalars.redact(&e.to_string())
+                        ));
+                    }
+                };
+
+                // A lazy input keeps the result lazy, so later commands extend the same plan. The row
+                // count stays unknown, counting would run the query.
+                if any_lazy {
+                    let columns = lazy_result
+                        .collect_schema()
+                        .map_err(|e| anyhow::anyhow!("Failed to plan query result: {}", e))?
+                        .iter_names()
+                        .map(|name| name.to_string())
+                        .collect();
+                    return Ok((SqlOutput::Lazy(Box::new(lazy_result)), None, columns));
                 }
-            };
 
-            // Collect the result
-            lazy_result
-                .collect()
-                .map_err(|e| anyhow::anyhow!("Failed to collect query result: {}", e))
-        })
+                // Collect the result
+                let df = lazy_result
+                    .collect()
+                    .map_err(|e| anyhow::anyhow!("Failed to collect query result: {}", e))?;
+                let columns = df
+                    .get_column_names()
+                    .iter()
+                    .map(|name| name.to_string())
+                    .collect();
+                let row_count = df.height() as u64;
+                Ok((SqlOutput::Eager(df), Some(row_count), columns))
+            },
+        )
         .await
         .map_err(|e| anyhow::anyhow!("Task join error: {}", e))??;
 
         // Store outputs
-        let row_count = df.height() as u64;
-        let column_names: Vec<ScalarValue> = df
-            .get_column_names()
-            .iter()
-            .map(|n| ScalarValue::String(n.to_string()))
-            .collect();
+        let column_names: Vec<ScalarValue> = columns.into_iter().map(ScalarValue::String).collect();
 
         let out = InsertBatch::new(context, output_prefix);
-        out.tabular("data", df).await?;
-        out.u64("rows", row_count).await?;
+        match output {
+            SqlOutput::Eager(df) => out.tabular("data", df).await?,
+            SqlOutput::Lazy(lf) => {
+                // The eager tables are embedded in the plan, they key it along with the lazy inputs
+                let mut sources = lazy_sources.unwrap_or_default();
+                sources.frames.extend(eager_inputs);
+                out.lazy("data", *lf, sources).await?
+            }
+        }
+        match row_count {
+            Some(rows) => out.u64("rows", rows).await?,
+            None => out.null("rows").await?,
+        }
         out.scalar("columns", ScalarValue::Array(column_names))
             .await?;
 
@@ -135,6 +183,9 @@ impl Descriptor for SqlCommand {
     fn command_results() -> &'static [ResultSpec<&'static str>] {
         &SQLCOMMAND_SPEC.1
     }
+    fn cacheable() -> bool {
+        true
+    }
 }
 
 impl FromAttributes for SqlCommand {
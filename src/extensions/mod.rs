@@ -148,6 +148,14 @@ impl Extensions {
             .unwrap_or(false)
     }
 
+    // Clones out the pipeline CancellationToken so callers can await cancellation without holding the lock.
+    pub async fn cancellation_token(&self) -> Option<tokio_util::sync::CancellationToken> {
+        self.read()
+            .await
+            .get::<tokio_util::sync::CancellationToken>()
+            .cloned()
+    }
+
     pub async fn cancel(&self) {
         if let Some(token) = self
             .read()
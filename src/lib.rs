@@ -3,9 +3,13 @@ mod dependencies;
 mod extensions;
 mod namespace;
 mod pipeline;
+mod repl;
+mod secrets;
 #[allow(unused)] // TODO: Remove temporary allow
 mod services;
 mod spec;
+#[cfg(feature = "tui")]
+mod tui;
 mod values;
 
 // Public API - external consumers use this via `panopticon_core::prelude::*`
@@ -13,17 +17,37 @@ pub mod prelude {
     // Built-in Commands
     pub use crate::commands::aggregate::AggregateCommand;
     pub use crate::commands::condition::ConditionCommand;
+    pub use crate::commands::export::ExportCommand;
     pub use crate::commands::file::FileCommand;
     pub use crate::commands::sql::SqlCommand;
     pub use crate::commands::template::TemplateCommand;
 
     // Pipeline
     pub use crate::pipeline::Pipeline;
-    pub use crate::pipeline::results::{ResultSettings, ResultStore};
+    pub use crate::pipeline::document::{
+        CommandDocument, DocumentFormat, InputDocument, NamespaceDocument, NamespaceMode,
+        PipelineDocument,
+    };
+    pub use crate::pipeline::explain::{
+        CommandExplanation, ExplainedResult, NamespaceExplanation, PipelineExplanation,
+        RequiredExtension,
+    };
+    pub use crate::pipeline::graph::{
+        GraphEdge, GraphEdgeKind, GraphNode, GraphNodeKind, PipelineGraph,
+    };
+    pub use crate::pipeline::registry::{CommandRegistry, RegisteredCommand};
+    pub use crate::pipeline::results::{
+        CommandResults, ResultSettings, ResultStore, ResultValue, TabularFormat,
+    };
+    pub use crate::pipeline::settings::{ErrorPolicy, ExecutionSettings};
+    pub use crate::repl::Repl;
+    pub use crate::secrets::{EnvSecrets, FileSecrets, Secret};
     pub use crate::services::PipelineServices;
+    #[cfg(feature = "tui")]
+    pub use crate::tui::Tui;
 
     // Namespace
-    pub use crate::namespace::{Namespace, NamespaceBuilder};
+    pub use crate::namespace::{IteratorType, Namespace, NamespaceBuilder, StaticSource};
 
     // Context
     pub use crate::values::scalar::ObjectBuilder;
@@ -39,7 +63,8 @@ pub mod extend {
     pub use crate::pipeline::traits::{
         Command, CommandFactory, Descriptor, Executable, FromAttributes,
     };
-    pub use crate::services::{EventHooks, PipelineIO};
+    pub use crate::secrets::{REDACTED, SecretProvider};
+    pub use crate::services::{EventHooks, PipelineIO, hook_events};
 
     // Spec types - declare your command's attributes and results
     pub use crate::spec::{
@@ -55,11 +80,13 @@ pub mod extend {
 
     // Extensions
     pub use crate::extensions::ExtensionKey;
+    pub use crate::pipeline::settings::PipelineDeadline;
 
     // Value types - used in trait signatures and command implementations
     pub use crate::values::context::ExecutionContext;
     pub use crate::values::helpers::{InsertBatch, to_scalar};
     pub use crate::values::scalar::{ScalarAsExt, ScalarMapExt, ScalarType};
+    pub use crate::values::tabular::LazySources;
 
     // Command helper type for LazyLock-time CommandSchemas
     pub use crate::commands::CommandSchema;
@@ -83,7 +110,7 @@ pub(crate) mod imports {
 
     // Namespace internals
     pub(crate) use crate::namespace::{
-        ExecutionMode, IteratorType, NamespaceHandle, RESERVED_NAMESPACES,
+        ExecutionMode, INPUTS_NAMESPACE, NamespaceHandle, RESERVED_NAMESPACES, SECRETS_NAMESPACE,
     };
 
     // Services internals
@@ -96,10 +123,7 @@ pub(crate) mod imports {
     pub(crate) use crate::spec::command::CommandSpec;
 
     // Pipeline result internals
-    pub(crate) use crate::pipeline::{
-        order::{ExecutionGroup, ExecutionPlan},
-        results::{CommandResults, ResultValue},
-    };
+    pub(crate) use crate::pipeline::order::{ExecutionGroup, ExecutionPlan};
 
     // Result and error handling (shadow extend::Result with identical definition + add Context)
     pub type Result<T> = anyhow::Result<T>;
@@ -15,7 +15,7 @@ impl NamespaceBuilder<sealed::Iterative> {
         let source = self
             .source
             .ok_or_else(|| anyhow::anyhow!("source is required for iterative namespace"))?;
-        Ok(Namespace::new(
+        Namespace::new(
             self.name,
             ExecutionMode::Iterative {
                 store_path,
@@ -24,7 +24,27 @@ impl NamespaceBuilder<sealed::Iterative> {
                 index_var: self.index_var,
             },
             sealed::BuilderToken(()),
-        ))
+        )
+        .with_max_parallelism(self.max_parallelism)
+        .with_iteration_parallelism(self.iteration_parallelism)
+        .with_when(self.when)
+    }
+
+    pub fn max_parallelism(mut self, max_parallelism: usize) -> Self {
+        self.max_parallelism = Some(max_parallelism);
+        self
+    }
+
+    // Opt-in: run up to `parallel_iterations` iterations at once, each with its own item/index scope
+    pub fn parallel_iterations(mut self, parallel_iterations: usize) -> Self {
+        self.iteration_parallelism = Some(parallel_iterations);
+        self
+    }
+
+    // Evaluated once before the iterations are resolved, skipping all of them when it's falsy
+    pub fn when(mut self, condition: &str) -> Self {
+        self.when = Some(condition.to_string());
+        self
     }
 
     pub fn store_path(mut self, store_path: StorePath) -> Self {
@@ -1,6 +1,8 @@
 use crate::imports::*;
 
 pub mod iterative;
+pub(crate) mod sources;
+pub use sources::StaticSource;
 #[cfg(test)]
 pub mod tests;
 
@@ -24,10 +26,14 @@ pub(crate) mod sealed {
     * DEFAULT_ITER_VAR - Default variable name for the current item in iterative namespaces
     * DEFAULT_INDEX_VAR - Default variable name for the current index in iterative namespaces
     * RESERVED_NAMESPACES - List of reserved namespace names that cannot be used
+    * INPUTS_NAMESPACE - Namespace pipeline inputs are stored under, reserved once a pipeline declares inputs
+    * SECRETS_NAMESPACE - Namespace secrets are stored under, reserved once a pipeline declares secrets
 */
 pub const DEFAULT_ITER_VAR: &str = "item";
 pub const DEFAULT_INDEX_VAR: &str = "index";
 pub const RESERVED_NAMESPACES: [&str; 2] = [DEFAULT_ITER_VAR, DEFAULT_INDEX_VAR];
+pub const INPUTS_NAMESPACE: &str = "inputs";
+pub const SECRETS_NAMESPACE: &str = "secrets";
 
 /*
     Types:
@@ -41,6 +47,13 @@ pub const RESERVED_NAMESPACES: [&str; 2] = [DEFAULT_ITER_VAR, DEFAULT_INDEX_VAR]
 pub struct Namespace {
     name: String,
     ty: ExecutionMode,
+    max_parallelism: usize, // Commands of this namespace allowed to run at the same time
+    iteration_parallelism: usize, // Iterations allowed to run at the same time (Iterative only)
+    when: Option<String>,   // Tera expression, the namespace's commands are skipped when it's falsy
+    when_dependencies: HashSet<StorePath>,
+    // Static only, what the values were built from: the inserted values and the sources loaded over them
+    inserted_values: HashMap<String, ScalarValue>,
+    static_sources: Vec<StaticSource>,
 }
 
 impl Namespace {
@@ -56,16 +69,100 @@ impl Namespace {
         Namespace {
             name: name.into(),
             ty,
+            max_parallelism: 1,
+            iteration_parallelism: 1,
+            when: None,
+            when_dependencies: HashSet::new(),
+            inserted_values: HashMap::new(),
+            static_sources: Vec::new(),
         }
     }
 
+    pub(crate) fn with_static_sources(
+        mut self,
+        inserted_values: HashMap<String, ScalarValue>,
+        static_sources: Vec<StaticSource>,
+    ) -> Self {
+        self.inserted_values = inserted_values;
+        self.static_sources = static_sources;
+        self
+    }
+
+    pub(crate) fn with_max_parallelism(mut self, max_parallelism: Option<usize>) -> Self {
+        if let Some(max_parallelism) = max_parallelism {
+            self.max_parallelism = max_parallelism.max(1);
+        }
+        self
+    }
+
+    pub(crate) fn with_iteration_parallelism(
+        mut self,
+        iteration_parallelism: Option<usize>,
+    ) -> Self {
+        if let Some(iteration_parallelism) = iteration_parallelism {
+            self.iteration_parallelism = iteration_parallelism.max(1);
+        }
+        self
+    }
+
+    // The condition is evaluated before any of the namespace's commands, so it can't read their results
+    pub(crate) fn with_when(mut self, when: Option<String>) -> Result<Self> {
+        let Some(when) = when else {
+            return Ok(self);
+        };
+        let mut dependencies = HashSet::new();
+        crate::dependencies::parser::parse_template_dependencies(
+            &format!("{{{{ {} }}}}", when),
+            &mut dependencies,
+        )
+        .with_context(|| format!("Invalid 'when' for namespace '{}'", self.name))?;
+        if let Some(own) = dependencies
+            .iter()
+            .find(|path| path.namespace().is_some_and(|ns| ns.as_str() == self.name))
+        {
+            return Err(anyhow::anyhow!(
+                "The 'when' of namespace '{}' can't reference its own results ('{}')",
+                self.name,
+                own.to_dotted()
+            ));
+        }
+        self.when = Some(when);
+        self.when_dependencies = dependencies;
+        Ok(self)
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    pub fn when(&self) -> Option<&str> {
+        self.when.as_deref()
+    }
+
+    pub(crate) fn when_dependencies(&self) -> &HashSet<StorePath> {
+        &self.when_dependencies
+    }
+
+    pub fn max_parallelism(&self) -> usize {
+        self.max_parallelism
+    }
+
+    pub fn iteration_parallelism(&self) -> usize {
+        self.iteration_parallelism
+    }
+
     pub(crate) fn ty(&self) -> &ExecutionMode {
         &self.ty
     }
+
+    // Static only. The values given to insert / object, before any source was loaded over them
+    pub(crate) fn inserted_values(&self) -> &HashMap<String, ScalarValue> {
+        &self.inserted_values
+    }
+
+    pub(crate) fn static_sources(&self) -> &[StaticSource] {
+        &self.static_sources
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -100,6 +197,10 @@ pub struct NamespaceBuilder<T> {
     iter_var: Option<String>,
     index_var: Option<String>,
     values: Option<std::collections::HashMap<String, ScalarValue>>,
+    sources: Vec<StaticSource>, // Static only, loaded over `values` on build
+    max_parallelism: Option<usize>,
+    iteration_parallelism: Option<usize>,
+    when: Option<String>, // Once and Iterative only
     // marker
     _marker: std::marker::PhantomData<T>,
 }
@@ -141,6 +242,17 @@ impl<'a> NamespaceHandle<'a, sealed::Once> {
             .add_command::<T>(self.namespace_index, name, attrs)
             .await
     }
+    // Adds a command registered in the pipeline's CommandRegistry under `command_type`
+    pub async fn add_command_by_type(
+        &mut self,
+        name: &str,
+        command_type: &str,
+        attrs: &Attributes,
+    ) -> Result<()> {
+        self.commands
+            .add_command_by_type(self.namespace_index, name, command_type, attrs)
+            .await
+    }
 }
 
 impl<'a> NamespaceHandle<'a, sealed::Iterative> {
@@ -152,22 +264,39 @@ impl<'a> NamespaceHandle<'a, sealed::Iterative> {
             .add_command::<T>(self.namespace_index, name, attrs)
             .await
     }
+    // Adds a command registered in the pipeline's CommandRegistry under `command_type`
+    pub async fn add_command_by_type(
+        &mut self,
+        name: &str,
+        command_type: &str,
+        attrs: &Attributes,
+    ) -> Result<()> {
+        self.commands
+            .add_command_by_type(self.namespace_index, name, command_type, attrs)
+            .await
+    }
 }
 
-#[derive(Debug, Clone)]
+// Serialised with a "type" tag for pipeline documents, e.g. { type: scalar_array, range: [0, 10] }
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
 pub enum IteratorType {
     ScalarStringSplit {
         delimiter: String,
     },
     ScalarArray {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         range: Option<(usize, usize)>,
     },
     ScalarObjectKeys {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         keys: Option<Vec<String>>,
+        #[serde(default)]
         exclude: bool,
     },
     TabularColumn {
         column: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         range: Option<(usize, usize)>,
     },
 }
@@ -189,6 +318,10 @@ mod once {
                 iter_var: None,
                 index_var: None,
                 values: None,
+                sources: Vec::new(),
+                max_parallelism: None,
+                iteration_parallelism: None,
+                when: None,
                 _marker: std::marker::PhantomData,
             }
         }
@@ -199,11 +332,19 @@ mod once {
                     self.name
                 ));
             }
-            Ok(Namespace::new(
-                self.name,
-                ExecutionMode::Once,
-                sealed::BuilderToken(()),
-            ))
+            Namespace::new(self.name, ExecutionMode::Once, sealed::BuilderToken(()))
+                .with_max_parallelism(self.max_parallelism)
+                .with_when(self.when)
+        }
+        pub fn max_parallelism(mut self, max_parallelism: usize) -> Self {
+            self.max_parallelism = Some(max_parallelism);
+            self
+        }
+        // Tera expression like a command's 'when', e.g. `config.enrich`. When it's falsy none of the
+        // namespace's commands run, each is recorded as skipped.
+        pub fn when(mut self, condition: &str) -> Self {
+            self.when = Some(condition.to_string());
+            self
         }
         pub fn iterative(self) -> NamespaceBuilder<sealed::Iterative> {
             NamespaceBuilder {
@@ -213,6 +354,10 @@ mod once {
                 iter_var: None,
                 index_var: None,
                 values: None,
+                sources: Vec::new(),
+                max_parallelism: self.max_parallelism,
+                iteration_parallelism: None,
+                when: self.when,
                 _marker: std::marker::PhantomData,
             }
         }
@@ -224,6 +369,10 @@ mod once {
                 iter_var: None,
                 index_var: None,
                 values: Some(std::collections::HashMap::new()),
+                sources: Vec::new(),
+                max_parallelism: None,
+                iteration_parallelism: None,
+                when: None,
                 _marker: std::marker::PhantomData,
             }
         }
@@ -243,11 +392,44 @@ mod static_ns {
             let values = self
                 .values
                 .ok_or_else(|| anyhow::anyhow!("values are required for static namespace"))?;
+            // Inserted values are the base, each source then overrides what came before it
+            let mut merged: tera::Map<String, ScalarValue> =
+                values.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+            for source in &self.sources {
+                let loaded = source.load().with_context(|| {
+                    format!("Failed to load values for static namespace '{}'", self.name)
+                })?;
+                tracing::debug!(namespace_name = %self.name, ?source, value_count = loaded.len(), "Loaded static values");
+                sources::merge_values(&mut merged, loaded);
+            }
             Ok(Namespace::new(
                 self.name,
-                ExecutionMode::Static { values },
+                ExecutionMode::Static {
+                    values: merged.into_iter().collect(),
+                },
                 sealed::BuilderToken(()),
-            ))
+            )
+            .with_static_sources(values, self.sources))
+        }
+        // Top-level keys of a JSON, YAML or TOML object become values, the format picked from the extension
+        pub fn load_file(mut self, path: impl Into<PathBuf>) -> Self {
+            self.sources.push(StaticSource::File { path: path.into() });
+            self
+        }
+        // Environment variables starting with `prefix`, e.g. with "APP_": APP_DB__HOST -> db.host
+        pub fn load_env(mut self, prefix: &str) -> Self {
+            self.sources.push(StaticSource::Env {
+                prefix: prefix.to_string(),
+            });
+            self
+        }
+        // As load_env, reading a .env file instead of the environment. A missing file is skipped.
+        pub fn load_dotenv(mut self, path: impl Into<PathBuf>, prefix: &str) -> Self {
+            self.sources.push(StaticSource::Dotenv {
+                path: path.into(),
+                prefix: prefix.to_string(),
+            });
+            self
         }
         pub fn insert<T: Into<String>>(mut self, key: T, value: ScalarValue) -> Self {
             if let Some(ref mut vals) = self.values {
@@ -0,0 +1,183 @@
+use crate::imports::*;
+
+/*
+    Consts:
+    * NESTING_SEPARATOR - Splits variable names into nested keys, e.g. APP_DB__HOST with prefix APP_ -> db.host
+*/
+const NESTING_SEPARATOR: &str = "__";
+
+/*
+    Types:
+    * StaticSource - Where a Static namespace loads values from when it's built, applied in the order added
+*/
+// Serialised with a "type" tag for pipeline documents, e.g. { type: dotenv, path: .env, prefix: APP_ }
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum StaticSource {
+    File { path: PathBuf }, // JSON, YAML or TOML object, the format picked from the extension
+    Env { prefix: String },
+    Dotenv { path: PathBuf, prefix: String }, // Skipped when the file doesn't exist
+}
+
+/*
+    Functions:
+    (CRATE METHODS)
+    * merge_values - Merges loaded values over existing ones, objects key by key
+    * variables_to_values - Turns NAME=value pairs starting with a prefix into nested, coerced values
+    * parse_dotenv - Parses the KEY=VALUE lines of a .env file
+    * coerce - Reads a variable's text as a bool, number, null, JSON array/object or string
+    (PRIVATE METHODS)
+    * load_file - Reads a JSON, YAML or TOML file holding an object
+*/
+impl StaticSource {
+    pub(crate) fn load(&self) -> Result<tera::Map<String, ScalarValue>> {
+        match self {
+            StaticSource::File { path } => load_file(path),
+            StaticSource::Env { prefix } => {
+                // Variables that aren't valid unicode can't be values, skip them rather than panic
+                let variables = std::env::vars_os()
+                    .filter_map(|(name, value)| {
+                        Some((name.into_string().ok()?, value.into_string().ok()?))
+                    })
+                    .map(|(name, value)| (name, coerce(&value)));
+                Ok(variables_to_values(variables, prefix))
+            }
+            StaticSource::Dotenv { path, prefix } => {
+                if !path.exists() {
+                    tracing::debug!(path = %path.display(), "No .env file, skipping");
+                    return Ok(tera::Map::new());
+                }
+                let input = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read '{}'", path.display()))?;
+                let variables = parse_dotenv(&input)
+                    .with_context(|| format!("Invalid .env file '{}'", path.display()))?;
+                Ok(variables_to_values(variables, prefix))
+            }
+        }
+    }
+}
+
+fn load_file(path: &std::path::Path) -> Result<tera::Map<String, ScalarValue>> {
+    let input = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read '{}'", path.display()))?;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let value: ScalarValue = match extension.as_str() {
+        "json" => serde_json::from_str(&input).map_err(anyhow::Error::from),
+        "yaml" | "yml" => serde_yaml::from_str(&input).map_err(anyhow::Error::from),
+        "toml" => toml::from_str(&input).map_err(anyhow::Error::from),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Unsupported file '{}', expected .json, .yaml, .yml or .toml",
+                path.display()
+            ));
+        }
+    }
+    .with_context(|| format!("Failed to parse '{}'", path.display()))?;
+    match value {
+        ScalarValue::Object(values) => Ok(values),
+        other => Err(anyhow::anyhow!(
+            "'{}' must hold an object, got {:?}",
+            path.display(),
+            scalar_type_of(&other)
+        )),
+    }
+}
+
+pub(crate) fn merge_values(
+    into: &mut tera::Map<String, ScalarValue>,
+    values: tera::Map<String, ScalarValue>,
+) {
+    for (key, value) in values {
+        match (into.get_mut(&key), value) {
+            (Some(ScalarValue::Object(existing)), ScalarValue::Object(value)) => {
+                merge_values(existing, value)
+            }
+            (_, value) => {
+                into.insert(key, value);
+            }
+        }
+    }
+}
+
+// Names are matched against the prefix as written, the rest is lowercased and split on NESTING_SEPARATOR
+pub(crate) fn variables_to_values(
+    variables: impl IntoIterator<Item = (String, ScalarValue)>,
+    prefix: &str,
+) -> tera::Map<String, ScalarValue> {
+    let mut values = tera::Map::new();
+    for (name, value) in variables {
+        let Some(rest) = name.strip_prefix(prefix) else {
+            continue;
+        };
+        let keys: Vec<String> = rest
+            .split(NESTING_SEPARATOR)
+            .map(|key| key.to_lowercase())
+            .collect();
+        if keys.iter().any(|key| key.is_empty()) {
+            tracing::debug!(variable = %name, "Skipping variable with an empty key");
+            continue;
+        }
+        let nested = keys.iter().skip(1).rev().fold(value, |value, key| {
+            let mut object = tera::Map::new();
+            object.insert(key.clone(), value);
+            ScalarValue::Object(object)
+        });
+        merge_values(
+            &mut values,
+            [(keys[0].clone(), nested)].into_iter().collect(),
+        );
+    }
+    values
+}
+
+// Supports comments, blank lines, 'export ' and single or double quoted values. Quoted values stay strings,
+// unquoted ones are coerced like environment variables.
+pub(crate) fn parse_dotenv(input: &str) -> Result<Vec<(String, ScalarValue)>> {
+    let mut variables = Vec::new();
+    for (number, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, raw) = line
+            .split_once('=')
+            .with_context(|| format!("Line {}: expected KEY=VALUE", number + 1))?;
+        let raw = raw.trim();
+        let value = match raw.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let body = &raw[1..];
+                let end = body.find(quote).with_context(|| {
+                    format!("Line {}: unterminated {} quote", number + 1, quote)
+                })?;
+                let text = &body[..end];
+                ScalarValue::String(match quote {
+                    '"' => text.replace("\\n", "\n").replace("\\t", "\t"),
+                    _ => text.to_string(),
+                })
+            }
+            // An unquoted value ends at a comment
+            _ => coerce(raw.split(" #").next().unwrap_or_default().trim_end()),
+        };
+        variables.push((name.trim().to_string(), value));
+    }
+    Ok(variables)
+}
+
+// Numbers with a leading zero (zip codes, ids) stay strings so no digits are lost
+pub(crate) fn coerce(raw: &str) -> ScalarValue {
+    if (raw.starts_with('[') || raw.starts_with('{'))
+        && let Ok(value) = serde_json::from_str(raw)
+    {
+        return value;
+    }
+    let digits = raw.strip_prefix('-').unwrap_or(raw);
+    if digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.") {
+        return ScalarValue::String(raw.to_string());
+    }
+    parse_scalar(raw)
+}
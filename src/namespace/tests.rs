@@ -534,3 +534,108 @@ async fn test_iterative_pipeline_indexed_outputs() {
     let non_indexed = StorePath::from_segments(["process", "render"]);
     assert!(results.get_by_source(&non_indexed).is_none());
 }
+
+#[tokio::test]
+async fn test_static_namespace_layers_files_and_dotenv() {
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("config.yaml"),
+        "db:\n  host: localhost\n  port: 5432\nregions: [eu, us]\nname: base\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("prod.toml"),
+        "name = \"prod\"\n[db]\nhost = \"db.internal\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join(".env"),
+        "# local overrides\nexport APP_DB__PORT=6543\nAPP_DEBUG=true # inline comment\nAPP_ZIP=01234\nAPP_LABEL=\"42\"\nOTHER=ignored\n",
+    )
+    .unwrap();
+
+    let namespace = NamespaceBuilder::new("config")
+        .static_ns()
+        .insert("name", ScalarValue::String("default".to_string()))
+        .insert("timeout", ScalarValue::from(30))
+        .load_file(dir.path().join("config.yaml"))
+        .load_file(dir.path().join("prod.toml"))
+        .load_dotenv(dir.path().join(".env"), "APP_")
+        .load_dotenv(dir.path().join("missing.env"), "APP_")
+        .build()
+        .unwrap();
+    let ExecutionMode::Static { values } = namespace.ty() else {
+        panic!("expected a static namespace");
+    };
+
+    // Later sources win, objects are merged key by key
+    assert_eq!(values["name"], ScalarValue::from("prod"));
+    assert_eq!(values["timeout"], ScalarValue::from(30));
+    assert_eq!(values["db"]["host"], ScalarValue::from("db.internal"));
+    assert_eq!(values["db"]["port"], ScalarValue::from(6543));
+    assert_eq!(values["regions"][1], ScalarValue::from("us"));
+    // Coercion: bools and numbers are typed, leading zeros and quoted values stay strings
+    assert_eq!(values["debug"], ScalarValue::Bool(true));
+    assert_eq!(values["zip"], ScalarValue::from("01234"));
+    assert_eq!(values["label"], ScalarValue::from("42"));
+    assert!(!values.contains_key("other"));
+}
+
+#[test]
+fn test_environment_variables_become_nested_values() {
+    init_tracing();
+    let variables = [
+        ("APP_DB__HOST", "db.internal"),
+        ("APP_DB__PORT", "5432"),
+        ("APP_REGIONS", "[\"eu\", \"us\"]"),
+        ("APP_", "no key"),
+        ("PATH", "/usr/bin"),
+    ]
+    .map(|(name, value)| (name.to_string(), sources::coerce(value)));
+    let values = sources::variables_to_values(variables, "APP_");
+    assert_eq!(values.len(), 2);
+    assert_eq!(values["db"]["host"], ScalarValue::from("db.internal"));
+    assert_eq!(values["db"]["port"], ScalarValue::from(5432));
+    assert_eq!(values["regions"], ScalarValue::from(vec!["eu", "us"]));
+
+    // The real environment, cargo sets CARGO_PKG_* for test binaries
+    let namespace = NamespaceBuilder::new("pkg")
+        .static_ns()
+        .load_env("CARGO_PKG_")
+        .build()
+        .unwrap();
+    let ExecutionMode::Static { values } = namespace.ty() else {
+        panic!("expected a static namespace");
+    };
+    assert_eq!(values["name"], ScalarValue::from("panopticon-core"));
+}
+
+#[test]
+fn test_static_sources_report_bad_input() {
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("list.json"), "[1, 2]").unwrap();
+    std::fs::write(dir.path().join("bad.env"), "JUST_A_NAME\n").unwrap();
+
+    let err = NamespaceBuilder::new("config")
+        .static_ns()
+        .load_file(dir.path().join("list.json"))
+        .build()
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("must hold an object"));
+    assert!(err.to_string().contains("static namespace 'config'"));
+
+    let missing = NamespaceBuilder::new("config")
+        .static_ns()
+        .load_file(dir.path().join("missing.yaml"))
+        .build();
+    assert!(missing.is_err());
+
+    let err = NamespaceBuilder::new("config")
+        .static_ns()
+        .load_dotenv(dir.path().join("bad.env"), "")
+        .build()
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("Line 1: expected KEY=VALUE"));
+}
@@ -0,0 +1,161 @@
+use super::checkpoint::{read_checkpoint, write_checkpoint};
+use crate::imports::*;
+use sha2::Digest;
+
+/*
+    Types:
+    * ResultCache - Content-addressed store of command outputs, one directory per cache key
+*/
+pub(crate) struct ResultCache {
+    dir: PathBuf,
+}
+
+impl ResultCache {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        ResultCache { dir }
+    }
+
+    // Hash of everything that determines a command's outputs: its type, substituted attributes, the values
+    // (scalar or tabular) at its dependency paths and the modification times of the files it reads.
+    // `dependencies` are resolved for the iteration being run, the spec's own can be templated or unindexed.
+    pub(crate) async fn key(
+        &self,
+        command_spec: &CommandSpec,
+        attributes: &Attributes,
+        dependencies: &[StorePath],
+        input_files: &[PathBuf],
+        context: &ExecutionContext,
+    ) -> Result<String> {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(env!("CARGO_PKG_VERSION"));
+        hasher.update(command_spec.command_type.as_bytes());
+
+        // Sorted by name, serde_json keeps insertion order and HashMap iteration order differs between runs
+        let attributes: std::collections::BTreeMap<&String, &ScalarValue> =
+            attributes.iter().collect();
+        hasher.update(serde_json::to_vec(&attributes)?);
+
+        let mut dependencies: Vec<&StorePath> = dependencies.iter().collect();
+        dependencies.sort_by_key(|path| path.to_dotted());
+        for dependency in dependencies {
+            hasher.update(dependency.to_dotted().as_bytes());
+            // A lazy frame isn't collected, its plan and what it reads stand in for the rows
+            if let Some(sources) = context.tabular().lazy_sources(dependency).await
+                && let Some(lf) = context.tabular().get_lazy(dependency).await?
+            {
+                hasher.update(
+                    lf.describe_plan()
+                        .context("Failed to describe lazy frame")?,
+                );
+                hash_files(&mut hasher, &sources.files).await;
+                for df in sources.frames {
+                    hasher.update(hash_frame_blocking(df).await?);
+                }
+            } else if let Some(df) = context.tabular().get(dependency).await? {
+                hasher.update(hash_frame_blocking(df).await?);
+            } else if let Some(value) = context.scalar().get(dependency).await? {
+                hasher.update(serde_json::to_vec(&value)?);
+            }
+        }
+
+        hash_files(&mut hasher, input_files).await;
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    // Restores a cached entry into the stores at output_prefix, returns false on a cache miss
+    pub(crate) async fn restore(
+        &self,
+        key: &str,
+        context: &ExecutionContext,
+        output_prefix: &StorePath,
+    ) -> Result<bool> {
+        let entry = self.dir.join(key);
+        if !entry.join(super::checkpoint::MANIFEST_FILENAME).exists() {
+            return Ok(false);
+        }
+        let (scalars, frames) = tokio::task::spawn_blocking(move || read_checkpoint(&entry))
+            .await
+            .context("Cache reader panicked")??;
+        context.scalar().insert(output_prefix, scalars).await?;
+        for (suffix, df) in frames {
+            let path = StorePath::from_dotted(&format!("{}.{}", output_prefix.to_dotted(), suffix));
+            context.tabular().insert(&path, df).await?;
+        }
+        Ok(true)
+    }
+
+    // Stores every scalar and frame under output_prefix, keyed relative to it so iterations can share entries
+    pub(crate) async fn store(
+        &self,
+        key: &str,
+        context: &ExecutionContext,
+        output_prefix: &StorePath,
+    ) -> Result<()> {
+        let Some(scalars) = context.scalar().get(output_prefix).await? else {
+            return Ok(());
+        };
+        // Only this command's frames. A command with a lazy output isn't stored, collecting the output
+        // would read everything the plan was meant to skip and rebuilding the plan is cheap.
+        let prefix = format!("{}.", output_prefix.to_dotted());
+        let mut frames: HashMap<String, TabularValue> = HashMap::new();
+        for key in context.tabular().keys().await {
+            let Some(field) = key.strip_prefix(&prefix) else {
+                continue;
+            };
+            let path = StorePath::from_dotted(&key);
+            if context.tabular().is_lazy(&path).await {
+                return Ok(());
+            }
+            if let Some(df) = context.tabular().get(&path).await? {
+                frames.insert(field.to_string(), df);
+            }
+        }
+        let entry = self.dir.join(key);
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            std::fs::create_dir_all(entry.join(super::checkpoint::TABULAR_DIRNAME))?;
+            write_checkpoint(&entry, &scalars, &frames)
+        })
+        .await
+        .context("Cache writer panicked")??;
+        Ok(())
+    }
+}
+
+// Path, size and modification time of each file. Missing files hash as such, the command reports the error.
+async fn hash_files(hasher: &mut sha2::Sha256, files: &[PathBuf]) {
+    for file in files {
+        hasher.update(file.to_string_lossy().as_bytes());
+        if let Ok(metadata) = tokio::fs::metadata(file).await {
+            hasher.update(metadata.len().to_le_bytes());
+            if let Ok(modified) = metadata.modified()
+                && let Ok(since_epoch) = modified.duration_since(std::time::UNIX_EPOCH)
+            {
+                hasher.update(since_epoch.as_nanos().to_le_bytes());
+            }
+        }
+    }
+}
+
+async fn hash_frame_blocking(df: TabularValue) -> Result<Vec<u8>> {
+    tokio::task::spawn_blocking(move || hash_frame(df))
+        .await
+        .context("Frame hashing panicked")?
+}
+
+// Schema plus polars' row hashes, with a fixed seed so keys match across runs
+fn hash_frame(mut df: TabularValue) -> Result<Vec<u8>> {
+    use polars::prelude::{PlSeedableRandomStateQuality, SeedableFromU64SeedExt};
+
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(format!("{:?}", df.schema()));
+    hasher.update((df.height() as u64).to_le_bytes());
+    if df.width() > 0 {
+        let hashes = df
+            .hash_rows(Some(PlSeedableRandomStateQuality::seed_from_u64(0)))
+            .context("Failed to hash frame")?;
+        for hash in hashes.into_no_null_iter() {
+            hasher.update(hash.to_le_bytes());
+        }
+    }
+    Ok(hasher.finalize().to_vec())
+}
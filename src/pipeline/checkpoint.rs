@@ -0,0 +1,273 @@
+use super::results::{TabularFormat, write_tabular};
+use crate::imports::*;
+
+/*
+    CONSTS:
+    * MANIFEST_FILENAME - Written last, a checkpoint directory without one is treated as empty
+    * SCALARS_FILENAME - JSON snapshot of a ScalarStore (cache entries)
+    * SCALARS_DIRNAME - Directory holding one JSON file per checkpointed command
+    * TABULAR_DIRNAME - Directory holding one Parquet file per TabularStore entry
+*/
+pub const MANIFEST_FILENAME: &str = "manifest.json";
+pub const SCALARS_FILENAME: &str = "scalars.json";
+pub const SCALARS_DIRNAME: &str = "scalars";
+pub const TABULAR_DIRNAME: &str = "tabular";
+
+/*
+    Types:
+    * Checkpointer - Extension that persists each command's outputs to a checkpoint directory as it finishes
+    * CheckpointManifest - Index of a checkpoint directory, the files written for each command output prefix
+    * SnapshotManifest - Index of a cache entry (scalar snapshot + Parquet file per frame)
+*/
+pub(crate) struct Checkpointer {
+    dir: PathBuf,
+    manifest: tokio::sync::Mutex<CheckpointManifest>, // Also makes concurrent commands take turns writing
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub(crate) struct CheckpointManifest {
+    commands: std::collections::BTreeMap<String, CheckpointCommand>, // Keyed by output prefix
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct CheckpointCommand {
+    scalars: Option<String>,
+    tabular: Vec<CheckpointFrame>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct SnapshotManifest {
+    scalars: String,
+    tabular: Vec<CheckpointFrame>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct CheckpointFrame {
+    key: String,
+    file: String,
+}
+
+impl CheckpointManifest {
+    pub(crate) fn output_prefixes(&self) -> Vec<StorePath> {
+        self.commands
+            .keys()
+            .map(|key| StorePath::from_dotted(key))
+            .collect()
+    }
+}
+
+impl Checkpointer {
+    // Starts from `manifest`, the one loaded when resuming into the same directory. Otherwise it's empty and
+    // written straight away, so a previous run's checkpoint can't be resumed by mistake.
+    pub(crate) fn new(dir: PathBuf, manifest: CheckpointManifest) -> Result<Self> {
+        for subdir in [SCALARS_DIRNAME, TABULAR_DIRNAME] {
+            std::fs::create_dir_all(dir.join(subdir)).with_context(|| {
+                format!("Failed to create checkpoint directory '{}'", dir.display())
+            })?;
+        }
+        write_json_atomic(&dir.join(MANIFEST_FILENAME), &manifest)?;
+        Ok(Checkpointer {
+            dir,
+            manifest: tokio::sync::Mutex::new(manifest),
+        })
+    }
+
+    // Writes the scalars and frames under output_prefix, then the manifest. Earlier commands' files are left
+    // alone, so each save costs what the command produced. A command with a lazy output is left out rather
+    // than collecting it, a resumed run executes it again to rebuild the plan.
+    #[tracing::instrument(skip(self, context), err, fields(dir = %self.dir.display()))]
+    pub(crate) async fn save(
+        &self,
+        context: &ExecutionContext,
+        output_prefix: &StorePath,
+    ) -> Result<()> {
+        for key in context.tabular().keys().await {
+            let path = StorePath::from_dotted(&key);
+            if path.starts_with(output_prefix) && context.tabular().is_lazy(&path).await {
+                tracing::debug!(command = %output_prefix.to_dotted(), "Lazy output, not checkpointed");
+                return Ok(());
+            }
+        }
+        let mut manifest = self.manifest.lock().await;
+        // Secrets never reach the disk, a resumed run looks them up again
+        let scalars = context
+            .scalar()
+            .get(output_prefix)
+            .await?
+            .map(|value| context.scalar().redact_value(&value));
+        let mut frames = Vec::new();
+        for key in context.tabular().keys().await {
+            let path = StorePath::from_dotted(&key);
+            if path.starts_with(output_prefix)
+                && let Some(df) = context.tabular().get(&path).await?
+            {
+                frames.push((key, df));
+            }
+        }
+        let dir = self.dir.clone();
+        let prefix = output_prefix.to_dotted();
+        let command = tokio::task::spawn_blocking(move || {
+            write_command(&dir, &prefix, scalars.as_ref(), &frames)
+        })
+        .await
+        .context("Checkpoint writer panicked")??;
+        manifest.commands.insert(output_prefix.to_dotted(), command);
+        write_json_atomic(&self.dir.join(MANIFEST_FILENAME), &*manifest)?;
+        tracing::debug!(command = %output_prefix.to_dotted(), "Checkpoint saved");
+        Ok(())
+    }
+}
+
+fn write_command(
+    dir: &std::path::Path,
+    prefix: &str,
+    scalars: Option<&ScalarValue>,
+    frames: &[(String, TabularValue)],
+) -> Result<CheckpointCommand> {
+    let mut command = CheckpointCommand {
+        scalars: None,
+        tabular: Vec::with_capacity(frames.len()),
+    };
+    for (key, df) in frames {
+        let file = format!("{}/{}.parquet", TABULAR_DIRNAME, key);
+        let tmp = dir.join(format!("{}.tmp", file));
+        write_tabular(df, &tmp, &TabularFormat::Parquet)
+            .with_context(|| format!("Failed to checkpoint frame '{}'", key))?;
+        std::fs::rename(&tmp, dir.join(&file))?;
+        command.tabular.push(CheckpointFrame {
+            key: key.clone(),
+            file,
+        });
+    }
+    if let Some(scalars) = scalars {
+        let file = format!("{}/{}.json", SCALARS_DIRNAME, prefix);
+        write_json_atomic(&dir.join(&file), scalars)?;
+        command.scalars = Some(file);
+    }
+    Ok(command)
+}
+
+// Loads a checkpoint written by Checkpointer::save into the context's stores
+#[tracing::instrument(skip(context), err, fields(dir = %dir.display()))]
+pub(crate) async fn load_checkpoint(
+    dir: &std::path::Path,
+    context: &ExecutionContext,
+) -> Result<CheckpointManifest> {
+    let dir = dir.to_path_buf();
+    let (manifest, scalars, frames) = tokio::task::spawn_blocking(move || read_commands(&dir))
+        .await
+        .context("Checkpoint reader panicked")??;
+    for (prefix, value) in scalars {
+        context
+            .scalar()
+            .insert(&StorePath::from_dotted(&prefix), value)
+            .await?;
+    }
+    let frame_count = frames.len();
+    for (key, df) in frames {
+        context
+            .tabular()
+            .insert(&StorePath::from_dotted(&key), df)
+            .await?;
+    }
+    tracing::debug!(
+        command_count = manifest.commands.len(),
+        frame_count = frame_count,
+        "Checkpoint loaded"
+    );
+    Ok(manifest)
+}
+
+type CheckpointContents = (
+    CheckpointManifest,
+    Vec<(String, ScalarValue)>,
+    Vec<(String, TabularValue)>,
+);
+
+fn read_commands(dir: &std::path::Path) -> Result<CheckpointContents> {
+    let manifest_path = dir.join(MANIFEST_FILENAME);
+    let manifest: CheckpointManifest = serde_json::from_reader(
+        std::fs::File::open(&manifest_path)
+            .with_context(|| format!("No checkpoint found at '{}'", manifest_path.display()))?,
+    )
+    .context("Failed to parse checkpoint manifest")?;
+    let mut scalars = Vec::new();
+    let mut frames = Vec::new();
+    for (prefix, command) in manifest.commands.iter() {
+        if let Some(file) = &command.scalars {
+            let value: ScalarValue = serde_json::from_reader(
+                std::fs::File::open(dir.join(file))
+                    .with_context(|| format!("Missing checkpoint scalars '{}'", file))?,
+            )
+            .with_context(|| format!("Failed to parse checkpoint scalars '{}'", file))?;
+            scalars.push((prefix.clone(), value));
+        }
+        for frame in command.tabular.iter() {
+            frames.push((frame.key.clone(), read_frame(dir, frame)?));
+        }
+    }
+    Ok((manifest, scalars, frames))
+}
+
+fn read_frame(dir: &std::path::Path, frame: &CheckpointFrame) -> Result<TabularValue> {
+    use polars::prelude::SerReader;
+
+    let file = std::fs::File::open(dir.join(&frame.file))
+        .with_context(|| format!("Missing checkpoint frame '{}'", frame.file))?;
+    polars::prelude::ParquetReader::new(file)
+        .finish()
+        .with_context(|| format!("Failed to read checkpoint frame '{}'", frame.file))
+}
+
+pub(super) fn write_checkpoint(
+    dir: &std::path::Path,
+    scalars: &ScalarValue,
+    frames: &HashMap<String, TabularValue>,
+) -> Result<()> {
+    let mut manifest = SnapshotManifest {
+        scalars: SCALARS_FILENAME.to_string(),
+        tabular: Vec::with_capacity(frames.len()),
+    };
+    for (key, df) in frames {
+        let file = format!("{}/{}.parquet", TABULAR_DIRNAME, key);
+        let tmp = dir.join(format!("{}.tmp", file));
+        write_tabular(df, &tmp, &TabularFormat::Parquet)
+            .with_context(|| format!("Failed to checkpoint frame '{}'", key))?;
+        std::fs::rename(&tmp, dir.join(&file))?;
+        manifest.tabular.push(CheckpointFrame {
+            key: key.clone(),
+            file,
+        });
+    }
+    write_json_atomic(&dir.join(SCALARS_FILENAME), scalars)?;
+    write_json_atomic(&dir.join(MANIFEST_FILENAME), &manifest)
+}
+
+pub(super) fn read_checkpoint(
+    dir: &std::path::Path,
+) -> Result<(ScalarValue, Vec<(String, TabularValue)>)> {
+    let manifest_path = dir.join(MANIFEST_FILENAME);
+    let manifest: SnapshotManifest = serde_json::from_reader(
+        std::fs::File::open(&manifest_path)
+            .with_context(|| format!("No checkpoint found at '{}'", manifest_path.display()))?,
+    )
+    .context("Failed to parse checkpoint manifest")?;
+    let scalars: ScalarValue =
+        serde_json::from_reader(std::fs::File::open(dir.join(&manifest.scalars))?)
+            .context("Failed to parse checkpoint scalars")?;
+    let mut frames = Vec::with_capacity(manifest.tabular.len());
+    for frame in manifest.tabular.iter() {
+        frames.push((frame.key.clone(), read_frame(dir, frame)?));
+    }
+    Ok((scalars, frames))
+}
+
+// Write-then-rename so a crash mid-write never leaves a truncated file behind
+fn write_json_atomic<T: serde::Serialize>(path: &std::path::Path, value: &T) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    serde_json::to_writer(std::fs::File::create(&tmp)?, value)
+        .with_context(|| format!("Failed to write '{}'", tmp.display()))?;
+    std::fs::rename(&tmp, path)
+        .with_context(|| format!("Failed to move checkpoint file into '{}'", path.display()))?;
+    Ok(())
+}
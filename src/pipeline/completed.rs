@@ -80,6 +80,16 @@ impl Pipeline<Completed> {
                             break;
                         }
                     }
+                    // A namespace skipped by its 'when' records its commands once, without an index
+                    if paths.is_empty()
+                        && context
+                            .scalar()
+                            .get(&base_path.with_segment("status"))
+                            .await?
+                            .is_some()
+                    {
+                        paths.push(base_path.clone());
+                    }
                     tracing::debug!(
                         command = %base_path.to_dotted(),
                         iteration_count = paths.len(),
@@ -93,86 +103,45 @@ impl Pipeline<Completed> {
                 let mut meta = HashMap::new();
                 let mut data = HashMap::new();
 
-                for result_spec in &cmd.expected_results {
-                    // Resolve one or more (field_name, kind, type_def) entries from the spec.
-                    // DerivedFromSingleAttribute over an array attribute expands to one entry
-                    // per element, keyed by the element's name_field value.
-                    let entries: Vec<(String, &ResultKind, Option<&TypeDef<String>>)> =
-                        match result_spec {
-                            ResultSpec::Field { name, kind, ty, .. } => {
-                                vec![(name.clone(), kind, Some(ty))]
-                            }
-                            ResultSpec::DerivedFromSingleAttribute {
-                                name_field,
-                                kind,
-                                ty,
-                                attribute,
-                                ..
-                            } => {
-                                let attr_value = cmd.attributes.get(attribute.as_str());
-                                match attr_value {
-                                    Some(ScalarValue::Array(arr)) => arr
-                                        .iter()
-                                        .filter_map(|elem| {
-                                            elem.as_object()
-                                                .and_then(|obj| obj.get(name_field.name()))
-                                                .and_then(|v| v.as_str())
-                                                .map(|s| (s.to_string(), kind, ty.as_ref()))
-                                        })
-                                        .collect(),
-                                    Some(v) => {
-                                        let resolved = v
-                                            .as_str()
-                                            .map(|s| s.to_string())
-                                            .unwrap_or_else(|| name_field.name().clone());
-                                        vec![(resolved, kind, ty.as_ref())]
-                                    }
-                                    None => {
-                                        vec![(name_field.name().clone(), kind, ty.as_ref())]
-                                    }
-                                }
-                            }
-                        };
-
-                    for (field_name, kind, type_def) in entries {
-                        let field_path = source.with_segment(&field_name);
-                        let is_tabular = type_def.is_some_and(|td| matches!(td, TypeDef::Tabular));
-
-                        if is_tabular {
-                            if let Some(df) = context.tabular().get(&field_path).await? {
-                                let rows_count = df.height();
-                                let columns_count = df.width();
-
-                                let file_name =
-                                    format!("{}.{}", field_path.to_dotted(), format.extension());
-                                let file_path = output_path.join(&file_name);
-                                write_tabular(&df, &file_path, &format)?;
-
-                                data.insert(
-                                    field_path,
-                                    ResultValue::Tabular {
-                                        path: file_path,
-                                        format: format.clone(),
-                                        rows_count,
-                                        columns_count,
-                                    },
-                                );
+                for (field, kind, type_def) in cmd.result_fields() {
+                    let field_path = source.join(&field);
+                    let is_tabular = type_def.is_some_and(|td| matches!(td, TypeDef::Tabular));
+
+                    if is_tabular {
+                        if let Some(df) = context.tabular().get(&field_path).await? {
+                            let rows_count = df.height();
+                            let columns_count = df.width();
+
+                            let file_name =
+                                format!("{}.{}", field_path.to_dotted(), format.extension());
+                            let file_path = output_path.join(&file_name);
+                            write_tabular(&df, &file_path, &format)?;
+
+                            data.insert(
+                                field_path,
+                                ResultValue::Tabular {
+                                    path: file_path,
+                                    format: format.clone(),
+                                    rows_count,
+                                    columns_count,
+                                },
+                            );
+                        }
+                    } else if let Some(value) = context.scalar().get(&field_path).await? {
+                        let value = context.scalar().redact_value(&value);
+                        match kind {
+                            ResultKind::Meta => {
+                                meta.insert(field_path, value);
                             }
-                        } else if let Some(value) = context.scalar().get(&field_path).await? {
-                            match kind {
-                                ResultKind::Meta => {
-                                    meta.insert(field_path, value);
-                                }
-                                ResultKind::Data => {
-                                    let ty = type_def
-                                        .and_then(|td| match td {
-                                            TypeDef::Scalar(st) => Some(st.clone()),
-                                            _ => None,
-                                        })
-                                        .unwrap_or_else(|| scalar_type_of(&value));
-
-                                    data.insert(field_path, ResultValue::Scalar { ty, value });
-                                }
+                            ResultKind::Data => {
+                                let ty = type_def
+                                    .and_then(|td| match td {
+                                        TypeDef::Scalar(st) => Some(st.clone()),
+                                        _ => None,
+                                    })
+                                    .unwrap_or_else(|| scalar_type_of(&value));
+
+                                data.insert(field_path, ResultValue::Scalar { ty, value });
                             }
                         }
                     }
@@ -198,11 +167,21 @@ impl Pipeline<Completed> {
         Ok(ResultStore { results })
     }
 
+    // Stores as the run left them, for inspecting values beyond what results() collects
+    pub fn context(&self) -> &ExecutionContext {
+        &self.state.context
+    }
+
     pub fn restart(self) -> Pipeline<Ready> {
         Pipeline::<Ready> {
             services: self.services,
             namespaces: self.namespaces,
             commands: self.commands,
+            registry: self.registry,
+            settings: self.settings,
+            inputs: self.inputs,
+            input_values: self.input_values,
+            secrets: self.secrets,
             state: Ready,
         }
     }
@@ -212,6 +191,11 @@ impl Pipeline<Completed> {
             services: self.services,
             namespaces: self.namespaces,
             commands: self.commands,
+            registry: self.registry,
+            settings: self.settings,
+            inputs: self.inputs,
+            input_values: self.input_values,
+            secrets: self.secrets,
             state: Draft,
         }
     }
@@ -0,0 +1,417 @@
+use super::Draft;
+use crate::imports::*;
+use std::collections::BTreeMap;
+
+/*
+    Types:
+    * PipelineDocument - Declarative pipeline definition, loadable from / writable to YAML, JSON or TOML
+    * InputDocument - A declared pipeline input, its type, default and hint
+    * NamespaceDocument - A namespace, its execution mode, mode-specific options and commands
+    * NamespaceMode - Execution mode of a namespace in a document (once, iterative, static)
+    * CommandDocument - A command by name, command type and raw (unsubstituted) attributes
+    * DocumentFormat - Serialisation format of a pipeline document
+*/
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineDocument {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<InputDocument>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<String>, // Names only, values come from the pipeline's secret providers
+    #[serde(default)]
+    pub namespaces: Vec<NamespaceDocument>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InputDocument {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: ScalarType,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<ScalarValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NamespaceDocument {
+    pub name: String,
+    #[serde(default)]
+    pub mode: NamespaceMode,
+    // Iterative only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store_path: Option<String>, // Dotted store path to iterate over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iter_var: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_var: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallel_iterations: Option<usize>,
+    // Once and Iterative
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_parallelism: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>, // Tera expression, the namespace is skipped when it's falsy
+    // Iterative only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iterator: Option<IteratorType>,
+    // Static only
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub values: BTreeMap<String, ScalarValue>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<StaticSource>, // Loaded over `values` in order when the namespace is added
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<CommandDocument>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NamespaceMode {
+    #[default]
+    Once,
+    Iterative,
+    Static,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandDocument {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub command_type: String, // Descriptor::command_type, e.g. "FileCommand"
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attrs: BTreeMap<String, ScalarValue>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl DocumentFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentFormat::Yaml => "yaml",
+            DocumentFormat::Json => "json",
+            DocumentFormat::Toml => "toml",
+        }
+    }
+
+    // Picks the format from a file extension (.yaml / .yml, .json, .toml)
+    pub fn from_path(path: &std::path::Path) -> Result<Self> {
+        let extension = path.extension().and_then(|e| e.to_str()).ok_or_else(|| {
+            anyhow::anyhow!("Pipeline file '{}' has no extension", path.display())
+        })?;
+        match extension.to_ascii_lowercase().as_str() {
+            "yml" => Ok(DocumentFormat::Yaml),
+            other => other
+                .parse()
+                .with_context(|| format!("Unsupported pipeline file '{}'", path.display())),
+        }
+    }
+}
+
+impl std::fmt::Display for DocumentFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for DocumentFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "yaml" => Ok(DocumentFormat::Yaml),
+            "json" => Ok(DocumentFormat::Json),
+            "toml" => Ok(DocumentFormat::Toml),
+            other => Err(anyhow::anyhow!(
+                "Unknown document format '{}', expected 'yaml', 'json' or 'toml'",
+                other
+            )),
+        }
+    }
+}
+
+impl PipelineDocument {
+    pub fn parse(input: &str, format: DocumentFormat) -> Result<Self> {
+        match format {
+            DocumentFormat::Yaml => serde_yaml::from_str(input).map_err(anyhow::Error::from),
+            DocumentFormat::Json => serde_json::from_str(input).map_err(anyhow::Error::from),
+            DocumentFormat::Toml => toml::from_str(input).map_err(anyhow::Error::from),
+        }
+        .with_context(|| format!("Failed to parse {} pipeline document", format))
+    }
+
+    pub fn render(&self, format: DocumentFormat) -> Result<String> {
+        match format {
+            DocumentFormat::Yaml => serde_yaml::to_string(self).map_err(anyhow::Error::from),
+            DocumentFormat::Json => serde_json::to_string_pretty(self).map_err(anyhow::Error::from),
+            DocumentFormat::Toml => toml::to_string(self).map_err(anyhow::Error::from),
+        }
+        .with_context(|| format!("Failed to serialise pipeline document as {}", format))
+    }
+
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let format = DocumentFormat::from_path(path)?;
+        let input = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read pipeline file '{}'", path.display()))?;
+        Self::parse(&input, format).with_context(|| format!("In '{}'", path.display()))
+    }
+
+    pub fn to_file(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        let path = path.as_ref();
+        let output = self.render(DocumentFormat::from_path(path)?)?;
+        std::fs::write(path, output)
+            .with_context(|| format!("Failed to write pipeline file '{}'", path.display()))
+    }
+}
+
+impl NamespaceDocument {
+    // Rejects options that don't apply to the namespace's mode, they're almost always a typo'd mode
+    fn check_mode_options(&self) -> Result<()> {
+        let misplaced = match self.mode {
+            NamespaceMode::Once => [
+                ("store_path", self.store_path.is_some()),
+                ("iterator", self.iterator.is_some()),
+                ("iter_var", self.iter_var.is_some()),
+                ("index_var", self.index_var.is_some()),
+                ("parallel_iterations", self.parallel_iterations.is_some()),
+                ("values", !self.values.is_empty()),
+                ("sources", !self.sources.is_empty()),
+            ]
+            .to_vec(),
+            NamespaceMode::Iterative => [
+                ("values", !self.values.is_empty()),
+                ("sources", !self.sources.is_empty()),
+            ]
+            .to_vec(),
+            NamespaceMode::Static => [
+                ("store_path", self.store_path.is_some()),
+                ("iterator", self.iterator.is_some()),
+                ("iter_var", self.iter_var.is_some()),
+                ("index_var", self.index_var.is_some()),
+                ("parallel_iterations", self.parallel_iterations.is_some()),
+                ("max_parallelism", self.max_parallelism.is_some()),
+                ("when", self.when.is_some()),
+                ("commands", !self.commands.is_empty()),
+            ]
+            .to_vec(),
+        };
+        if let Some((field, _)) = misplaced.into_iter().find(|(_, set)| *set) {
+            return Err(anyhow::anyhow!(
+                "'{}' is not valid for {:?} namespace '{}'",
+                field,
+                self.mode,
+                self.name
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Pipeline<Draft> {
+    pub async fn from_document(document: &PipelineDocument) -> Result<Self> {
+        let mut pipeline = Pipeline::new();
+        pipeline.load_document(document).await?;
+        Ok(pipeline)
+    }
+
+    // Adds the document's inputs, secrets, namespaces and commands to this pipeline, in document order
+    #[tracing::instrument(skip(self, document), err, fields(namespace_count = document.namespaces.len()))]
+    pub async fn load_document(&mut self, document: &PipelineDocument) -> Result<()> {
+        for input in &document.inputs {
+            let mut builder =
+                AttributeSpecBuilder::new(input.name.clone(), TypeDef::Scalar(input.ty.clone()));
+            if input.required {
+                builder = builder.required();
+            }
+            if let Some(default) = &input.default {
+                builder = builder.default_value(default.clone());
+            }
+            if let Some(hint) = &input.hint {
+                builder = builder.hint(hint.clone());
+            }
+            self.add_input(builder.build())?;
+        }
+        for secret in &document.secrets {
+            self.add_secret(secret)?;
+        }
+        for namespace in &document.namespaces {
+            namespace.check_mode_options()?;
+            let namespace_index = match namespace.mode {
+                NamespaceMode::Once => {
+                    let mut builder = NamespaceBuilder::new(&namespace.name);
+                    if let Some(max_parallelism) = namespace.max_parallelism {
+                        builder = builder.max_parallelism(max_parallelism);
+                    }
+                    if let Some(when) = &namespace.when {
+                        builder = builder.when(when);
+                    }
+                    self.add_namespace(builder).await?.namespace_index()
+                }
+                NamespaceMode::Iterative => {
+                    let mut builder = NamespaceBuilder::new(&namespace.name).iterative();
+                    if let Some(store_path) = &namespace.store_path {
+                        builder = builder.store_path(StorePath::from_dotted(store_path));
+                    }
+                    if let Some(iterator) = &namespace.iterator {
+                        builder = builder.source(iterator.clone());
+                    }
+                    if let Some(iter_var) = &namespace.iter_var {
+                        builder = builder.iter_var(iter_var);
+                    }
+                    if let Some(index_var) = &namespace.index_var {
+                        builder = builder.index_var(index_var);
+                    }
+                    if let Some(max_parallelism) = namespace.max_parallelism {
+                        builder = builder.max_parallelism(max_parallelism);
+                    }
+                    if let Some(parallel_iterations) = namespace.parallel_iterations {
+                        builder = builder.parallel_iterations(parallel_iterations);
+                    }
+                    if let Some(when) = &namespace.when {
+                        builder = builder.when(when);
+                    }
+                    self.add_namespace(builder)
+                        .await
+                        .with_context(|| format!("Invalid namespace '{}'", namespace.name))?
+                        .namespace_index()
+                }
+                NamespaceMode::Static => {
+                    let builder = namespace.values.iter().fold(
+                        NamespaceBuilder::new(&namespace.name).static_ns(),
+                        |builder, (key, value)| builder.insert(key, value.clone()),
+                    );
+                    let builder =
+                        namespace
+                            .sources
+                            .iter()
+                            .fold(builder, |builder, source| match source {
+                                StaticSource::File { path } => builder.load_file(path),
+                                StaticSource::Env { prefix } => builder.load_env(prefix),
+                                StaticSource::Dotenv { path, prefix } => {
+                                    builder.load_dotenv(path, prefix)
+                                }
+                            });
+                    self.add_namespace(builder).await?.namespace_index()
+                }
+            };
+
+            for command in &namespace.commands {
+                let attributes: Attributes = command
+                    .attrs
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                self.add_command_by_type(
+                    namespace_index,
+                    &command.name,
+                    &command.command_type,
+                    &attributes,
+                )
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to add command '{}.{}'",
+                        namespace.name, command.name
+                    )
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T> Pipeline<T> {
+    // The pipeline's inputs, secret names, namespaces and commands as a document. Services, execution settings
+    // and supplied input values aren't included. Typed arrays and objects are written as plain 'array' / 'object' inputs.
+    pub fn to_document(&self) -> PipelineDocument {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|input| InputDocument {
+                name: input.name.clone(),
+                ty: match &input.ty {
+                    TypeDef::Scalar(ty) => ty.clone(),
+                    TypeDef::ArrayOf(_) => ScalarType::Array,
+                    TypeDef::ObjectOf { .. } => ScalarType::Object,
+                    TypeDef::Tabular => unreachable!("add_input rejects Tabular inputs"),
+                },
+                required: input.required,
+                default: input.default_value.clone(),
+                hint: input.hint.clone(),
+            })
+            .collect();
+        let namespaces = self
+            .namespaces
+            .iter()
+            .enumerate()
+            .map(|(ns_idx, namespace)| {
+                let mut document = NamespaceDocument {
+                    name: namespace.name().to_string(),
+                    ..Default::default()
+                };
+                match namespace.ty() {
+                    ExecutionMode::Once => {}
+                    ExecutionMode::Iterative {
+                        store_path,
+                        source,
+                        iter_var,
+                        index_var,
+                    } => {
+                        document.mode = NamespaceMode::Iterative;
+                        document.store_path = Some(store_path.to_dotted());
+                        document.iterator = Some(source.clone());
+                        document.iter_var = iter_var.clone();
+                        document.index_var = index_var.clone();
+                        document.parallel_iterations =
+                            Some(namespace.iteration_parallelism()).filter(|&n| n > 1);
+                    }
+                    // What the values were built from rather than the loaded values, which can hold
+                    // environment variables and are read again when the document is loaded
+                    ExecutionMode::Static { .. } => {
+                        document.mode = NamespaceMode::Static;
+                        document.values = namespace
+                            .inserted_values()
+                            .iter()
+                            .map(|(k, v)| (k.clone(), v.clone()))
+                            .collect();
+                        document.sources = namespace.static_sources().to_vec();
+                    }
+                }
+                if document.mode != NamespaceMode::Static {
+                    document.max_parallelism = Some(namespace.max_parallelism()).filter(|&n| n > 1);
+                    document.when = namespace.when().map(str::to_string);
+                }
+                document.commands = self
+                    .commands
+                    .iter()
+                    .filter(|c| c.namespace_index == ns_idx)
+                    .map(|c| CommandDocument {
+                        name: c.name.clone(),
+                        command_type: c.command_type.clone(),
+                        attrs: c
+                            .attributes
+                            .iter()
+                            .map(|(k, v)| (k.clone(), v.clone()))
+                            .collect(),
+                    })
+                    .collect();
+                document
+            })
+            .collect();
+        PipelineDocument {
+            inputs,
+            secrets: self.secrets.clone(),
+            namespaces,
+        }
+    }
+}
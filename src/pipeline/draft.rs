@@ -1,6 +1,7 @@
 use super::{Draft, Ready};
 use crate::imports::*;
 use crate::namespace::sealed::Build;
+use crate::pipeline::validation::validate_value;
 
 impl Pipeline<Draft> {
     pub fn new() -> Self {
@@ -8,6 +9,11 @@ impl Pipeline<Draft> {
             services: PipelineServices::default(),
             namespaces: Vec::new(),
             commands: Vec::new(),
+            registry: Arc::new(CommandRegistry::default()),
+            settings: ExecutionSettings::default(),
+            inputs: Vec::new(),
+            input_values: Attributes::new(),
+            secrets: Vec::new(),
             state: Draft,
         }
     }
@@ -61,6 +67,13 @@ impl Pipeline<Draft> {
                 namespace.name()
             ));
         }
+        if self.is_declared_namespace(namespace.name()) {
+            return Err(anyhow::anyhow!(
+                "Namespace name '{}' is reserved for the pipeline's {}",
+                namespace.name(),
+                namespace.name()
+            ));
+        }
         self.namespaces.push(namespace);
         let index = self.namespaces.len() - 1;
         self.services
@@ -83,6 +96,89 @@ impl Pipeline<Draft> {
         })
     }
 
+    // Declares an input supplied with Pipeline<Ready>::with_inputs, commands read it from 'inputs.<name>'.
+    // Required inputs without a default must be supplied, a default must match the input's type.
+    pub fn add_input(&mut self, spec: impl Into<AttributeSpec<String>>) -> Result<()> {
+        let spec = spec.into();
+        tracing::debug!(input_name = %spec.name, required = spec.required, "Adding input to Pipeline");
+        if self.inputs.iter().any(|input| input.name == spec.name) {
+            return Err(anyhow::anyhow!(
+                "Input with name '{}' already exists",
+                spec.name
+            ));
+        }
+        if matches!(spec.ty, TypeDef::Tabular) {
+            return Err(anyhow::anyhow!(
+                "Input '{}' can't be Tabular, inputs are scalar values",
+                spec.name
+            ));
+        }
+        if self
+            .namespaces
+            .iter()
+            .any(|ns| ns.name() == INPUTS_NAMESPACE)
+        {
+            return Err(anyhow::anyhow!(
+                "Can't declare input '{}', a namespace is already named '{}'",
+                spec.name,
+                INPUTS_NAMESPACE
+            ));
+        }
+        if let Some(default) = &spec.default_value {
+            validate_value(
+                default,
+                &spec.ty,
+                &format!("{}.{}", INPUTS_NAMESPACE, spec.name),
+            )
+            .with_context(|| format!("Invalid default for input '{}'", spec.name))?;
+        }
+        self.inputs.push(spec);
+        Ok(())
+    }
+
+    // Declares a secret looked up in the pipeline's secret providers when it executes. Commands read it from
+    // 'secrets.<name>', and its value is redacted from errors, results and checkpoints.
+    pub fn add_secret(&mut self, name: &str) -> Result<()> {
+        tracing::debug!(secret_name = %name, "Adding secret to Pipeline");
+        if self.secrets.iter().any(|secret| secret == name) {
+            return Err(anyhow::anyhow!(
+                "Secret with name '{}' already exists",
+                name
+            ));
+        }
+        if self
+            .namespaces
+            .iter()
+            .any(|ns| ns.name() == SECRETS_NAMESPACE)
+        {
+            return Err(anyhow::anyhow!(
+                "Can't declare secret '{}', a namespace is already named '{}'",
+                name,
+                SECRETS_NAMESPACE
+            ));
+        }
+        self.secrets.push(name.to_string());
+        Ok(())
+    }
+
+    // 'inputs' and 'secrets' are only reserved once the pipeline declares some
+    fn is_declared_namespace(&self, name: &str) -> bool {
+        (name == INPUTS_NAMESPACE && !self.inputs.is_empty())
+            || (name == SECRETS_NAMESPACE && !self.secrets.is_empty())
+    }
+
+    // Replaces the command registry, e.g. with one holding third-party commands
+    pub fn with_registry(mut self, registry: CommandRegistry) -> Self {
+        self.registry = Arc::new(registry);
+        self
+    }
+
+    // Makes T addressable by its command type, see add_command_by_type and pipeline documents
+    pub fn register_command<T: Command>(&mut self) -> Result<()> {
+        Arc::make_mut(&mut self.registry).register::<T>()?;
+        Ok(())
+    }
+
     // Used by the namespace handle to add commands - hence pub(crate)
     pub(crate) async fn add_command<T>(
         &mut self,
@@ -93,11 +189,52 @@ impl Pipeline<Draft> {
     where
         T: Command,
     {
-        let (ns_name, cmd_name, cmd_type) = (
-            self.namespaces[namespace].name(),
-            name.to_string(),
+        self.push_command(
+            namespace,
+            name,
             T::command_type(),
-        );
+            attrs,
+            CommandSpec::new::<T>,
+        )
+        .await
+    }
+
+    // As add_command, with the command type looked up in the registry
+    pub(crate) async fn add_command_by_type(
+        &mut self,
+        namespace: usize,
+        name: &str,
+        command_type: &str,
+        attrs: &Attributes,
+    ) -> Result<()> {
+        let registered = self.registry.get(command_type).cloned().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown command type '{}', registered types are: {}",
+                command_type,
+                self.registry.command_types().join(", ")
+            )
+        })?;
+        self.push_command(
+            namespace,
+            name,
+            registered.command_type(),
+            attrs,
+            |namespace_index, name, attributes| {
+                registered.new_spec(namespace_index, name, attributes)
+            },
+        )
+        .await
+    }
+
+    async fn push_command(
+        &mut self,
+        namespace: usize,
+        name: &str,
+        cmd_type: &str,
+        attrs: &Attributes,
+        new_spec: impl FnOnce(usize, String, Attributes) -> Result<CommandSpec>,
+    ) -> Result<()> {
+        let (ns_name, cmd_name) = (self.namespaces[namespace].name(), name.to_string());
         tracing::debug!(
             namespace = %ns_name,
             command_name = %cmd_name,
@@ -122,16 +259,13 @@ impl Pipeline<Draft> {
             }
         }
 
-        self.commands.push(CommandSpec::new::<T>(
-            namespace,
-            name.to_string(),
-            attrs.clone(),
-        )?);
+        self.commands
+            .push(new_spec(namespace, name.to_string(), attrs.clone())?);
         self.services
             .after_added_command(hook_events::CommandInit {
                 namespace_index: namespace,
                 command_name: name.to_string(),
-                command_type: T::command_type().to_string(),
+                command_type: cmd_type.to_string(),
             })
             .await?;
         tracing::debug!(
@@ -156,8 +290,7 @@ impl Pipeline<Draft> {
 
         // Namespace + Command name validation
         let mut namespace_names = HashSet::new();
-        let mut command_names_per_namespace: HashMap<&str, HashSet<&str>> = HashMap::new();
-        for (ns_name, cmd_name) in self.command_ns_pairs_iter() {
+        for ns_name in self.namespaces.iter().map(|ns| ns.name()) {
             // Check namespace names
             if !namespace_names.insert(ns_name) {
                 tracing::warn!(
@@ -181,7 +314,17 @@ impl Pipeline<Draft> {
                     ns_name
                 ));
             }
+            if self.is_declared_namespace(ns_name) {
+                return Err(anyhow::anyhow!(
+                    "Namespace name '{}' is reserved for the pipeline's {}",
+                    ns_name,
+                    ns_name
+                ));
+            }
+        }
 
+        let mut command_names_per_namespace: HashMap<&str, HashSet<&str>> = HashMap::new();
+        for (ns_name, cmd_name) in self.command_ns_pairs_iter() {
             // Check command names within namespace
             let cmd_set = command_names_per_namespace.entry(ns_name).or_default();
             if !cmd_set.insert(cmd_name) {
@@ -299,6 +442,11 @@ impl Pipeline<Draft> {
             services: self.services,
             namespaces: self.namespaces,
             commands: self.commands,
+            registry: self.registry,
+            settings: self.settings,
+            inputs: self.inputs,
+            input_values: self.input_values,
+            secrets: self.secrets,
             state: Ready,
         })
     }
@@ -0,0 +1,202 @@
+use crate::imports::*;
+
+/*
+    Types:
+    * PipelineExplanation - Dry-run view of a Ready pipeline: namespaces in execution order and what each command will do
+    * NamespaceExplanation - A namespace, how it executes and which namespaces it waits for
+    * CommandExplanation - A command, its wave, dependencies, extension edges and expected result paths
+    * RequiredExtension - An extension a command requires and the command providing it
+    * ExplainedResult - A result path the command is expected to produce
+*/
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PipelineExplanation {
+    pub namespaces: Vec<NamespaceExplanation>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct NamespaceExplanation {
+    pub name: String,
+    pub mode: String,                  // "once", "iterative" or "static"
+    pub iterates_over: Option<String>, // Store path an iterative namespace iterates over
+    pub when: Option<String>,          // Condition the namespace's commands are skipped on
+    pub depends_on: Vec<String>,       // Namespaces that finish before this one starts
+    pub commands: Vec<CommandExplanation>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CommandExplanation {
+    pub name: String,
+    pub command_type: String,
+    pub wave: usize, // Commands in the same wave may run together when the namespace allows it
+    pub dependencies: Vec<String>,
+    pub provides_extensions: Vec<String>,
+    pub requires_extensions: Vec<RequiredExtension>,
+    pub results: Vec<ExplainedResult>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RequiredExtension {
+    pub extension: String,
+    pub provided_by: Option<String>, // "namespace.command"
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ExplainedResult {
+    pub path: String, // Iterative namespaces use an '<index>' placeholder segment
+    pub kind: String, // "data" or "meta"
+    pub tabular: bool,
+}
+
+impl PipelineExplanation {
+    pub(crate) fn new(namespaces: &[Namespace], commands: &[CommandSpec]) -> Result<Self> {
+        let plan = ExecutionPlan::new(namespaces, commands)?;
+
+        let mut providers: HashMap<&ExtensionKey, String> = HashMap::new();
+        for command in commands {
+            for key in &command.provides_extensions {
+                let ns_name = namespaces[command.namespace_index].name();
+                providers.insert(key, format!("{}.{}", ns_name, command.name));
+            }
+        }
+
+        let mut explained = Vec::with_capacity(namespaces.len());
+        for &ns_idx in plan.namespace_order() {
+            let group = plan.group(ns_idx)?;
+            let namespace = group.namespace;
+            let (mode, iterates_over) = match namespace.ty() {
+                ExecutionMode::Once => ("once", None),
+                ExecutionMode::Iterative { store_path, .. } => {
+                    ("iterative", Some(store_path.to_dotted()))
+                }
+                ExecutionMode::Static { .. } => ("static", None),
+            };
+            let iterative = iterates_over.is_some();
+
+            let mut explained_commands = Vec::with_capacity(group.commands.len());
+            for command in &group.commands {
+                let wave = group
+                    .waves
+                    .iter()
+                    .position(|wave| wave.iter().any(|c| std::ptr::eq(*c, *command)))
+                    .unwrap_or(0);
+                let mut prefix = StorePath::from_segments([namespace.name(), &command.name]);
+                if iterative {
+                    prefix = prefix.with_segment("<index>");
+                }
+                let mut dependencies: Vec<String> =
+                    command.dependencies.iter().map(|d| d.to_dotted()).collect();
+                dependencies.sort();
+
+                explained_commands.push(CommandExplanation {
+                    name: command.name.clone(),
+                    command_type: command.command_type.clone(),
+                    wave,
+                    dependencies,
+                    provides_extensions: command
+                        .provides_extensions
+                        .iter()
+                        .map(|k| k.type_name().to_string())
+                        .collect(),
+                    requires_extensions: command
+                        .requires_extensions
+                        .iter()
+                        .map(|k| RequiredExtension {
+                            extension: k.type_name().to_string(),
+                            provided_by: providers.get(k).cloned(),
+                        })
+                        .collect(),
+                    results: command
+                        .result_fields()
+                        .into_iter()
+                        .map(|(field, kind, ty)| ExplainedResult {
+                            path: prefix.join(&field).to_dotted(),
+                            kind: match kind {
+                                ResultKind::Data => "data",
+                                ResultKind::Meta => "meta",
+                            }
+                            .to_string(),
+                            tabular: matches!(ty, Some(TypeDef::Tabular)),
+                        })
+                        .collect(),
+                });
+            }
+
+            explained.push(NamespaceExplanation {
+                name: namespace.name().to_string(),
+                mode: mode.to_string(),
+                iterates_over,
+                when: namespace.when().map(str::to_string),
+                depends_on: plan
+                    .dependencies_of(ns_idx)
+                    .into_iter()
+                    .map(|idx| namespaces[idx].name().to_string())
+                    .collect(),
+                commands: explained_commands,
+            });
+        }
+        Ok(PipelineExplanation {
+            namespaces: explained,
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialise pipeline explanation")
+    }
+}
+
+impl std::fmt::Display for PipelineExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let command_count: usize = self.namespaces.iter().map(|ns| ns.commands.len()).sum();
+        writeln!(
+            f,
+            "Execution plan: {} namespaces, {} commands",
+            self.namespaces.len(),
+            command_count
+        )?;
+        for (i, namespace) in self.namespaces.iter().enumerate() {
+            write!(f, "{}. {} [{}", i + 1, namespace.name, namespace.mode)?;
+            if let Some(source) = &namespace.iterates_over {
+                write!(f, " over {}", source)?;
+            }
+            write!(f, "]")?;
+            if !namespace.depends_on.is_empty() {
+                write!(f, " after {}", namespace.depends_on.join(", "))?;
+            }
+            if let Some(when) = &namespace.when {
+                write!(f, " when {}", when)?;
+            }
+            writeln!(f)?;
+            for command in &namespace.commands {
+                writeln!(
+                    f,
+                    "   - {} ({}) wave {}",
+                    command.name, command.command_type, command.wave
+                )?;
+                if !command.dependencies.is_empty() {
+                    writeln!(f, "       depends on: {}", command.dependencies.join(", "))?;
+                }
+                for extension in &command.provides_extensions {
+                    writeln!(f, "       provides: {}", extension)?;
+                }
+                for required in &command.requires_extensions {
+                    writeln!(
+                        f,
+                        "       requires: {} (from {})",
+                        required.extension,
+                        required.provided_by.as_deref().unwrap_or("nothing")
+                    )?;
+                }
+                for result in &command.results {
+                    writeln!(
+                        f,
+                        "       -> {} ({}{})",
+                        result.path,
+                        result.kind,
+                        if result.tabular { ", tabular" } else { "" }
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,363 @@
+use crate::imports::*;
+
+/*
+    Types:
+    * PipelineGraph - Nodes (namespaces, commands) and typed edges of a pipeline, exportable as DOT or Mermaid
+    * GraphNode - A namespace or command, identified by its dotted path
+    * GraphNodeKind - Whether a node is a namespace or a command
+    * GraphEdge - A directed edge from the node producing something to the node consuming it
+    * GraphEdgeKind - Why the edge exists (store path dependency, iterative source, extension provider)
+*/
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PipelineGraph {
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct GraphNode {
+    pub id: String,        // "namespace" or "namespace.command"
+    pub namespace: String, // Namespace the node belongs to (its own name for namespace nodes)
+    pub label: String,     // Execution mode for namespaces, command type for commands
+    pub kind: GraphNodeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphNodeKind {
+    Namespace,
+    Command,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: GraphEdgeKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum GraphEdgeKind {
+    StorePath { path: String }, // `to` reads `path`, which `from` produces
+    IterativeSource { path: String }, // `to` is an iterative namespace iterating over `path`
+    Condition { path: String }, // `to` is a namespace whose 'when' reads `path`
+    ExtensionProvider { extension: String }, // `to` requires an extension `from` provides
+}
+
+impl GraphEdgeKind {
+    fn label(&self) -> &str {
+        match self {
+            GraphEdgeKind::StorePath { path } => path,
+            GraphEdgeKind::IterativeSource { path } => path,
+            GraphEdgeKind::Condition { path } => path,
+            GraphEdgeKind::ExtensionProvider { extension } => extension,
+        }
+    }
+}
+
+impl PipelineGraph {
+    pub(crate) fn new(namespaces: &[Namespace], commands: &[CommandSpec]) -> Self {
+        let mut nodes = Vec::with_capacity(namespaces.len() + commands.len());
+        for namespace in namespaces {
+            let mode = match namespace.ty() {
+                ExecutionMode::Once => "once",
+                ExecutionMode::Iterative { .. } => "iterative",
+                ExecutionMode::Static { .. } => "static",
+            };
+            nodes.push(GraphNode {
+                id: namespace.name().to_string(),
+                namespace: namespace.name().to_string(),
+                label: mode.to_string(),
+                kind: GraphNodeKind::Namespace,
+            });
+        }
+        let command_ids: Vec<String> = commands
+            .iter()
+            .map(|c| format!("{}.{}", namespaces[c.namespace_index].name(), c.name))
+            .collect();
+        for (command, id) in commands.iter().zip(&command_ids) {
+            nodes.push(GraphNode {
+                id: id.clone(),
+                namespace: namespaces[command.namespace_index].name().to_string(),
+                label: command.command_type.clone(),
+                kind: GraphNodeKind::Command,
+            });
+        }
+
+        // A path is produced by the command whose prefix it starts with, otherwise by its (static) namespace
+        let producer_of = |path: &StorePath| -> Option<String> {
+            let dotted = path.to_dotted();
+            command_ids
+                .iter()
+                .find(|id| dotted == **id || dotted.starts_with(&format!("{}.", id)))
+                .cloned()
+                .or_else(|| {
+                    let ns_name = path.namespace()?;
+                    namespaces
+                        .iter()
+                        .find(|ns| ns.name() == ns_name.as_str())
+                        .map(|ns| ns.name().to_string())
+                })
+        };
+
+        let mut edges = Vec::new();
+        for namespace in namespaces {
+            if let ExecutionMode::Iterative { store_path, .. } = namespace.ty()
+                && let Some(from) = producer_of(store_path)
+            {
+                edges.push(GraphEdge {
+                    from,
+                    to: namespace.name().to_string(),
+                    kind: GraphEdgeKind::IterativeSource {
+                        path: store_path.to_dotted(),
+                    },
+                });
+            }
+        }
+        for namespace in namespaces {
+            let mut dependencies: Vec<&StorePath> = namespace.when_dependencies().iter().collect();
+            dependencies.sort_by_key(|path| path.to_dotted());
+            for dependency in dependencies {
+                if let Some(from) = producer_of(dependency) {
+                    edges.push(GraphEdge {
+                        from,
+                        to: namespace.name().to_string(),
+                        kind: GraphEdgeKind::Condition {
+                            path: dependency.to_dotted(),
+                        },
+                    });
+                }
+            }
+        }
+        for (command, id) in commands.iter().zip(&command_ids) {
+            let mut dependencies: Vec<&StorePath> = command.dependencies.iter().collect();
+            dependencies.sort_by_key(|path| path.to_dotted());
+            for dependency in dependencies {
+                if let Some(from) = producer_of(dependency)
+                    && &from != id
+                {
+                    edges.push(GraphEdge {
+                        from,
+                        to: id.clone(),
+                        kind: GraphEdgeKind::StorePath {
+                            path: dependency.to_dotted(),
+                        },
+                    });
+                }
+            }
+            for required in &command.requires_extensions {
+                for (provider, provider_id) in commands.iter().zip(&command_ids) {
+                    if provider.provides_extensions.contains(required) {
+                        edges.push(GraphEdge {
+                            from: provider_id.clone(),
+                            to: id.clone(),
+                            kind: GraphEdgeKind::ExtensionProvider {
+                                extension: required.type_name().to_string(),
+                            },
+                        });
+                    }
+                }
+            }
+        }
+        PipelineGraph { nodes, edges }
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &GraphNode> {
+        self.nodes.iter()
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = &GraphEdge> {
+        self.edges.iter()
+    }
+
+    // The given nodes plus everything they need: producers of the paths they read, the sources their
+    // namespaces iterate over and the providers of extensions they require, transitively.
+    pub fn upstream_of<S: AsRef<str>>(&self, ids: &[S]) -> HashSet<String> {
+        self.closure(ids, |id| {
+            let mut next: Vec<&str> = self
+                .edges
+                .iter()
+                .filter(|e| e.to == id)
+                .map(|e| e.from.as_str())
+                .collect();
+            // A command also needs whatever its (iterative) namespace iterates over
+            if let Some(node) = self.node(id)
+                && node.kind == GraphNodeKind::Command
+            {
+                next.push(node.namespace.as_str());
+            }
+            next
+        })
+    }
+
+    // The given nodes plus everything that reads from them, transitively. The commands of a namespace
+    // are downstream of it, so everything in an iterative namespace is downstream of its source.
+    pub fn downstream_of<S: AsRef<str>>(&self, ids: &[S]) -> HashSet<String> {
+        self.closure(ids, |id| {
+            let mut next: Vec<&str> = self
+                .edges
+                .iter()
+                .filter(|e| e.from == id)
+                .map(|e| e.to.as_str())
+                .collect();
+            if let Some(node) = self.node(id)
+                && node.kind == GraphNodeKind::Namespace
+            {
+                next.extend(
+                    self.nodes
+                        .iter()
+                        .filter(|n| n.kind == GraphNodeKind::Command && n.namespace == id)
+                        .map(|n| n.id.as_str()),
+                );
+            }
+            next
+        })
+    }
+
+    fn closure<'a, S, F>(&'a self, ids: &[S], neighbours: F) -> HashSet<String>
+    where
+        S: AsRef<str>,
+        F: Fn(&str) -> Vec<&'a str>,
+    {
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut stack: Vec<String> = ids.iter().map(|id| id.as_ref().to_string()).collect();
+        while let Some(id) = stack.pop() {
+            if seen.contains(&id) {
+                continue;
+            }
+            stack.extend(
+                neighbours(&id)
+                    .into_iter()
+                    .filter(|n| !seen.contains(*n))
+                    .map(String::from),
+            );
+            seen.insert(id);
+        }
+        seen
+    }
+
+    fn node(&self, id: &str) -> Option<&GraphNode> {
+        self.nodes.iter().find(|n| n.id == id)
+    }
+
+    // Graphviz DOT, one cluster per namespace holding the namespace node and its commands
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph pipeline {\n    rankdir=LR;\n    node [shape=box];\n");
+        for (ns_idx, namespace) in self.namespace_nodes().enumerate() {
+            out.push_str(&format!(
+                "    subgraph \"cluster_{}\" {{\n        label=\"{}\";\n",
+                ns_idx,
+                dot_escape(&namespace.id)
+            ));
+            out.push_str(&format!(
+                "        \"{}\" [shape=folder, label=\"{} ({})\"];\n",
+                dot_escape(&namespace.id),
+                dot_escape(&namespace.id),
+                dot_escape(&namespace.label)
+            ));
+            for command in self.commands_in(&namespace.id) {
+                let name = command
+                    .id
+                    .strip_prefix(&format!("{}.", namespace.id))
+                    .unwrap_or(&command.id);
+                out.push_str(&format!(
+                    "        \"{}\" [label=\"{}\\n{}\"];\n",
+                    dot_escape(&command.id),
+                    dot_escape(name),
+                    dot_escape(&command.label)
+                ));
+            }
+            out.push_str("    }\n");
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                GraphEdgeKind::StorePath { .. } => "solid",
+                GraphEdgeKind::IterativeSource { .. } => "dashed",
+                GraphEdgeKind::Condition { .. } => "dotted",
+                GraphEdgeKind::ExtensionProvider { .. } => "bold",
+            };
+            out.push_str(&format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\", style={}];\n",
+                dot_escape(&edge.from),
+                dot_escape(&edge.to),
+                dot_escape(edge.kind.label()),
+                style
+            ));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    // Mermaid flowchart, one subgraph per namespace. Node ids are positional since names may contain dots.
+    pub fn to_mermaid(&self) -> String {
+        let node_ids: HashMap<&str, String> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.id.as_str(), format!("n{}", i)))
+            .collect();
+        let mut out = String::from("flowchart LR\n");
+        for (ns_idx, namespace) in self.namespace_nodes().enumerate() {
+            out.push_str(&format!(
+                "    subgraph ns{}[\"{}\"]\n",
+                ns_idx,
+                mermaid_escape(&namespace.id)
+            ));
+            out.push_str(&format!(
+                "        {}[/\"{} ({})\"/]\n",
+                node_ids[namespace.id.as_str()],
+                mermaid_escape(&namespace.id),
+                mermaid_escape(&namespace.label)
+            ));
+            for command in self.commands_in(&namespace.id) {
+                let name = command
+                    .id
+                    .strip_prefix(&format!("{}.", namespace.id))
+                    .unwrap_or(&command.id);
+                out.push_str(&format!(
+                    "        {}[\"{}<br/>{}\"]\n",
+                    node_ids[command.id.as_str()],
+                    mermaid_escape(name),
+                    mermaid_escape(&command.label)
+                ));
+            }
+            out.push_str("    end\n");
+        }
+        for edge in &self.edges {
+            let arrow = match edge.kind {
+                GraphEdgeKind::StorePath { .. } => "-->",
+                GraphEdgeKind::IterativeSource { .. } | GraphEdgeKind::Condition { .. } => "-.->",
+                GraphEdgeKind::ExtensionProvider { .. } => "==>",
+            };
+            out.push_str(&format!(
+                "    {} {}|\"{}\"| {}\n",
+                node_ids[edge.from.as_str()],
+                arrow,
+                mermaid_escape(edge.kind.label()),
+                node_ids[edge.to.as_str()]
+            ));
+        }
+        out
+    }
+
+    fn namespace_nodes(&self) -> impl Iterator<Item = &GraphNode> {
+        self.nodes
+            .iter()
+            .filter(|n| n.kind == GraphNodeKind::Namespace)
+    }
+
+    fn commands_in<'a>(&'a self, namespace: &'a str) -> impl Iterator<Item = &'a GraphNode> {
+        self.nodes
+            .iter()
+            .filter(move |n| n.kind == GraphNodeKind::Command && n.namespace == namespace)
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn mermaid_escape(s: &str) -> String {
+    s.replace('"', "#quot;")
+}
@@ -1,7 +1,16 @@
 use crate::imports::*;
 
+pub mod cache;
+pub mod checkpoint;
+pub mod document;
+pub mod explain;
+pub mod graph;
 pub mod order;
+pub mod registry;
 pub mod results;
+pub mod settings;
+#[cfg(test)]
+pub mod tests;
 pub mod traits;
 pub mod validation;
 
@@ -20,6 +29,11 @@ pub struct Pipeline<T = Draft> {
     pub(crate) services: PipelineServices,
     pub(crate) namespaces: Vec<Namespace>,
     pub(crate) commands: Vec<CommandSpec>,
+    pub(crate) registry: Arc<CommandRegistry>, // Command types that can be added by name
+    pub(crate) settings: ExecutionSettings,
+    pub(crate) inputs: Vec<AttributeSpec<String>>, // Declared inputs, exposed under 'inputs.<name>'
+    pub(crate) input_values: Attributes,           // Supplied with Pipeline<Ready>::with_inputs
+    pub(crate) secrets: Vec<String>, // Declared secret names, exposed under 'secrets.<name>'
     state: T,
 }
 
@@ -29,6 +43,11 @@ impl Default for Pipeline<Draft> {
             services: PipelineServices::default(),
             namespaces: Vec::new(),
             commands: Vec::new(),
+            registry: Arc::new(CommandRegistry::default()),
+            settings: ExecutionSettings::default(),
+            inputs: Vec::new(),
+            input_values: Attributes::new(),
+            secrets: Vec::new(),
             state: Draft,
         }
     }
@@ -40,13 +59,59 @@ impl Pipeline<Draft> {
             services,
             namespaces: Vec::new(),
             commands: Vec::new(),
+            registry: Arc::new(CommandRegistry::default()),
+            settings: ExecutionSettings::default(),
+            inputs: Vec::new(),
+            input_values: Attributes::new(),
+            secrets: Vec::new(),
             state: Draft,
         }
     }
 }
 
 impl<T> Pipeline<T> {
+    // Namespaces, commands and the edges between them, for diagrams (see PipelineGraph::to_dot / to_mermaid)
+    pub fn graph(&self) -> graph::PipelineGraph {
+        graph::PipelineGraph::new(&self.namespaces, &self.commands)
+    }
+
+    pub fn registry(&self) -> &CommandRegistry {
+        &self.registry
+    }
+
+    pub fn inputs(&self) -> &[AttributeSpec<String>] {
+        &self.inputs
+    }
+
+    pub fn secrets(&self) -> &[String] {
+        &self.secrets
+    }
+
+    // Adds an event hook on top of the services the pipeline was created with
+    pub fn with_hook<H: EventHooks + 'static>(mut self, hook: H) -> Self {
+        self.services.add_hook(hook);
+        self
+    }
+
+    // Adds a source the declared secrets are looked up in, after any the services already have
+    pub fn with_secret_provider<P: SecretProvider + 'static>(mut self, provider: P) -> Self {
+        self.services.add_secret_provider(provider);
+        self
+    }
+
     // Returns an iterator of namespace and command name pairs
+    // An empty draft keeping everything that isn't part of the pipeline's document: the command registry,
+    // services, execution settings and supplied input values
+    pub(crate) fn blank(&self) -> Pipeline<Draft> {
+        Pipeline {
+            services: self.services.clone(),
+            registry: self.registry.clone(),
+            settings: self.settings.clone(),
+            input_values: self.input_values.clone(),
+            ..Pipeline::default()
+        }
+    }
+
     fn command_ns_pairs_iter(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
         self.commands.iter().map(move |cmd| {
             let ns_name = &self.namespaces[cmd.namespace_index].name();
@@ -5,43 +5,128 @@ pub struct ExecutionGroup<'a> {
     pub(crate) namespace: &'a Namespace,
     pub(crate) namespace_index: usize,
     pub(crate) commands: Vec<&'a CommandSpec>,
+    // Commands grouped into waves, every command in a wave only depends on commands in earlier waves
+    pub(crate) waves: Vec<Vec<&'a CommandSpec>>,
 }
 
 pub struct ExecutionPlan<'a> {
     namespaces: &'a [Namespace],
     commands: &'a [CommandSpec],
     namespace_order: Vec<usize>,
+    namespace_dependencies: HashMap<usize, HashSet<usize>>,
+    selection: Option<HashSet<usize>>, // Command indices to execute, None runs everything
     current: usize,
 }
 
 impl<'a> ExecutionPlan<'a> {
     pub fn new(namespaces: &'a [Namespace], commands: &'a [CommandSpec]) -> Result<Self> {
-        let namespace_order = compute_namespace_order(namespaces, commands)?;
+        let (namespace_dependencies, priority) = build_namespace_graph(namespaces, commands);
+        let namespace_order =
+            topological_sort_with_priority(&namespace_dependencies, namespaces.len(), &priority)
+                .map_err(|_| {
+                    anyhow::anyhow!("Circular dependency detected in namespace execution order")
+                })?;
         Ok(ExecutionPlan {
             namespaces,
             commands,
             namespace_order,
+            namespace_dependencies,
+            selection: None,
             current: 0,
         })
     }
 
-    fn get_ordered_commands_for_namespace(&self, ns_idx: usize) -> Result<Vec<&'a CommandSpec>> {
+    // Restricts the plan to the given command indices. Namespaces left without commands are dropped.
+    pub(crate) fn with_selection(mut self, selected: &HashSet<usize>) -> Self {
+        let kept: HashSet<usize> = selected
+            .iter()
+            .filter_map(|&idx| self.commands.get(idx).map(|c| c.namespace_index))
+            .collect();
+        self.namespace_order.retain(|ns_idx| kept.contains(ns_idx));
+        self.namespace_dependencies
+            .retain(|ns_idx, _| kept.contains(ns_idx));
+        for deps in self.namespace_dependencies.values_mut() {
+            deps.retain(|ns_idx| kept.contains(ns_idx));
+        }
+        self.selection = Some(selected.clone());
+        self
+    }
+
+    // Namespace indices in a valid sequential execution order
+    pub(crate) fn namespace_order(&self) -> &[usize] {
+        &self.namespace_order
+    }
+
+    // Namespaces `ns_idx` has to wait for, in index order
+    pub(crate) fn dependencies_of(&self, ns_idx: usize) -> Vec<usize> {
+        let mut deps: Vec<usize> = self
+            .namespace_dependencies
+            .get(&ns_idx)
+            .map(|deps| deps.iter().copied().collect())
+            .unwrap_or_default();
+        deps.sort_unstable();
+        deps
+    }
+
+    // True when every namespace `ns_idx` depends on is contained in `finished`
+    pub(crate) fn is_ready(&self, ns_idx: usize, finished: &HashSet<usize>) -> bool {
+        self.namespace_dependencies
+            .get(&ns_idx)
+            .is_none_or(|deps| deps.is_subset(finished))
+    }
+
+    pub(crate) fn group(&self, ns_idx: usize) -> Result<ExecutionGroup<'a>> {
+        let namespace = &self.namespaces[ns_idx];
+        let (commands, waves) = self.get_ordered_commands_for_namespace(ns_idx)?;
+        tracing::debug!(
+            namespace = namespace.name(),
+            command_count = commands.len(),
+            wave_count = waves.len(),
+            "Yielding execution group"
+        );
+        Ok(ExecutionGroup {
+            namespace,
+            namespace_index: ns_idx,
+            commands,
+            waves,
+        })
+    }
+
+    fn get_ordered_commands_for_namespace(
+        &self,
+        ns_idx: usize,
+    ) -> Result<(Vec<&'a CommandSpec>, Vec<Vec<&'a CommandSpec>>)> {
         let namespace = &self.namespaces[ns_idx];
 
         // Filter commands belonging to this namespace
         let ns_commands: Vec<&CommandSpec> = self
             .commands
             .iter()
-            .filter(|c| c.namespace_index == ns_idx)
+            .enumerate()
+            .filter(|(idx, c)| {
+                c.namespace_index == ns_idx
+                    && self.selection.as_ref().is_none_or(|s| s.contains(idx))
+            })
+            .map(|(_, c)| c)
             .collect();
 
         if ns_commands.is_empty() {
-            return Ok(vec![]);
+            return Ok((vec![], vec![]));
         }
 
-        let order = compute_command_order(&ns_commands, namespace.name())?;
-
-        Ok(order.into_iter().map(|i| ns_commands[i]).collect())
+        let graph = build_command_graph(&ns_commands, namespace.name());
+        let order = topological_sort(&graph, ns_commands.len()).map_err(|_| {
+            anyhow::anyhow!("Circular dependency detected in command execution order")
+        })?;
+        let waves = compute_waves(&graph, &order);
+
+        Ok((
+            order.into_iter().map(|i| ns_commands[i]).collect(),
+            waves
+                .into_iter()
+                .map(|wave| wave.into_iter().map(|i| ns_commands[i]).collect())
+                .collect(),
+        ))
     }
 }
 
@@ -56,34 +141,15 @@ impl<'a> Iterator for ExecutionPlan<'a> {
         let ns_idx = self.namespace_order[self.current];
         self.current += 1;
 
-        let namespace = &self.namespaces[ns_idx];
-
-        match self.get_ordered_commands_for_namespace(ns_idx) {
-            Ok(commands) => {
-                tracing::debug!(
-                    namespace = namespace.name(),
-                    command_count = commands.len(),
-                    "Yielding execution group"
-                );
-                Some(Ok(ExecutionGroup {
-                    namespace,
-                    namespace_index: ns_idx,
-                    commands,
-                }))
-            }
-            Err(e) => Some(Err(e)),
-        }
+        Some(self.group(ns_idx))
     }
 }
 
-fn compute_namespace_order(
+// Returns (namespace -> namespaces it depends on, scheduling priority per namespace)
+fn build_namespace_graph(
     namespaces: &[Namespace],
     commands: &[CommandSpec],
-) -> Result<Vec<usize>> {
-    if namespaces.is_empty() {
-        return Ok(vec![]);
-    }
-
+) -> (HashMap<usize, HashSet<usize>>, HashMap<usize, u32>) {
     let name_to_idx: HashMap<&str, usize> = namespaces
         .iter()
         .enumerate()
@@ -116,6 +182,17 @@ fn compute_namespace_order(
         }
     }
 
+    // Add dependencies from namespace 'when' conditions
+    for (idx, namespace) in namespaces.iter().enumerate() {
+        for dep_path in namespace.when_dependencies() {
+            if let Some(dep_ns_name) = dep_path.namespace()
+                && let Some(&dep_ns_idx) = name_to_idx.get(dep_ns_name.as_str())
+            {
+                graph.get_mut(&idx).unwrap().insert(dep_ns_idx);
+            }
+        }
+    }
+
     // Add dependencies from command dependencies (cross-namespace references)
     for command in commands {
         let command_ns_idx = command.namespace_index;
@@ -163,15 +240,14 @@ fn compute_namespace_order(
         }
     }
 
-    topological_sort_with_priority(&graph, namespaces.len(), &priority)
-        .map_err(|_| anyhow::anyhow!("Circular dependency detected in namespace execution order"))
+    (graph, priority)
 }
 
-fn compute_command_order(commands: &[&CommandSpec], namespace: &str) -> Result<Vec<usize>> {
-    if commands.is_empty() {
-        return Ok(vec![]);
-    }
-
+// Returns command index -> indices of the commands (within the same namespace) it depends on
+fn build_command_graph(
+    commands: &[&CommandSpec],
+    namespace: &str,
+) -> HashMap<usize, HashSet<usize>> {
     let mut prefix_to_idx: HashMap<StorePath, usize> = HashMap::new();
     for (idx, command) in commands.iter().enumerate() {
         let prefix = StorePath::from_segments([namespace, &command.name]);
@@ -210,8 +286,30 @@ fn compute_command_order(commands: &[&CommandSpec], namespace: &str) -> Result<V
         }
     }
 
-    topological_sort(&graph, commands.len())
-        .map_err(|_| anyhow::anyhow!("Circular dependency detected in command execution order"))
+    graph
+}
+
+// Splits a topological order into waves: a node's wave is one past the latest wave of its dependencies.
+// Nodes keep their relative topological order within a wave.
+fn compute_waves(graph: &HashMap<usize, HashSet<usize>>, order: &[usize]) -> Vec<Vec<usize>> {
+    let mut wave_of: HashMap<usize, usize> = HashMap::new();
+    let mut waves: Vec<Vec<usize>> = Vec::new();
+    for &node in order {
+        let wave = graph
+            .get(&node)
+            .into_iter()
+            .flatten()
+            .filter_map(|dep| wave_of.get(dep))
+            .map(|dep_wave| dep_wave + 1)
+            .max()
+            .unwrap_or(0);
+        wave_of.insert(node, wave);
+        if waves.len() <= wave {
+            waves.resize_with(wave + 1, Vec::new);
+        }
+        waves[wave].push(node);
+    }
+    waves
 }
 
 fn topological_sort_with_priority(
@@ -1,5 +1,99 @@
 use super::{Completed, Draft, Ready};
 use crate::imports::*;
+use crate::pipeline::cache::ResultCache;
+use crate::pipeline::checkpoint::{CheckpointManifest, Checkpointer, load_checkpoint};
+use crate::pipeline::traits::{
+    EXECUTION_STATUS_ERROR, EXECUTION_STATUS_SKIPPED, EXECUTION_STATUS_SUCCESS, evaluate_when,
+};
+use crate::pipeline::validation::validate_value;
+
+/*
+    Types:
+    * FailedCommands - Extension tracking commands that failed under 'skip_dependents' (and the commands skipped
+      because of them) by output prefix, along with the extensions those commands would have provided
+*/
+#[derive(Default)]
+struct FailedCommands {
+    paths: HashSet<StorePath>, // Output prefixes, so an iteration's failure only affects that iteration
+
+    extensions: HashSet<ExtensionKey>,
+}
+
+impl FailedCommands {
+    async fn record(
+        context: &ExecutionContext,
+        output_prefix: StorePath,
+        command_spec: &CommandSpec,
+    ) {
+        let mut extensions = context.extensions().write().await;
+        if !extensions.contains::<FailedCommands>() {
+            extensions.insert(FailedCommands::default());
+        }
+        if let Some(failed) = extensions.get_mut::<FailedCommands>() {
+            failed.paths.insert(output_prefix);
+            failed
+                .extensions
+                .extend(command_spec.provides_extensions.iter().cloned());
+        }
+    }
+
+    // Why the command can't run, if it depends on the output or extension of a failed command
+    async fn blocking_reason(
+        context: &ExecutionContext,
+        command_spec: &CommandSpec,
+        namespace: &str,
+        iteration_index: Option<usize>,
+    ) -> Option<String> {
+        let extensions = context.extensions().read().await;
+        let failed = extensions.get::<FailedCommands>()?;
+        if let Some((dependency, path)) = command_spec.dependencies.iter().find_map(|dependency| {
+            let dependency = in_iteration(dependency, namespace, iteration_index);
+            // A dependency on a whole iterative command is missing the failed iteration's output too
+            failed
+                .paths
+                .iter()
+                .find(|path| dependency.starts_with(path) || path.starts_with(&dependency))
+                .map(|path| (dependency, path))
+        }) {
+            return Some(format!(
+                "Dependency '{}' is unavailable because '{}' failed",
+                dependency.to_dotted(),
+                path.to_dotted()
+            ));
+        }
+        command_spec
+            .requires_extensions
+            .iter()
+            .find(|key| failed.extensions.contains(key))
+            .map(|key| {
+                format!(
+                    "Extension '{}' is unavailable because its provider failed",
+                    key
+                )
+            })
+    }
+}
+
+// Inside an iterative namespace, an unindexed reference to a sibling command means this iteration's output
+fn in_iteration(
+    dependency: &StorePath,
+    namespace: &str,
+    iteration_index: Option<usize>,
+) -> StorePath {
+    match (iteration_index, dependency.segments()) {
+        (Some(index), [dep_namespace, command, rest @ ..])
+            if dep_namespace == namespace
+                && rest.first().is_none_or(|s| s.parse::<usize>().is_err()) =>
+        {
+            let mut path = StorePath::from_segments([dep_namespace, command]).with_index(index);
+            for segment in rest {
+                path.add_segment(segment.as_str());
+            }
+            path
+        }
+        _ => dependency.clone(),
+    }
+}
 
 impl Pipeline<Ready> {
     #[tracing::instrument(skip(self), err, fields(
@@ -7,6 +101,49 @@ impl Pipeline<Ready> {
     command_count = self.commands.len()
 ))]
     pub async fn execute(self) -> Result<Pipeline<Completed>> {
+        self.run(None, None).await
+    }
+
+    // Executes only the commands matching `targets` and everything they transitively need.
+    // A target can name a namespace, a command or any path inside a command's outputs.
+    #[tracing::instrument(skip(self, targets), err, fields(target_count = targets.len()))]
+    pub async fn execute_targets(self, targets: &[StorePath]) -> Result<Pipeline<Completed>> {
+        let graph = self.graph();
+        let selected = self.select_commands(&graph.upstream_of(&self.target_nodes(targets)?));
+        self.run(None, Some(selected)).await
+    }
+
+    // Executes the commands matching `sources` and everything downstream of them, along with whatever
+    // those commands need to run.
+    #[tracing::instrument(skip(self, sources), err, fields(source_count = sources.len()))]
+    pub async fn execute_downstream(self, sources: &[StorePath]) -> Result<Pipeline<Completed>> {
+        let graph = self.graph();
+        let downstream: Vec<String> = graph
+            .downstream_of(&self.target_nodes(sources)?)
+            .into_iter()
+            .collect();
+        let selected = self.select_commands(&graph.upstream_of(&downstream));
+        self.run(None, Some(selected)).await
+    }
+
+    // Rehydrates the stores from a checkpoint directory, then executes the pipeline skipping every command
+    // whose status is already 'success'. Checkpoints keep being written to the same directory unless the
+    // settings name a different one.
+    #[tracing::instrument(skip(self, dir), err, fields(dir = %dir.as_ref().display()))]
+    pub async fn resume_from(
+        self,
+        dir: impl AsRef<std::path::Path>,
+    ) -> Result<Pipeline<Completed>> {
+        self.run(Some(dir.as_ref().to_path_buf()), None).await
+    }
+
+    async fn run(
+        self,
+        resume_from: Option<PathBuf>,
+        selection: Option<HashSet<usize>>,
+    ) -> Result<Pipeline<Completed>> {
+        let inputs = self.resolve_inputs()?;
+        let secrets = self.resolve_secrets().await?;
         self.services
             .before_execute_pipeline(hook_events::PipelineInfo {
                 namespace_count: self.namespaces.len(),
@@ -31,96 +168,74 @@ impl Pipeline<Ready> {
             static_value_count = static_count,
             "Inserted static values into ExecutionContext scalar store"
         );
+        let restored = match &resume_from {
+            Some(dir) => load_checkpoint(dir, &context).await?,
+            None => CheckpointManifest::default(),
+        };
+        // After the checkpoint, so a resumed run uses the inputs it was given rather than the previous run's
+        for (name, value) in inputs {
+            let store_path = StorePath::from_segments([INPUTS_NAMESPACE, name.as_str()]);
+            context.scalar().insert(&store_path, value).await?;
+        }
+        // Also after the checkpoint, which only holds redacted copies
+        for (name, secret) in &secrets {
+            let store_path = StorePath::from_segments([SECRETS_NAMESPACE, name.as_str()]);
+            context.scalar().insert_secret(&store_path, secret).await?;
+        }
+        if let Some(dir) = self.settings.checkpoint_dir.clone().or(resume_from.clone()) {
+            let checkpointer = if resume_from.as_ref() == Some(&dir) {
+                Checkpointer::new(dir, restored)?
+            } else {
+                // Resuming into another directory, which starts with what was restored
+                let checkpointer = Checkpointer::new(dir, CheckpointManifest::default())?;
+                for output_prefix in restored.output_prefixes() {
+                    checkpointer.save(&context, &output_prefix).await?;
+                }
+                checkpointer
+            };
+            context
+                .extensions()
+                .write()
+                .await
+                .insert(Arc::new(checkpointer));
+        }
 
-        let plan = ExecutionPlan::new(&self.namespaces, &self.commands)?;
-
-        for group_result in plan {
-            let ExecutionGroup {
-                namespace,
-                namespace_index,
-                commands,
-            } = group_result?;
-            self.services
-                .before_execute_namespace(hook_events::NamespaceInfo {
-                    namespace_index,
-                    namespace_name: namespace.name().to_string(),
-                    command_count: commands.len(),
-                })
-                .await?;
+        let mut plan = ExecutionPlan::new(&self.namespaces, &self.commands)?;
+        if let Some(selection) = &selection {
             tracing::debug!(
-                namespace_index = namespace_index,
-                command_count = commands.len(),
-                "Executing command group"
+                selected_command_count = selection.len(),
+                "Executing a subset of the pipeline"
             );
-            match &namespace.ty() {
-                ExecutionMode::Once => {
-                    self.execute_commands(&commands, namespace.name(), &context, None)
-                        .await?;
-                }
-                ExecutionMode::Iterative {
-                    store_path,
-                    source: _,
-                    iter_var,
-                    index_var,
-                } => {
-                    tracing::debug!(
-                        namespace = store_path
-                            .namespace()
-                            .map(|ns| ns.as_str())
-                            .unwrap_or("<no-namespace>"),
-                        store_path = store_path.to_dotted(),
-                        "Processing iterative namespace"
-                    );
-                    let iter_items: Vec<ScalarValue> =
-                        namespace.ty().resolve_iter_values(&context).await?;
+            plan = plan.with_selection(selection);
+        }
+        // With a timeout, cancel the pipeline once the deadline passes; commands bound themselves by it too.
+        let watcher = match self.settings.timeout {
+            Some(timeout) => {
+                let deadline = tokio::time::Instant::now() + timeout;
+                context
+                    .extensions()
+                    .write()
+                    .await
+                    .insert(PipelineDeadline(deadline));
+                let token = context.extensions().cancellation_token().await;
+                Some(tokio::spawn(async move {
+                    tokio::time::sleep_until(deadline).await;
                     tracing::debug!(
-                        iteration_count = iter_items.len(),
-                        "Extracted items for iterative namespace"
+                        timeout_ms = timeout.as_millis() as u64,
+                        "Pipeline deadline passed, cancelling"
                     );
-                    for (index, item) in iter_items.iter().enumerate() {
-                        // Add the item and index values to the context for substitution.
-                        if let Some(var_name) = iter_var {
-                            context.scalar().insert_raw(var_name, item.clone()).await?;
-                        }
-                        if let Some(index_name) = index_var {
-                            context
-                                .scalar()
-                                .insert_raw(index_name, to_scalar::i64(index as i64))
-                                .await?;
-                        }
-                        self.execute_commands(&commands, namespace.name(), &context, Some(index))
-                            .await?;
-                        // Remove the iteration variables from the context.
-                        if let Some(var_name) = iter_var {
-                            context
-                                .scalar()
-                                .remove(&StorePath::from_segments([var_name]))
-                                .await?;
-                        }
-                        if let Some(_index_name) = index_var {
-                            context
-                                .scalar()
-                                .remove(&StorePath::from_segments([_index_name]))
-                                .await?;
-                        }
+                    if let Some(token) = token {
+                        token.cancel();
                     }
-                }
-                ExecutionMode::Static { values: _ } => {
-                    // Variables namespace does not execute commands.
-                    tracing::debug!(
-                        namespace = namespace.name(),
-                        "Variables namespace - skipping command execution"
-                    );
-                }
+                }))
             }
-            self.services
-                .after_execute_namespace(hook_events::NamespaceExecuted {
-                    namespace_index,
-                    namespace_name: namespace.name().to_string(),
-                    executed_at: Instant::now(),
-                })
-                .await?;
+            None => None,
+        };
+        let executed = self.execute_plan(&plan, &context).await;
+        if let Some(watcher) = watcher {
+            watcher.abort();
         }
+        executed.map_err(|e| context.redact_error(e))?;
 
         tracing::debug!("Completed execution of all Commands");
         self.services
@@ -134,55 +249,601 @@ impl Pipeline<Ready> {
             services: self.services,
             namespaces: self.namespaces,
             commands: self.commands,
+            registry: self.registry,
+            settings: self.settings,
+            inputs: self.inputs,
+            input_values: self.input_values,
+            secrets: self.secrets,
             state: Completed { context },
         })
     }
 
-    #[tracing::instrument(skip(self, commands, context), err, fields(namespace, command_count = commands.len(), iteration_index = ?iteration_index))]
-    async fn execute_commands(
+    // Schedules every namespace whose dependencies have finished, up to `max_concurrency` at a time.
+    // Ready namespaces are started in plan order so a limit of 1 matches sequential execution.
+    async fn execute_plan(
         &self,
-        commands: &[&CommandSpec],
-        namespace: &str,
+        plan: &ExecutionPlan<'_>,
         context: &ExecutionContext,
-        iteration_index: Option<usize>,
     ) -> Result<()> {
-        for command_spec in commands.iter() {
+        use futures::StreamExt;
+
+        let max_concurrency = self.settings.max_concurrency.max(1);
+
+        let mut pending: Vec<usize> = plan.namespace_order().to_vec();
+        let mut finished: HashSet<usize> = HashSet::new();
+        let mut running = futures::stream::FuturesUnordered::new();
+
+        loop {
+            while running.len() < max_concurrency
+                && let Some(position) = pending
+                    .iter()
+                    .position(|ns_idx| plan.is_ready(*ns_idx, &finished))
+            {
+                let ns_idx = pending.remove(position);
+                let group = self.execute_group(plan.group(ns_idx)?, context);
+                running.push(async move { (ns_idx, group.await) });
+            }
+
+            let Some((ns_idx, result)) = running.next().await else {
+                break;
+            };
+            result?;
+            finished.insert(ns_idx);
+        }
+
+        if !pending.is_empty() {
+            return Err(anyhow::anyhow!(
+                "{} namespace(s) could not be scheduled, their dependencies never completed",
+                pending.len()
+            ));
+        }
+        Ok(())
+    }
+
+    async fn execute_group(
+        &self,
+        group: ExecutionGroup<'_>,
+        context: &ExecutionContext,
+    ) -> Result<()> {
+        use futures::{StreamExt, TryStreamExt};
+
+        let ExecutionGroup {
+            namespace,
+            namespace_index,
+            ref commands,
+            ..
+        } = group;
+        if let Some(condition) = namespace.when()
+            && !evaluate_when(condition, context).await?
+        {
+            return self.skip_group(&group, context).await;
+        }
+        // Resolved up front so the hook can report how many iterations are coming
+        let iter_items: Option<Vec<ScalarValue>> = match namespace.ty() {
+            ExecutionMode::Iterative { .. } => {
+                Some(namespace.ty().resolve_iter_values(context).await?)
+            }
+            _ => None,
+        };
+        self.services
+            .before_execute_namespace(hook_events::NamespaceInfo {
+                namespace_index,
+                namespace_name: namespace.name().to_string(),
+                command_count: commands.len(),
+                iteration_count: iter_items.as_ref().map(Vec::len),
+            })
+            .await?;
+        tracing::debug!(
+            namespace_index = namespace_index,
+            command_count = commands.len(),
+            "Executing command group"
+        );
+        match &namespace.ty() {
+            ExecutionMode::Once => {
+                self.execute_commands(&group, context, None).await?;
+            }
+            ExecutionMode::Iterative {
+                store_path,
+                source: _,
+                iter_var,
+                index_var,
+            } => {
+                tracing::debug!(
+                    namespace = store_path
+                        .namespace()
+                        .map(|ns| ns.as_str())
+                        .unwrap_or("<no-namespace>"),
+                    store_path = store_path.to_dotted(),
+                    "Processing iterative namespace"
+                );
+                let iter_items = iter_items.unwrap_or_default();
+                let iteration_parallelism = namespace.iteration_parallelism();
+                tracing::debug!(
+                    iteration_count = iter_items.len(),
+                    iteration_parallelism = iteration_parallelism,
+                    "Extracted items for iterative namespace"
+                );
+                // Each iteration sees its item and index through an isolated scope on the context.
+                let group = &group;
+                let iterations = iter_items.into_iter().enumerate().map(|(index, item)| {
+                    let mut vars = HashMap::new();
+                    if let Some(var_name) = iter_var {
+                        vars.insert(var_name.clone(), item);
+                    }
+                    if let Some(index_name) = index_var {
+                        vars.insert(index_name.clone(), to_scalar::i64(index as i64));
+                    }
+                    let scoped = context.scoped(vars);
+                    async move { self.execute_commands(group, &scoped, Some(index)).await }
+                });
+                if iteration_parallelism <= 1 {
+                    for iteration in iterations {
+                        iteration.await?;
+                    }
+                } else {
+                    futures::stream::iter(iterations)
+                        .buffer_unordered(iteration_parallelism)
+                        .try_collect::<Vec<()>>()
+                        .await?;
+                }
+            }
+            ExecutionMode::Static { values: _ } => {
+                // Variables namespace does not execute commands.
+                tracing::debug!(
+                    namespace = namespace.name(),
+                    "Variables namespace - skipping command execution"
+                );
+            }
+        }
+        self.services
+            .after_execute_namespace(hook_events::NamespaceExecuted {
+                namespace_index,
+                namespace_name: namespace.name().to_string(),
+                executed_at: Instant::now(),
+            })
+            .await?;
+        Ok(())
+    }
+
+    // Records every command of a namespace whose 'when' is false as skipped, once, without an iteration index.
+    // The hooks still see the namespace and its commands so progress reporting adds up.
+    async fn skip_group(
+        &self,
+        group: &ExecutionGroup<'_>,
+        context: &ExecutionContext,
+    ) -> Result<()> {
+        let namespace = group.namespace;
+        tracing::debug!(
+            namespace = namespace.name(),
+            command_count = group.commands.len(),
+            "Skipping namespace - 'when' condition is false"
+        );
+        self.services
+            .before_execute_namespace(hook_events::NamespaceInfo {
+                namespace_index: group.namespace_index,
+                namespace_name: namespace.name().to_string(),
+                command_count: group.commands.len(),
+                iteration_count: None,
+            })
+            .await?;
+        for command_spec in &group.commands {
             self.services
                 .before_execute_command(hook_events::CommandInfo {
                     namespace_index: command_spec.namespace_index,
                     command_name: command_spec.name.clone(),
                     command_type: command_spec.command_type.clone(),
-                    command_count: commands.len(),
+                    command_count: group.commands.len(),
+                    iteration_index: None,
                 })
                 .await?;
-            // Run substitution on all string attributes.
-            let substituted_attrs =
-                substitute_attributes(&command_spec.attributes, context, &command_spec.name)
-                    .await?;
-            let command = (command_spec.builder)(&substituted_attrs)?;
-            // Create output prefix as [namespace, command_name] or [namespace, command_name, index]
-            let mut output_prefix = StorePath::from_segments([namespace, &command_spec.name]);
-            if let Some(idx) = iteration_index {
-                output_prefix = output_prefix.with_index(idx);
-            }
-            command.execute(context, &output_prefix).await?;
+            let output_prefix = StorePath::from_segments([namespace.name(), &command_spec.name]);
+            let batch = InsertBatch::new(context, &output_prefix);
+            batch
+                .string("status", EXECUTION_STATUS_SKIPPED.to_string())
+                .await?;
+            batch.u64("duration_ms", 0).await?;
             self.services
                 .after_execute_command(hook_events::CommandExecuted {
                     namespace_index: command_spec.namespace_index,
                     command_name: command_spec.name.clone(),
                     command_type: command_spec.command_type.clone(),
+                    iteration_index: None,
+                    status: Some(EXECUTION_STATUS_SKIPPED.to_string()),
                     executed_at: Instant::now(),
                 })
                 .await?;
         }
+        self.services
+            .after_execute_namespace(hook_events::NamespaceExecuted {
+                namespace_index: group.namespace_index,
+                namespace_name: namespace.name().to_string(),
+                executed_at: Instant::now(),
+            })
+            .await
+    }
+
+    // Runs the commands of a group once. With a namespace max_parallelism above 1 the commands run
+    // wave by wave, with up to max_parallelism commands of the same wave in flight at a time.
+    #[tracing::instrument(skip(self, group, context), err, fields(
+        namespace = group.namespace.name(),
+        command_count = group.commands.len(),
+        iteration_index = ?iteration_index,
+    ))]
+    async fn execute_commands(
+        &self,
+        group: &ExecutionGroup<'_>,
+        context: &ExecutionContext,
+        iteration_index: Option<usize>,
+    ) -> Result<()> {
+        use futures::{StreamExt, TryStreamExt};
+
+        let max_parallelism = group.namespace.max_parallelism();
+        if max_parallelism <= 1 {
+            for command_spec in group.commands.iter() {
+                self.execute_command(command_spec, group, context, iteration_index)
+                    .await?;
+            }
+            return Ok(());
+        }
+
+        for (wave_index, wave) in group.waves.iter().enumerate() {
+            tracing::debug!(
+                wave_index = wave_index,
+                wave_size = wave.len(),
+                max_parallelism = max_parallelism,
+                "Executing command wave"
+            );
+            futures::stream::iter(wave.iter().map(|command_spec| {
+                self.execute_command(command_spec, group, context, iteration_index)
+            }))
+            .buffer_unordered(max_parallelism)
+            .try_collect::<Vec<()>>()
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn execute_command(
+        &self,
+        command_spec: &CommandSpec,
+        group: &ExecutionGroup<'_>,
+        context: &ExecutionContext,
+        iteration_index: Option<usize>,
+    ) -> Result<()> {
+        let namespace = group.namespace.name();
+        self.services
+            .before_execute_command(hook_events::CommandInfo {
+                namespace_index: command_spec.namespace_index,
+                command_name: command_spec.name.clone(),
+                command_type: command_spec.command_type.clone(),
+                command_count: group.commands.len(),
+                iteration_index,
+            })
+            .await?;
+        // Create output prefix as [namespace, command_name] or [namespace, command_name, index]
+        let command_path = StorePath::from_segments([namespace, &command_spec.name]);
+        let mut output_prefix = command_path.clone();
+        if let Some(idx) = iteration_index {
+            output_prefix = output_prefix.with_index(idx);
+        }
+        let batch = InsertBatch::new(context, &output_prefix);
+
+        // Only possible when resuming from a checkpoint
+        let status_path = output_prefix.with_segment("status");
+        match context.scalar().get(&status_path).await? {
+            Some(status) if status.as_str() == Some(EXECUTION_STATUS_SUCCESS) => {
+                tracing::debug!(
+                    command = %output_prefix.to_dotted(),
+                    "Skipping command - already succeeded in the checkpoint"
+                );
+                // Hooks still see a finished command for every one that started
+                self.services
+                    .after_execute_command(hook_events::CommandExecuted {
+                        namespace_index: command_spec.namespace_index,
+                        command_name: command_spec.name.clone(),
+                        command_type: command_spec.command_type.clone(),
+                        iteration_index,
+                        status: Some(EXECUTION_STATUS_SUCCESS.to_string()),
+                        executed_at: Instant::now(),
+                    })
+                    .await?;
+                return Ok(());
+            }
+            // Clear what the failed run left behind so it can't be mistaken for this run's output
+            Some(_) => {
+                context.scalar().remove_at(&output_prefix).await?;
+            }
+            None => {}
+        }
+
+        if let Some(reason) =
+            FailedCommands::blocking_reason(context, command_spec, namespace, iteration_index).await
+        {
+            tracing::debug!(
+                command = %output_prefix.to_dotted(),
+                reason = %reason,
+                "Skipping command - a dependency failed"
+            );
+            batch
+                .string("status", EXECUTION_STATUS_SKIPPED.to_string())
+                .await?;
+            batch.string("error", reason).await?;
+            // Whatever depended on this command is now missing its inputs too
+            FailedCommands::record(context, output_prefix.clone(), command_spec).await;
+        } else if let Err(e) = self
+            .run_command(
+                command_spec,
+                context,
+                &output_prefix,
+                namespace,
+                iteration_index,
+            )
+            .await
+            .map_err(|e| context.redact_error(e))
+        {
+            let policy = command_spec
+                .error_policy()?
+                .unwrap_or(self.settings.on_error);
+            if policy == ErrorPolicy::Fail {
+                return Err(e);
+            }
+            tracing::warn!(
+                command = %output_prefix.to_dotted(),
+                on_error = %policy,
+                error = %format!("{:#}", e),
+                "Command failed, continuing pipeline"
+            );
+            // Failures before the command started (substitution, construction) have no status yet
+            if context.scalar().get(&status_path).await?.is_none() {
+                batch
+                    .string("status", EXECUTION_STATUS_ERROR.to_string())
+                    .await?;
+                batch.string("error", format!("{:#}", e)).await?;
+            }
+            if policy == ErrorPolicy::SkipDependents {
+                FailedCommands::record(context, output_prefix.clone(), command_spec).await;
+            }
+        }
+        // Cloned out so the extensions lock isn't held while the checkpoint is written
+        let checkpointer = context
+            .extensions()
+            .read()
+            .await
+            .get::<Arc<Checkpointer>>()
+            .cloned();
+        if let Some(checkpointer) = checkpointer {
+            // Like the cache, a redacted copy would be restored as real data, so a resumed run executes it again
+            if self.reads_secrets(command_spec) {
+                tracing::debug!(command = %output_prefix.to_dotted(), "Reads secrets, not checkpointed");
+            } else {
+                checkpointer.save(context, &output_prefix).await?;
+            }
+        }
+        let status = context
+            .scalar()
+            .get(&status_path)
+            .await?
+            .and_then(|status| status.as_str().map(str::to_string));
+        self.services
+            .after_execute_command(hook_events::CommandExecuted {
+                namespace_index: command_spec.namespace_index,
+                command_name: command_spec.name.clone(),
+                command_type: command_spec.command_type.clone(),
+                iteration_index,
+                status,
+                executed_at: Instant::now(),
+            })
+            .await?;
+        Ok(())
+    }
+
+    // Substitutes attributes, builds the command and executes it at the given output prefix.
+    async fn run_command(
+        &self,
+        command_spec: &CommandSpec,
+        context: &ExecutionContext,
+        output_prefix: &StorePath,
+        namespace: &str,
+        iteration_index: Option<usize>,
+    ) -> Result<()> {
+        // Run substitution on all string attributes.
+        let substituted_attrs =
+            substitute_attributes(&command_spec.attributes, context, &command_spec.name).await?;
+        let command = (command_spec.builder)(&substituted_attrs)?;
+
+        let cache = match &self.settings.cache_dir {
+            Some(dir) if command_spec.cacheable && !self.reads_secrets(command_spec) => {
+                ResultCache::new(dir.clone())
+            }
+            _ => return command.execute(context, output_prefix).await,
+        };
+        // A skipped command has nothing worth caching or restoring
+        if let Some(condition) = substituted_attrs.get("when").and_then(|v| v.as_str())
+            && !evaluate_when(condition, context).await?
+        {
+            return command.execute(context, output_prefix).await;
+        }
+        // The paths this iteration reads, so each iteration's key covers its own upstream outputs
+        let mut dependencies = Vec::with_capacity(command_spec.dependencies.len());
+        for dependency in &command_spec.dependencies {
+            let dependency = match dependency.to_dotted() {
+                dotted if dotted.contains("{{") => {
+                    StorePath::from_dotted(&context.substitute(dotted).await?)
+                }
+                _ => dependency.clone(),
+            };
+            dependencies.push(in_iteration(&dependency, namespace, iteration_index));
+        }
+        let key = cache
+            .key(
+                command_spec,
+                &substituted_attrs,
+                &dependencies,
+                &command.input_files(context).await?,
+                context,
+            )
+            .await?;
+        let batch = InsertBatch::new(context, output_prefix);
+        if cache.restore(&key, context, output_prefix).await? {
+            tracing::debug!(
+                command = %output_prefix.to_dotted(),
+                cache_key = %key,
+                "Restored command outputs from cache"
+            );
+            return batch.bool("cached", true).await;
+        }
+        command.execute(context, output_prefix).await?;
+        batch.bool("cached", false).await?;
+        // Cancelled commands return Ok without producing outputs
+        let status = context
+            .scalar()
+            .get(&output_prefix.with_segment("status"))
+            .await?;
+        if status.as_ref().and_then(|s| s.as_str()) == Some(EXECUTION_STATUS_SUCCESS) {
+            cache.store(&key, context, output_prefix).await?;
+        }
         Ok(())
     }
 
+    // Cached and checkpointed outputs are written to disk as they are, so commands that can see a secret are
+    // never cached or checkpointed
+    fn reads_secrets(&self, command_spec: &CommandSpec) -> bool {
+        !self.secrets.is_empty()
+            && command_spec.dependencies.iter().any(|dependency| {
+                dependency.namespace().map(String::as_str) == Some(SECRETS_NAMESPACE)
+            })
+    }
+
+    // Graph node ids (namespaces and commands) matched by each target, erroring on targets matching nothing.
+    // A namespace target stands for every command in it.
+    fn target_nodes(&self, targets: &[StorePath]) -> Result<Vec<String>> {
+        let mut nodes = Vec::new();
+        for target in targets {
+            let before = nodes.len();
+            for namespace in &self.namespaces {
+                if target.to_dotted() == namespace.name() {
+                    nodes.push(namespace.name().to_string());
+                }
+            }
+            for (ns_name, cmd_name) in self.command_ns_pairs_iter() {
+                let command_path = StorePath::from_segments([ns_name, cmd_name]);
+                if target.starts_with(&command_path) || target.to_dotted() == ns_name {
+                    nodes.push(command_path.to_dotted());
+                }
+            }
+            if nodes.len() == before {
+                return Err(anyhow::anyhow!(
+                    "Target '{}' does not match any namespace or command",
+                    target.to_dotted()
+                ));
+            }
+        }
+        Ok(nodes)
+    }
+
+    // Command indices whose graph node is in `nodes`
+    fn select_commands(&self, nodes: &HashSet<String>) -> HashSet<usize> {
+        self.command_ns_pairs_iter()
+            .enumerate()
+            .filter(|(_, (ns_name, cmd_name))| nodes.contains(&format!("{}.{}", ns_name, cmd_name)))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    // Dry run: what execute() would do, without running anything
+    pub fn explain(&self) -> Result<PipelineExplanation> {
+        PipelineExplanation::new(&self.namespaces, &self.commands)
+    }
+
+    pub fn settings(&self) -> &ExecutionSettings {
+        &self.settings
+    }
+
+    pub fn with_settings(mut self, settings: ExecutionSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    // Values for the declared inputs, replacing any supplied before. They're checked when the pipeline executes.
+    pub fn with_inputs(mut self, inputs: Attributes) -> Self {
+        self.input_values = inputs;
+        self
+    }
+
+    // Supplied values and defaults of the declared inputs, each checked against its input's type
+    fn resolve_inputs(&self) -> Result<Vec<(String, ScalarValue)>> {
+        let mut unknown: Vec<&String> = self
+            .input_values
+            .keys()
+            .filter(|name| !self.inputs.iter().any(|input| &input.name == *name))
+            .collect();
+        unknown.sort();
+        if let Some(name) = unknown.first() {
+            let declared: Vec<&str> = self.inputs.iter().map(|i| i.name.as_str()).collect();
+            return Err(anyhow::anyhow!(
+                "Unknown input '{}', declared inputs are: {}",
+                name,
+                declared.join(", ")
+            ));
+        }
+
+        let mut resolved = Vec::new();
+        for input in &self.inputs {
+            let value = match self.input_values.get(&input.name) {
+                Some(value) => value.clone(),
+                None => match &input.default_value {
+                    Some(default) => default.clone(),
+                    None if input.required => {
+                        return Err(anyhow::anyhow!("Missing required input '{}'", input.name));
+                    }
+                    None => continue, // Optional without a default, left out of the store
+                },
+            };
+            validate_value(
+                &value,
+                &input.ty,
+                &format!("{}.{}", INPUTS_NAMESPACE, input.name),
+            )?;
+            resolved.push((input.name.clone(), value));
+        }
+        tracing::debug!(input_count = resolved.len(), "Resolved pipeline inputs");
+        Ok(resolved)
+    }
+
+    // Looks each declared secret up in the secret providers
+    async fn resolve_secrets(&self) -> Result<Vec<(String, Secret)>> {
+        let mut resolved = Vec::with_capacity(self.secrets.len());
+        for name in &self.secrets {
+            if !self.services.has_secret_providers() {
+                return Err(anyhow::anyhow!(
+                    "Secret '{}' is declared but the pipeline has no secret providers",
+                    name
+                ));
+            }
+            let secret = self
+                .services
+                .secret(name)
+                .await
+                .with_context(|| format!("Failed to look up secret '{}'", name))?
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Secret '{}' wasn't found by any secret provider", name)
+                })?;
+            resolved.push((name.clone(), secret));
+        }
+        tracing::debug!(secret_count = resolved.len(), "Resolved pipeline secrets");
+        Ok(resolved)
+    }
+
     pub fn edit(self) -> Pipeline<Draft> {
         Pipeline::<Draft> {
             services: self.services,
             namespaces: self.namespaces,
             commands: self.commands,
+            registry: self.registry,
+            settings: self.settings,
+            inputs: self.inputs,
+            input_values: self.input_values,
+            secrets: self.secrets,
             state: Draft,
         }
     }
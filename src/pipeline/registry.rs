@@ -0,0 +1,115 @@
+use crate::imports::*;
+
+/*
+    Types:
+    * CommandRegistry - Command types addressable by their Descriptor::command_type string
+    * RegisteredCommand - A registered command's spec constructor plus its static attribute/result metadata
+*/
+#[derive(Clone)]
+pub struct CommandRegistry {
+    commands: HashMap<String, RegisteredCommand>,
+}
+
+#[derive(Clone)]
+pub struct RegisteredCommand {
+    command_type: &'static str,
+    attributes: Vec<&'static AttributeSpec<&'static str>>,
+    results: Vec<&'static ResultSpec<&'static str>>,
+    new_spec: fn(usize, String, Attributes) -> Result<CommandSpec>, // CommandSpec::new::<T>, carries T's factory
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        let mut registry = CommandRegistry::empty();
+        // Built-ins can't clash with each other, so registration can't fail here
+        registry.insert::<AggregateCommand>();
+        registry.insert::<ConditionCommand>();
+        registry.insert::<ExportCommand>();
+        registry.insert::<FileCommand>();
+        registry.insert::<SqlCommand>();
+        registry.insert::<TemplateCommand>();
+        registry
+    }
+}
+
+impl CommandRegistry {
+    // Pre-populated with the built-in commands
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn empty() -> Self {
+        CommandRegistry {
+            commands: HashMap::new(),
+        }
+    }
+
+    pub fn register<T: Command>(&mut self) -> Result<&mut Self> {
+        if self.commands.contains_key(T::command_type()) {
+            return Err(anyhow::anyhow!(
+                "Command type '{}' is already registered",
+                T::command_type()
+            ));
+        }
+        self.insert::<T>();
+        Ok(self)
+    }
+
+    pub fn with_command<T: Command>(mut self) -> Result<Self> {
+        self.register::<T>()?;
+        Ok(self)
+    }
+
+    fn insert<T: Command>(&mut self) {
+        tracing::debug!(command_type = %T::command_type(), "Registering command type");
+        self.commands.insert(
+            T::command_type().to_string(),
+            RegisteredCommand {
+                command_type: T::command_type(),
+                attributes: T::available_attributes(),
+                results: T::available_results(),
+                new_spec: CommandSpec::new::<T>,
+            },
+        );
+    }
+
+    pub fn get(&self, command_type: &str) -> Option<&RegisteredCommand> {
+        self.commands.get(command_type)
+    }
+
+    pub fn contains(&self, command_type: &str) -> bool {
+        self.commands.contains_key(command_type)
+    }
+
+    // Registered command types, sorted
+    pub fn command_types(&self) -> Vec<&str> {
+        let mut types: Vec<&str> = self.commands.keys().map(String::as_str).collect();
+        types.sort_unstable();
+        types
+    }
+}
+
+impl RegisteredCommand {
+    pub fn command_type(&self) -> &'static str {
+        self.command_type
+    }
+
+    // Includes the common attributes (when, on_error, ...)
+    pub fn attributes(&self) -> &[&'static AttributeSpec<&'static str>] {
+        &self.attributes
+    }
+
+    // Includes the common results (status, duration_ms, ...)
+    pub fn results(&self) -> &[&'static ResultSpec<&'static str>] {
+        &self.results
+    }
+
+    pub(crate) fn new_spec(
+        &self,
+        namespace_index: usize,
+        name: String,
+        attributes: Attributes,
+    ) -> Result<CommandSpec> {
+        (self.new_spec)(namespace_index, name, attributes)
+    }
+}
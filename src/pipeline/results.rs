@@ -13,7 +13,8 @@ pub const DEFAULT_OUTPUT_DIRNAME: &str = "panopticon_results";
     * ResultStore - Store of all command results after pipeline execution
     * CommandResults - Results produced by a single command, including metadata and actual data
     * ResultValue - Enum representing either a scalar result or a tabular result with associated metadata
-    * TabularFormat - Enum representing supported tabular data formats (CSV, Parquet, JSON)
+    * TabularFormat - Enum representing supported tabular data formats (CSV, Parquet, JSON, NDJSON)
+    * TabularWriteOptions - Format specific options used when writing tabular data
 */
 #[derive(Debug, Clone)]
 pub struct ResultSettings {
@@ -172,11 +173,19 @@ impl ResultValue {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TabularFormat {
     Csv,
     Parquet,
     Json,
+    Ndjson, // One JSON object per line
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TabularWriteOptions {
+    pub(crate) delimiter: u8,                                    // CSV only
+    pub(crate) header: bool,                                     // CSV only
+    pub(crate) compression: polars::prelude::ParquetCompression, // Parquet only
 }
 
 impl std::fmt::Display for TabularFormat {
@@ -191,6 +200,53 @@ impl TabularFormat {
             TabularFormat::Csv => "csv",
             TabularFormat::Parquet => "parquet",
             TabularFormat::Json => "json",
+            TabularFormat::Ndjson => "ndjson",
+        }
+    }
+
+    // Errors when an option that only applies to `only` is set for this format. Options for another
+    // format are mistakes rather than something to ignore.
+    pub(crate) fn check_option(
+        &self,
+        option: &str,
+        only: TabularFormat,
+        subject: &str,
+    ) -> Result<()> {
+        if *self != only {
+            return Err(anyhow::anyhow!(
+                "'{}' only applies to {} {}",
+                option,
+                only,
+                subject
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for TabularWriteOptions {
+    fn default() -> Self {
+        TabularWriteOptions {
+            delimiter: b',',
+            header: true,
+            compression: polars::prelude::ParquetCompression::default(),
+        }
+    }
+}
+
+impl std::str::FromStr for TabularFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "csv" => Ok(TabularFormat::Csv),
+            "parquet" => Ok(TabularFormat::Parquet),
+            "json" => Ok(TabularFormat::Json),
+            "ndjson" | "jsonl" => Ok(TabularFormat::Ndjson),
+            other => Err(anyhow::anyhow!(
+                "Unknown tabular format '{}', expected 'csv', 'parquet', 'json' or 'ndjson'",
+                other
+            )),
         }
     }
 }
@@ -199,12 +255,23 @@ pub(crate) fn write_tabular(
     df: &TabularValue,
     path: &PathBuf,
     format: &TabularFormat,
+) -> Result<()> {
+    write_tabular_with(df, path, format, &TabularWriteOptions::default())
+}
+
+pub(crate) fn write_tabular_with(
+    df: &TabularValue,
+    path: &PathBuf,
+    format: &TabularFormat,
+    options: &TabularWriteOptions,
 ) -> Result<()> {
     let mut df = df.clone();
     match format {
         TabularFormat::Csv => {
             let file = std::fs::File::create(path)?;
             polars::prelude::CsvWriter::new(file)
+                .with_separator(options.delimiter)
+                .include_header(options.header)
                 .finish(&mut df)
                 .context("Failed to write CSV")?;
         }
@@ -214,9 +281,17 @@ pub(crate) fn write_tabular(
                 .finish(&mut df)
                 .context("Failed to write JSON")?;
         }
+        TabularFormat::Ndjson => {
+            let file = std::fs::File::create(path)?;
+            polars::prelude::JsonWriter::new(file)
+                .with_json_format(polars::prelude::JsonFormat::JsonLines)
+                .finish(&mut df)
+                .context("Failed to write NDJSON")?;
+        }
         TabularFormat::Parquet => {
             let file = std::fs::File::create(path)?;
             polars::prelude::ParquetWriter::new(file)
+                .with_compression(options.compression)
                 .finish(&mut df)
                 .context("Failed to write Parquet")?;
         }
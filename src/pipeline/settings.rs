@@ -0,0 +1,136 @@
+use std::path::PathBuf;
+
+/*
+    Types:
+    * ExecutionSettings - Settings for how a Ready pipeline is executed (concurrency limits, deadline)
+    * PipelineDeadline - Extension holding the instant a pipeline with a timeout must finish by
+    * ErrorPolicy - What happens to the rest of the pipeline when a command fails ('on_error')
+*/
+#[derive(Debug, Clone)]
+pub struct ExecutionSettings {
+    pub(crate) max_concurrency: usize, // Maximum number of namespaces executing at the same time, 1 by default
+    pub(crate) timeout: Option<std::time::Duration>, // Pipeline-wide deadline, measured from the start of execute()
+    pub(crate) on_error: ErrorPolicy, // Default policy for commands without an 'on_error' attribute
+    pub(crate) checkpoint_dir: Option<PathBuf>, // Context is persisted here after every command when set
+    pub(crate) cache_dir: Option<PathBuf>, // Outputs of cacheable commands are stored and reused from here when set
+}
+
+// Builder methods
+impl ExecutionSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_concurrency(&self) -> usize {
+        self.max_concurrency
+    }
+
+    pub fn timeout(&self) -> Option<std::time::Duration> {
+        self.timeout
+    }
+
+    pub fn on_error(&self) -> ErrorPolicy {
+        self.on_error
+    }
+
+    pub fn checkpoint_dir(&self) -> Option<&PathBuf> {
+        self.checkpoint_dir.as_ref()
+    }
+
+    pub fn cache_dir(&self) -> Option<&PathBuf> {
+        self.cache_dir.as_ref()
+    }
+
+    // A limit of 1 executes namespaces strictly one after another, in plan order.
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_on_error(mut self, on_error: ErrorPolicy) -> Self {
+        self.on_error = on_error;
+        self
+    }
+
+    pub fn with_checkpoint_dir(mut self, dir: PathBuf) -> Self {
+        self.checkpoint_dir = Some(dir);
+        self
+    }
+
+    pub fn with_cache_dir(mut self, dir: PathBuf) -> Self {
+        self.cache_dir = Some(dir);
+        self
+    }
+}
+
+impl Default for ExecutionSettings {
+    fn default() -> Self {
+        ExecutionSettings {
+            max_concurrency: 1,
+            timeout: None,
+            on_error: ErrorPolicy::default(),
+            checkpoint_dir: None,
+            cache_dir: None,
+        }
+    }
+}
+
+// Inserted into Extensions when the pipeline has a timeout, so commands can bound their own work.
+#[derive(Debug, Clone, Copy)]
+pub struct PipelineDeadline(pub(crate) tokio::time::Instant);
+
+impl PipelineDeadline {
+    pub fn instant(&self) -> tokio::time::Instant {
+        self.0
+    }
+
+    pub fn remaining(&self) -> std::time::Duration {
+        self.0
+            .saturating_duration_since(tokio::time::Instant::now())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    #[default]
+    Fail, // Abort the pipeline with the command's error
+    Continue,       // Record the error and carry on with every other command
+    SkipDependents, // Record the error and skip commands that depend on the failed command's outputs
+}
+
+impl ErrorPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorPolicy::Fail => "fail",
+            ErrorPolicy::Continue => "continue",
+            ErrorPolicy::SkipDependents => "skip_dependents",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for ErrorPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(ErrorPolicy::Fail),
+            "continue" => Ok(ErrorPolicy::Continue),
+            "skip_dependents" => Ok(ErrorPolicy::SkipDependents),
+            other => Err(anyhow::anyhow!(
+                "Unknown on_error policy '{}', expected 'fail', 'continue' or 'skip_dependents'",
+                other
+            )),
+        }
+    }
+}
@@ -0,0 +1,3170 @@
+use super::*;
+use crate::test_utils::init_tracing;
+
+/*
+    ProbeCommand - test command that sleeps for `delay_ms` and records how many probes sharing the same
+    `probe` key were running at the same time. `after` is a StorePath reference used to declare ordering.
+*/
+static PROBE_SPEC: CommandSchema = LazyLock::new(|| {
+    CommandSpecBuilder::new()
+        .attribute(
+            AttributeSpecBuilder::new("probe", TypeDef::Scalar(ScalarType::String))
+                .required()
+                .hint("Key used to group concurrency statistics")
+                .build(),
+        )
+        .attribute(
+            AttributeSpecBuilder::new("delay_ms", TypeDef::Scalar(ScalarType::Number))
+                .hint("How long the command sleeps for")
+                .build(),
+        )
+        .attribute(
+            AttributeSpecBuilder::new("after", TypeDef::Scalar(ScalarType::String))
+                .hint("Store path this command depends on")
+                .reference(ReferenceKind::StorePath)
+                .build(),
+        )
+        .attribute(
+            AttributeSpecBuilder::new("label", TypeDef::Scalar(ScalarType::String))
+                .hint("Rendered value echoed back as the `label` result")
+                .reference(ReferenceKind::StaticTeraTemplate)
+                .build(),
+        )
+        .fixed_result(
+            "order",
+            TypeDef::Scalar(ScalarType::Number),
+            Some("Position this probe finished in, relative to its probe key"),
+            ResultKind::Data,
+        )
+        .fixed_result(
+            "label",
+            TypeDef::Scalar(ScalarType::String),
+            Some("The rendered `label` attribute, empty when not set"),
+            ResultKind::Data,
+        )
+        .build()
+});
+
+#[derive(Default)]
+struct ProbeStats {
+    running: usize,
+    peak: usize,
+    finished: usize,
+}
+
+static PROBES: LazyLock<std::sync::Mutex<HashMap<String, ProbeStats>>> =
+    LazyLock::new(Default::default);
+
+fn probe_peak(probe: &str) -> usize {
+    PROBES
+        .lock()
+        .unwrap()
+        .get(probe)
+        .map(|s| s.peak)
+        .unwrap_or(0)
+}
+
+struct ProbeCommand {
+    probe: String,
+    delay_ms: u64,
+    label: String,
+}
+
+impl Descriptor for ProbeCommand {
+    fn command_type() -> &'static str {
+        "ProbeCommand"
+    }
+    fn command_attributes() -> &'static [AttributeSpec<&'static str>] {
+        &PROBE_SPEC.0
+    }
+    fn command_results() -> &'static [ResultSpec<&'static str>] {
+        &PROBE_SPEC.1
+    }
+}
+
+impl FromAttributes for ProbeCommand {
+    fn from_attributes(attrs: &Attributes) -> Result<Self> {
+        Ok(ProbeCommand {
+            probe: attrs.get_required_string("probe")?,
+            delay_ms: attrs.get_optional_i64("delay_ms").unwrap_or(0) as u64,
+            label: attrs.get_optional_string("label").unwrap_or_default(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Executable for ProbeCommand {
+    async fn execute(&self, context: &ExecutionContext, output_prefix: &StorePath) -> Result<()> {
+        {
+            let mut probes = PROBES.lock().unwrap();
+            let stats = probes.entry(self.probe.clone()).or_default();
+            stats.running += 1;
+            stats.peak = stats.peak.max(stats.running);
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(self.delay_ms)).await;
+        let order = {
+            let mut probes = PROBES.lock().unwrap();
+            let stats = probes.entry(self.probe.clone()).or_default();
+            stats.running -= 1;
+            stats.finished += 1;
+            stats.finished
+        };
+        let out = InsertBatch::new(context, output_prefix);
+        out.u64("order", order as u64).await?;
+        out.string("label", self.label.clone()).await
+    }
+}
+
+fn probe_attrs(probe: &str, delay_ms: u64, after: Option<&str>) -> Attributes {
+    let mut attrs = ObjectBuilder::new()
+        .insert("probe", probe)
+        .insert("delay_ms", delay_ms)
+        .build_hashmap();
+    if let Some(after) = after {
+        attrs.insert("after".to_string(), to_scalar::string(after));
+    }
+    attrs
+}
+
+async fn scalar_at(completed: &Pipeline<Completed>, dotted: &str) -> Option<ScalarValue> {
+    completed
+        .state
+        .context
+        .scalar()
+        .get(&StorePath::from_dotted(dotted))
+        .await
+        .unwrap()
+}
+
+// Builds a pipeline holding just one command and returns the error, with its context, that the command
+// fails to compile or run with
+async fn command_error<T: Command>(namespace: &str, attrs: &Attributes) -> String {
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new(namespace))
+        .await
+        .unwrap()
+        .add_command::<T>("cmd", attrs)
+        .await
+        .unwrap();
+    let err = match pipeline.compile().await {
+        Ok(ready) => ready.execute().await.err(),
+        Err(err) => Some(err),
+    };
+    format!("{:#}", err.expect("the command should fail"))
+}
+
+#[tokio::test]
+async fn test_independent_namespaces_run_concurrently() {
+    init_tracing();
+
+    let mut pipeline = Pipeline::new();
+    for name in ["source_a", "source_b", "source_c"] {
+        pipeline
+            .add_namespace(NamespaceBuilder::new(name))
+            .await
+            .unwrap()
+            .add_command::<ProbeCommand>("load", &probe_attrs("ns_concurrent", 150, None))
+            .await
+            .unwrap();
+    }
+
+    let completed = pipeline
+        .compile()
+        .await
+        .unwrap()
+        .with_settings(ExecutionSettings::new().with_max_concurrency(3))
+        .execute()
+        .await
+        .unwrap();
+
+    assert_eq!(probe_peak("ns_concurrent"), 3);
+    for name in ["source_a", "source_b", "source_c"] {
+        let status = scalar_at(&completed, &format!("{name}.load.status")).await;
+        assert_eq!(status, Some(to_scalar::string("success")));
+    }
+}
+
+#[tokio::test]
+async fn test_namespace_dependencies_respected_when_concurrent() {
+    init_tracing();
+
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("first"))
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>("slow", &probe_attrs("ns_ordered", 100, None))
+        .await
+        .unwrap();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("second"))
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>(
+            "fast",
+            &probe_attrs("ns_ordered", 0, Some("first.slow.order")),
+        )
+        .await
+        .unwrap();
+
+    let completed = pipeline
+        .compile()
+        .await
+        .unwrap()
+        .with_settings(ExecutionSettings::new().with_max_concurrency(4))
+        .execute()
+        .await
+        .unwrap();
+
+    // The dependent namespace only starts once its dependency has finished
+    assert_eq!(probe_peak("ns_ordered"), 1);
+    assert_eq!(
+        scalar_at(&completed, "first.slow.order").await,
+        Some(to_scalar::u64(1))
+    );
+    assert_eq!(
+        scalar_at(&completed, "second.fast.order").await,
+        Some(to_scalar::u64(2))
+    );
+}
+
+#[tokio::test]
+async fn test_namespaces_are_sequential_by_default() {
+    init_tracing();
+
+    let mut pipeline = Pipeline::new();
+    for name in ["one", "two", "three"] {
+        pipeline
+            .add_namespace(NamespaceBuilder::new(name))
+            .await
+            .unwrap()
+            .add_command::<ProbeCommand>("step", &probe_attrs("ns_sequential", 20, None))
+            .await
+            .unwrap();
+    }
+
+    pipeline.compile().await.unwrap().execute().await.unwrap();
+
+    // Sequential unless the settings opt in
+    assert_eq!(ExecutionSettings::default().max_concurrency(), 1);
+    assert_eq!(probe_peak("ns_sequential"), 1);
+}
+
+#[tokio::test]
+async fn test_namespace_max_parallelism_runs_command_waves() {
+    init_tracing();
+
+    let mut pipeline = Pipeline::new();
+    let mut handle = pipeline
+        .add_namespace(NamespaceBuilder::new("load").max_parallelism(3))
+        .await
+        .unwrap();
+    for name in ["users", "orders", "products"] {
+        handle
+            .add_command::<ProbeCommand>(name, &probe_attrs("cmd_waves", 100, None))
+            .await
+            .unwrap();
+    }
+    // Depends on a command in the first wave, so it runs in a second wave on its own
+    handle
+        .add_command::<ProbeCommand>(
+            "summary",
+            &probe_attrs("cmd_waves", 0, Some("load.users.order")),
+        )
+        .await
+        .unwrap();
+
+    let completed = pipeline.compile().await.unwrap().execute().await.unwrap();
+
+    assert_eq!(probe_peak("cmd_waves"), 3);
+    assert_eq!(
+        scalar_at(&completed, "load.summary.order").await,
+        Some(to_scalar::u64(4))
+    );
+}
+
+#[tokio::test]
+async fn test_namespace_default_parallelism_is_sequential() {
+    init_tracing();
+
+    let mut pipeline = Pipeline::new();
+    let mut handle = pipeline
+        .add_namespace(NamespaceBuilder::new("load"))
+        .await
+        .unwrap();
+    for name in ["first", "second"] {
+        handle
+            .add_command::<ProbeCommand>(name, &probe_attrs("cmd_sequential", 20, None))
+            .await
+            .unwrap();
+    }
+
+    pipeline.compile().await.unwrap().execute().await.unwrap();
+
+    assert_eq!(probe_peak("cmd_sequential"), 1);
+}
+
+#[tokio::test]
+async fn test_parallel_iterations_use_isolated_scopes() {
+    init_tracing();
+
+    let items = ["alpha", "beta", "gamma", "delta"];
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("source").static_ns().insert(
+            "items",
+            ScalarValue::Array(items.iter().map(|i| to_scalar::string(*i)).collect()),
+        ))
+        .await
+        .unwrap();
+    let mut attrs = probe_attrs("iter_parallel", 100, None);
+    attrs.insert(
+        "label".to_string(),
+        to_scalar::string("{{ idx }}:{{ item }}"),
+    );
+    pipeline
+        .add_namespace(
+            NamespaceBuilder::new("fan_out")
+                .iterative()
+                .store_path(StorePath::from_segments(["source", "items"]))
+                .scalar_array(None)
+                .iter_var("item")
+                .index_var("idx")
+                .parallel_iterations(4),
+        )
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>("probe", &attrs)
+        .await
+        .unwrap();
+
+    let completed = pipeline.compile().await.unwrap().execute().await.unwrap();
+
+    assert_eq!(probe_peak("iter_parallel"), 4);
+    for (idx, item) in items.iter().enumerate() {
+        assert_eq!(
+            scalar_at(&completed, &format!("fan_out.probe.{idx}.label")).await,
+            Some(to_scalar::string(format!("{idx}:{item}")))
+        );
+    }
+    // Iteration variables never leak into the shared store
+    assert_eq!(scalar_at(&completed, "item").await, None);
+    assert_eq!(scalar_at(&completed, "idx").await, None);
+}
+
+/*
+    FlakyCommand - test command that fails the first `failures` times it runs for a given `key`.
+*/
+static FLAKY_SPEC: CommandSchema = LazyLock::new(|| {
+    CommandSpecBuilder::new()
+        .attribute(
+            AttributeSpecBuilder::new("key", TypeDef::Scalar(ScalarType::String))
+                .required()
+                .hint("Key used to count attempts")
+                .build(),
+        )
+        .attribute(
+            AttributeSpecBuilder::new("failures", TypeDef::Scalar(ScalarType::Number))
+                .required()
+                .hint("How many attempts fail before the command succeeds")
+                .build(),
+        )
+        .build()
+});
+
+static FLAKY_ATTEMPTS: LazyLock<std::sync::Mutex<HashMap<String, u64>>> =
+    LazyLock::new(Default::default);
+
+struct FlakyCommand {
+    key: String,
+    failures: u64,
+}
+
+impl Descriptor for FlakyCommand {
+    fn command_type() -> &'static str {
+        "FlakyCommand"
+    }
+    fn command_attributes() -> &'static [AttributeSpec<&'static str>] {
+        &FLAKY_SPEC.0
+    }
+    fn command_results() -> &'static [ResultSpec<&'static str>] {
+        &FLAKY_SPEC.1
+    }
+}
+
+impl FromAttributes for FlakyCommand {
+    fn from_attributes(attrs: &Attributes) -> Result<Self> {
+        Ok(FlakyCommand {
+            key: attrs.get_required_string("key")?,
+            failures: attrs.get_required_i64("failures")? as u64,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Executable for FlakyCommand {
+    async fn execute(&self, _context: &ExecutionContext, _output_prefix: &StorePath) -> Result<()> {
+        let attempt = {
+            let mut attempts = FLAKY_ATTEMPTS.lock().unwrap();
+            let count = attempts.entry(self.key.clone()).or_default();
+            *count += 1;
+            *count
+        };
+        if attempt <= self.failures {
+            return Err(anyhow::anyhow!("transient failure on attempt {}", attempt));
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_retries_recover_from_transient_errors() {
+    init_tracing();
+
+    let attrs = ObjectBuilder::new()
+        .insert("key", "retry_recovers")
+        .insert("failures", 2)
+        .insert("retries", 3)
+        .insert("retry_delay_ms", 20)
+        .insert("retry_backoff", 2.0)
+        .build_hashmap();
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("api"))
+        .await
+        .unwrap()
+        .add_command::<FlakyCommand>("fetch", &attrs)
+        .await
+        .unwrap();
+
+    let started = std::time::Instant::now();
+    let completed = pipeline.compile().await.unwrap().execute().await.unwrap();
+
+    // Two retries: 20ms, then 20ms * 2
+    assert!(started.elapsed() >= std::time::Duration::from_millis(60));
+    assert_eq!(
+        scalar_at(&completed, "api.fetch.status").await,
+        Some(to_scalar::string("success"))
+    );
+    assert_eq!(
+        scalar_at(&completed, "api.fetch.attempts").await,
+        Some(to_scalar::u64(3))
+    );
+}
+
+#[tokio::test]
+async fn test_retries_exhausted_fails_pipeline() {
+    init_tracing();
+
+    let attrs = ObjectBuilder::new()
+        .insert("key", "retry_exhausted")
+        .insert("failures", 5)
+        .insert("retries", 1)
+        .build_hashmap();
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("api"))
+        .await
+        .unwrap()
+        .add_command::<FlakyCommand>("fetch", &attrs)
+        .await
+        .unwrap();
+
+    let result = pipeline.compile().await.unwrap().execute().await;
+
+    assert!(result.is_err());
+    assert_eq!(FLAKY_ATTEMPTS.lock().unwrap()["retry_exhausted"], 2);
+}
+
+#[tokio::test]
+async fn test_pipeline_timeout_during_retry_wait() {
+    init_tracing();
+
+    let attrs = ObjectBuilder::new()
+        .insert("key", "retry_wait_timeout")
+        .insert("failures", 5)
+        .insert("retries", 3)
+        .insert("retry_delay_ms", 5000)
+        .insert("on_error", "continue")
+        .build_hashmap();
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("api"))
+        .await
+        .unwrap()
+        .add_command::<FlakyCommand>("fetch", &attrs)
+        .await
+        .unwrap();
+
+    let started = std::time::Instant::now();
+    let completed = pipeline
+        .compile()
+        .await
+        .unwrap()
+        .with_settings(ExecutionSettings::new().with_timeout(std::time::Duration::from_millis(50)))
+        .execute()
+        .await
+        .unwrap();
+
+    // The deadline passed while waiting for the second attempt, that's the outcome rather than the first error
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    assert_eq!(
+        scalar_at(&completed, "api.fetch.status").await,
+        Some(to_scalar::string("timeout"))
+    );
+    assert_eq!(
+        scalar_at(&completed, "api.fetch.attempts").await,
+        Some(to_scalar::u64(1))
+    );
+    let error = scalar_at(&completed, "api.fetch.error").await.unwrap();
+    assert!(error.as_str().unwrap().contains("timed out after"));
+}
+
+fn is_timeout(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|e| e.to_string().contains("timed out after"))
+}
+
+#[tokio::test]
+async fn test_command_timeout_ms() {
+    init_tracing();
+
+    let mut fast = probe_attrs("timeout_fast", 10, None);
+    fast.insert("timeout_ms".to_string(), to_scalar::u64(1000));
+    let mut slow = probe_attrs("timeout_slow", 5000, None);
+    slow.insert("timeout_ms".to_string(), to_scalar::u64(50));
+
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("fast"))
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>("probe", &fast)
+        .await
+        .unwrap();
+    let completed = pipeline.compile().await.unwrap().execute().await.unwrap();
+    assert_eq!(
+        scalar_at(&completed, "fast.probe.status").await,
+        Some(to_scalar::string("success"))
+    );
+
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("slow"))
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>("probe", &slow)
+        .await
+        .unwrap();
+    let started = std::time::Instant::now();
+    let err = pipeline
+        .compile()
+        .await
+        .unwrap()
+        .execute()
+        .await
+        .err()
+        .expect("slow command should time out");
+    assert!(is_timeout(&err), "unexpected error: {err:?}");
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+
+    // With the failure tolerated, the timeout shows in the command's status
+    slow.insert("on_error".to_string(), to_scalar::string("continue"));
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("slow"))
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>("probe", &slow)
+        .await
+        .unwrap();
+    let completed = pipeline.compile().await.unwrap().execute().await.unwrap();
+    assert_eq!(
+        scalar_at(&completed, "slow.probe.status").await,
+        Some(to_scalar::string("timeout"))
+    );
+    let error = scalar_at(&completed, "slow.probe.error").await.unwrap();
+    assert!(error.as_str().unwrap().contains("timed out after"));
+}
+
+#[tokio::test]
+async fn test_pipeline_timeout_bounds_execution() {
+    init_tracing();
+
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("slow"))
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>("probe", &probe_attrs("pipeline_timeout", 5000, None))
+        .await
+        .unwrap();
+
+    let started = std::time::Instant::now();
+    let err = pipeline
+        .compile()
+        .await
+        .unwrap()
+        .with_settings(ExecutionSettings::new().with_timeout(std::time::Duration::from_millis(50)))
+        .execute()
+        .await
+        .err()
+        .expect("pipeline deadline should interrupt the command");
+    assert!(is_timeout(&err), "unexpected error: {err:?}");
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+
+    // Commands starting after the deadline are cancelled rather than run
+    let mut interrupted = probe_attrs("pipeline_timeout_status", 5000, None);
+    interrupted.insert("on_error".to_string(), to_scalar::string("continue"));
+    let mut pipeline = Pipeline::new();
+    let mut handle = pipeline
+        .add_namespace(NamespaceBuilder::new("slow"))
+        .await
+        .unwrap();
+    handle
+        .add_command::<ProbeCommand>("interrupted", &interrupted)
+        .await
+        .unwrap();
+    handle
+        .add_command::<ProbeCommand>(
+            "later",
+            &probe_attrs(
+                "pipeline_timeout_status",
+                0,
+                Some("slow.interrupted.status"),
+            ),
+        )
+        .await
+        .unwrap();
+    let completed = pipeline
+        .compile()
+        .await
+        .unwrap()
+        .with_settings(ExecutionSettings::new().with_timeout(std::time::Duration::from_millis(50)))
+        .execute()
+        .await
+        .unwrap();
+    assert_eq!(
+        scalar_at(&completed, "slow.interrupted.status").await,
+        Some(to_scalar::string("timeout"))
+    );
+    assert_eq!(
+        scalar_at(&completed, "slow.later.status").await,
+        Some(to_scalar::string("cancelled"))
+    );
+    assert_eq!(scalar_at(&completed, "slow.later.order").await, None);
+}
+
+fn flaky_attrs(key: &str, failures: u64) -> Attributes {
+    ObjectBuilder::new()
+        .insert("key", key)
+        .insert("failures", failures)
+        .build_hashmap()
+}
+
+#[tokio::test]
+async fn test_on_error_continue_keeps_partial_results() {
+    init_tracing();
+
+    let mut failing = flaky_attrs("on_error_continue", 10);
+    failing.insert("on_error".to_string(), to_scalar::string("continue"));
+
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("api"))
+        .await
+        .unwrap()
+        .add_command::<FlakyCommand>("fetch", &failing)
+        .await
+        .unwrap();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("local"))
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>("load", &probe_attrs("on_error_continue", 0, None))
+        .await
+        .unwrap();
+
+    let completed = pipeline.compile().await.unwrap().execute().await.unwrap();
+
+    assert_eq!(
+        scalar_at(&completed, "api.fetch.status").await,
+        Some(to_scalar::string("error"))
+    );
+    let error = scalar_at(&completed, "api.fetch.error").await.unwrap();
+    assert!(error.as_str().unwrap().contains("transient failure"));
+    assert_eq!(
+        scalar_at(&completed, "local.load.status").await,
+        Some(to_scalar::string("success"))
+    );
+
+    let output = tempfile::tempdir().unwrap();
+    let results = completed
+        .results(ResultSettings::new().with_output_path(output.path().to_path_buf()))
+        .await
+        .unwrap();
+    let fetch = results
+        .get_by_source(&StorePath::from_dotted("api.fetch"))
+        .unwrap();
+    assert_eq!(
+        fetch.meta_get(&StorePath::from_dotted("api.fetch.status")),
+        Some(&to_scalar::string("error"))
+    );
+}
+
+#[tokio::test]
+async fn test_on_error_skip_dependents_from_pipeline_default() {
+    init_tracing();
+
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("api"))
+        .await
+        .unwrap()
+        .add_command::<FlakyCommand>("fetch", &flaky_attrs("on_error_skip", 10))
+        .await
+        .unwrap();
+    let mut handle = pipeline
+        .add_namespace(NamespaceBuilder::new("report"))
+        .await
+        .unwrap();
+    handle
+        .add_command::<ProbeCommand>(
+            "direct",
+            &probe_attrs("on_error_skip", 0, Some("api.fetch.status")),
+        )
+        .await
+        .unwrap();
+    handle
+        .add_command::<ProbeCommand>(
+            "transitive",
+            &probe_attrs("on_error_skip", 0, Some("report.direct.order")),
+        )
+        .await
+        .unwrap();
+    handle
+        .add_command::<ProbeCommand>("independent", &probe_attrs("on_error_skip", 0, None))
+        .await
+        .unwrap();
+
+    let completed = pipeline
+        .compile()
+        .await
+        .unwrap()
+        .with_settings(ExecutionSettings::new().with_on_error(ErrorPolicy::SkipDependents))
+        .execute()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        scalar_at(&completed, "api.fetch.status").await,
+        Some(to_scalar::string("error"))
+    );
+    for skipped in ["report.direct", "report.transitive"] {
+        assert_eq!(
+            scalar_at(&completed, &format!("{skipped}.status")).await,
+            Some(to_scalar::string("skipped"))
+        );
+    }
+    assert_eq!(
+        scalar_at(&completed, "report.independent.status").await,
+        Some(to_scalar::string("success"))
+    );
+}
+
+#[tokio::test]
+async fn test_on_error_skip_dependents_per_iteration() {
+    init_tracing();
+
+    // Both iterations share a flaky key, so only the first one fails
+    let items = ["shared", "shared"];
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("source").static_ns().insert(
+            "items",
+            ScalarValue::Array(items.iter().map(|i| to_scalar::string(*i)).collect()),
+        ))
+        .await
+        .unwrap();
+    let mut handle = pipeline
+        .add_namespace(
+            NamespaceBuilder::new("fan_out")
+                .iterative()
+                .store_path(StorePath::from_segments(["source", "items"]))
+                .scalar_array(None)
+                .iter_var("item"),
+        )
+        .await
+        .unwrap();
+    handle
+        .add_command::<FlakyCommand>("fetch", &flaky_attrs("skip_per_iteration_{{ item }}", 1))
+        .await
+        .unwrap();
+    handle
+        .add_command::<ProbeCommand>(
+            "report",
+            &probe_attrs("skip_per_iteration", 0, Some("fan_out.fetch.status")),
+        )
+        .await
+        .unwrap();
+
+    let completed = pipeline
+        .compile()
+        .await
+        .unwrap()
+        .with_settings(ExecutionSettings::new().with_on_error(ErrorPolicy::SkipDependents))
+        .execute()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        scalar_at(&completed, "fan_out.fetch.0.status").await,
+        Some(to_scalar::string("error"))
+    );
+    assert_eq!(
+        scalar_at(&completed, "fan_out.report.0.status").await,
+        Some(to_scalar::string("skipped"))
+    );
+    assert_eq!(
+        scalar_at(&completed, "fan_out.fetch.1.status").await,
+        Some(to_scalar::string("success"))
+    );
+    assert_eq!(
+        scalar_at(&completed, "fan_out.report.1.status").await,
+        Some(to_scalar::string("success"))
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_on_error_rejected_at_compile() {
+    init_tracing();
+
+    let mut attrs = probe_attrs("on_error_invalid", 0, None);
+    attrs.insert("on_error".to_string(), to_scalar::string("ignore"));
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("bad"))
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>("probe", &attrs)
+        .await
+        .unwrap();
+
+    assert!(pipeline.compile().await.is_err());
+}
+
+// Counts command events, started and finished should always match
+#[derive(Default, Clone)]
+struct CommandEventCounts {
+    started: Arc<std::sync::atomic::AtomicUsize>,
+    finished: Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl EventHooks for CommandEventCounts {
+    async fn before_execute_command(&self, _event: &hook_events::CommandInfo) -> Result<()> {
+        self.started
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Ok(())
+    }
+    async fn after_execute_command(&self, _event: &hook_events::CommandExecuted) -> Result<()> {
+        self.finished
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+async fn checkpointed_pipeline(flaky_key: &str, services: PipelineServices) -> Pipeline<Ready> {
+    let users = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/users.csv");
+    let file_attrs = ObjectBuilder::new()
+        .insert(
+            "files",
+            ScalarValue::Array(vec![
+                ObjectBuilder::new()
+                    .insert("name", "users")
+                    .insert("file", users.to_string_lossy().to_string())
+                    .insert("format", "csv")
+                    .build_scalar(),
+            ]),
+        )
+        .build_hashmap();
+
+    let mut pipeline = Pipeline::with_services(services);
+    let mut handle = pipeline
+        .add_namespace(NamespaceBuilder::new("data"))
+        .await
+        .unwrap();
+    handle
+        .add_command::<FileCommand>("load", &file_attrs)
+        .await
+        .unwrap();
+    handle
+        .add_command::<ProbeCommand>("probe", &probe_attrs(flaky_key, 0, None))
+        .await
+        .unwrap();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("step"))
+        .await
+        .unwrap()
+        .add_command::<FlakyCommand>("flaky", &flaky_attrs(flaky_key, 1))
+        .await
+        .unwrap();
+    pipeline.compile().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_resume_from_checkpoint_skips_successful_commands() {
+    init_tracing();
+
+    let checkpoint = tempfile::tempdir().unwrap();
+    let settings = ExecutionSettings::new()
+        .with_max_concurrency(1)
+        .with_checkpoint_dir(checkpoint.path().to_path_buf());
+
+    // First run dies on the flaky command, after the data namespace was checkpointed
+    let first = checkpointed_pipeline("checkpoint_resume", PipelineServices::new())
+        .await
+        .with_settings(settings)
+        .execute()
+        .await;
+    assert!(first.is_err());
+    assert!(checkpoint.path().join("manifest.json").exists());
+    assert!(
+        checkpoint
+            .path()
+            .join("tabular/data.load.users.data.parquet")
+            .exists()
+    );
+
+    let counts = CommandEventCounts::default();
+    let mut services = PipelineServices::new();
+    services.add_hook(counts.clone());
+    let completed = checkpointed_pipeline("checkpoint_resume", services)
+        .await
+        .resume_from(checkpoint.path())
+        .await
+        .unwrap();
+
+    // The probe was not run again, its result came from the checkpoint
+    assert_eq!(PROBES.lock().unwrap()["checkpoint_resume"].finished, 1);
+    // Commands restored from the checkpoint still finish for hooks
+    let started = counts.started.load(std::sync::atomic::Ordering::SeqCst);
+    assert_eq!(started, 3);
+    assert_eq!(
+        counts.finished.load(std::sync::atomic::Ordering::SeqCst),
+        started
+    );
+    assert_eq!(
+        scalar_at(&completed, "step.flaky.status").await,
+        Some(to_scalar::string("success"))
+    );
+    let users = completed
+        .state
+        .context
+        .tabular()
+        .get(&StorePath::from_dotted("data.load.users.data"))
+        .await
+        .unwrap()
+        .expect("frame restored from checkpoint");
+    assert!(users.height() > 0);
+}
+
+#[tokio::test]
+async fn test_checkpoint_saves_each_command_once() {
+    init_tracing();
+
+    let checkpoint = tempfile::tempdir().unwrap();
+    let frame = checkpoint
+        .path()
+        .join("tabular/data.load.users.data.parquet");
+    let commands = |dir: &std::path::Path| {
+        let manifest: ScalarValue =
+            serde_json::from_reader(std::fs::File::open(dir.join("manifest.json")).unwrap())
+                .unwrap();
+        let mut commands: Vec<String> = manifest["commands"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        commands.sort();
+        commands
+    };
+
+    let first = checkpointed_pipeline("checkpoint_incremental", PipelineServices::new())
+        .await
+        .with_settings(
+            ExecutionSettings::new()
+                .with_max_concurrency(1)
+                .with_checkpoint_dir(checkpoint.path().to_path_buf()),
+        )
+        .execute()
+        .await;
+    assert!(first.is_err());
+    let written = std::fs::metadata(&frame).unwrap().modified().unwrap();
+
+    // Finishing the run only writes the command that ran this time
+    checkpointed_pipeline("checkpoint_incremental", PipelineServices::new())
+        .await
+        .resume_from(checkpoint.path())
+        .await
+        .unwrap();
+    assert_eq!(
+        std::fs::metadata(&frame).unwrap().modified().unwrap(),
+        written
+    );
+    assert_eq!(
+        commands(checkpoint.path()),
+        ["data.load", "data.probe", "step.flaky"]
+    );
+
+    // Resuming into another directory carries the restored commands over
+    let moved = tempfile::tempdir().unwrap();
+    checkpointed_pipeline("checkpoint_incremental", PipelineServices::new())
+        .await
+        .with_settings(ExecutionSettings::new().with_checkpoint_dir(moved.path().to_path_buf()))
+        .resume_from(checkpoint.path())
+        .await
+        .unwrap();
+    assert!(
+        moved
+            .path()
+            .join("tabular/data.load.users.data.parquet")
+            .exists()
+    );
+    assert_eq!(commands(moved.path()), commands(checkpoint.path()));
+}
+
+async fn cached_file_pipeline(
+    file: &std::path::Path,
+    cache_dir: &std::path::Path,
+) -> Pipeline<Ready> {
+    let file_attrs = ObjectBuilder::new()
+        .insert(
+            "files",
+            ScalarValue::Array(vec![
+                ObjectBuilder::new()
+                    .insert("name", "users")
+                    .insert("file", file.to_string_lossy().to_string())
+                    .insert("format", "csv")
+                    .build_scalar(),
+            ]),
+        )
+        .build_hashmap();
+    let mut pipeline = Pipeline::new();
+    let mut handle = pipeline
+        .add_namespace(NamespaceBuilder::new("data"))
+        .await
+        .unwrap();
+    handle
+        .add_command::<FileCommand>("load", &file_attrs)
+        .await
+        .unwrap();
+    handle
+        .add_command::<ProbeCommand>("probe", &probe_attrs("cache_uncacheable", 0, None))
+        .await
+        .unwrap();
+    pipeline
+        .compile()
+        .await
+        .unwrap()
+        .with_settings(ExecutionSettings::new().with_cache_dir(cache_dir.to_path_buf()))
+}
+
+#[tokio::test]
+async fn test_cache_restores_unchanged_commands() {
+    init_tracing();
+
+    let workdir = tempfile::tempdir().unwrap();
+    let cache_dir = workdir.path().join("cache");
+    let users = workdir.path().join("users.csv");
+    std::fs::copy(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/users.csv"),
+        &users,
+    )
+    .unwrap();
+
+    let first = cached_file_pipeline(&users, &cache_dir)
+        .await
+        .execute()
+        .await
+        .unwrap();
+    assert_eq!(
+        scalar_at(&first, "data.load.cached").await,
+        Some(to_scalar::bool(false))
+    );
+    // Commands that don't opt in are never cached
+    assert_eq!(scalar_at(&first, "data.probe.cached").await, None);
+
+    let second = cached_file_pipeline(&users, &cache_dir)
+        .await
+        .execute()
+        .await
+        .unwrap();
+    assert_eq!(
+        scalar_at(&second, "data.load.cached").await,
+        Some(to_scalar::bool(true))
+    );
+    assert_eq!(
+        scalar_at(&second, "data.load.users.rows").await,
+        scalar_at(&first, "data.load.users.rows").await
+    );
+    let restored = second
+        .state
+        .context
+        .tabular()
+        .get(&StorePath::from_dotted("data.load.users.data"))
+        .await
+        .unwrap()
+        .expect("frame restored from cache");
+    assert!(restored.height() > 0);
+
+    // Changing the input file invalidates the entry
+    let mut contents = std::fs::read_to_string(&users).unwrap();
+    let extra_row = contents.lines().nth(1).unwrap().to_string();
+    contents.push_str(&extra_row);
+    contents.push('\n');
+    std::fs::write(&users, contents).unwrap();
+    let third = cached_file_pipeline(&users, &cache_dir)
+        .await
+        .execute()
+        .await
+        .unwrap();
+    assert_eq!(
+        scalar_at(&third, "data.load.cached").await,
+        Some(to_scalar::bool(false))
+    );
+}
+
+#[tokio::test]
+async fn test_cache_keys_follow_dependency_frames() {
+    init_tracing();
+
+    let workdir = tempfile::tempdir().unwrap();
+    let users = workdir.path().join("users.csv");
+    let yaml = format!(
+        r#"
+namespaces:
+  - name: data
+    commands:
+      - name: load
+        type: FileCommand
+        attrs:
+          files:
+            - name: users
+              file: "{users}"
+              format: csv
+  - name: query
+    commands:
+      - name: names
+        type: SqlCommand
+        attrs:
+          tables:
+            - name: users
+              source: data.load.users.data
+          query: "SELECT name FROM users"
+"#,
+        users = users.display()
+    );
+    let run = || async {
+        let document = PipelineDocument::parse(&yaml, DocumentFormat::Yaml).unwrap();
+        Pipeline::from_document(&document)
+            .await
+            .unwrap()
+            .compile()
+            .await
+            .unwrap()
+            .with_settings(ExecutionSettings::new().with_cache_dir(workdir.path().join("cache")))
+            .execute()
+            .await
+            .unwrap()
+    };
+
+    std::fs::write(&users, "name,age\nAnn,30\nBob,40\n").unwrap();
+    run().await;
+    // Same content in a rewritten file: the load reruns, the query's input frame hashes the same
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    std::fs::write(&users, "name,age\nAnn,30\nBob,40\n").unwrap();
+    let completed = run().await;
+    assert_eq!(
+        scalar_at(&completed, "data.load.cached").await,
+        Some(to_scalar::bool(false))
+    );
+    assert_eq!(
+        scalar_at(&completed, "query.names.cached").await,
+        Some(to_scalar::bool(true))
+    );
+
+    std::fs::write(&users, "name,age\nAnn,30\nCat,40\n").unwrap();
+    let completed = run().await;
+    assert_eq!(
+        scalar_at(&completed, "query.names.cached").await,
+        Some(to_scalar::bool(false))
+    );
+}
+
+#[tokio::test]
+async fn test_cache_keys_follow_each_iterations_dependencies() {
+    init_tracing();
+
+    let workdir = tempfile::tempdir().unwrap();
+    let yaml = format!(
+        r#"
+namespaces:
+  - name: config
+    mode: static
+    values:
+      regions: [eu, us]
+  - name: per_region
+    mode: iterative
+    store_path: config.regions
+    iter_var: region
+    index_var: idx
+    iterator:
+      type: scalar_array
+    commands:
+      - name: load
+        type: FileCommand
+        attrs:
+          files:
+            - name: users
+              file: "{dir}/{{{{ region }}}}.csv"
+              format: csv
+      - name: stats
+        type: AggregateCommand
+        attrs:
+          source: "per_region.load.{{{{ idx }}}}.users.data"
+          aggregations:
+            - name: row_count
+              op: count
+"#,
+        dir = workdir.path().display()
+    );
+    let run = || async {
+        let document = PipelineDocument::parse(&yaml, DocumentFormat::Yaml).unwrap();
+        Pipeline::from_document(&document)
+            .await
+            .unwrap()
+            .compile()
+            .await
+            .unwrap()
+            .with_settings(ExecutionSettings::new().with_cache_dir(workdir.path().join("cache")))
+            .execute()
+            .await
+            .unwrap()
+    };
+
+    std::fs::write(workdir.path().join("eu.csv"), "name\nAnn\n").unwrap();
+    std::fs::write(workdir.path().join("us.csv"), "name\nBob\n").unwrap();
+    run().await;
+
+    // Only the first iteration's upstream frame changes
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    std::fs::write(workdir.path().join("eu.csv"), "name\nAnn\nCat\n").unwrap();
+    let completed = run().await;
+    assert_eq!(
+        scalar_at(&completed, "per_region.stats.0.cached").await,
+        Some(to_scalar::bool(false))
+    );
+    assert_eq!(
+        scalar_at(&completed, "per_region.stats.0.row_count").await,
+        Some(to_scalar::u64(2))
+    );
+    assert_eq!(
+        scalar_at(&completed, "per_region.stats.1.cached").await,
+        Some(to_scalar::bool(true))
+    );
+}
+
+#[tokio::test]
+async fn test_explain_describes_plan_without_executing() {
+    init_tracing();
+
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("source").static_ns().insert(
+            "items",
+            ScalarValue::Array(vec![to_scalar::string("a"), to_scalar::string("b")]),
+        ))
+        .await
+        .unwrap();
+    pipeline
+        .add_namespace(
+            NamespaceBuilder::new("fan_out")
+                .iterative()
+                .store_path(StorePath::from_segments(["source", "items"]))
+                .scalar_array(None),
+        )
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>("each", &probe_attrs("explain", 0, None))
+        .await
+        .unwrap();
+    let mut handle = pipeline
+        .add_namespace(NamespaceBuilder::new("report"))
+        .await
+        .unwrap();
+    handle
+        .add_command::<ProbeCommand>("first", &probe_attrs("explain", 0, None))
+        .await
+        .unwrap();
+    handle
+        .add_command::<ProbeCommand>(
+            "second",
+            &probe_attrs("explain", 0, Some("report.first.order")),
+        )
+        .await
+        .unwrap();
+
+    let explanation = pipeline.compile().await.unwrap().explain().unwrap();
+
+    let names: Vec<&str> = explanation
+        .namespaces
+        .iter()
+        .map(|ns| ns.name.as_str())
+        .collect();
+    assert_eq!(names, ["source", "fan_out", "report"]);
+    let fan_out = &explanation.namespaces[1];
+    assert_eq!(fan_out.mode, "iterative");
+    assert_eq!(fan_out.iterates_over.as_deref(), Some("source.items"));
+    assert_eq!(fan_out.depends_on, ["source"]);
+    assert!(
+        fan_out.commands[0]
+            .results
+            .iter()
+            .any(|r| r.path == "fan_out.each.<index>.order" && r.kind == "data")
+    );
+    let report = &explanation.namespaces[2];
+    assert_eq!(report.commands[1].name, "second");
+    assert_eq!(report.commands[1].wave, 1);
+    assert_eq!(report.commands[1].dependencies, ["report.first.order"]);
+
+    let json: serde_json::Value = serde_json::from_str(&explanation.to_json().unwrap()).unwrap();
+    assert_eq!(json["namespaces"][2]["commands"][0]["name"], "first");
+    let text = explanation.to_string();
+    assert!(text.contains("2. fan_out [iterative over source.items] after source"));
+    assert!(text.contains("depends on: report.first.order"));
+    // Nothing ran
+    assert!(!PROBES.lock().unwrap().contains_key("explain"));
+}
+
+#[tokio::test]
+async fn test_graph_exports_typed_edges() {
+    init_tracing();
+
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(
+            NamespaceBuilder::new("source")
+                .static_ns()
+                .insert("items", ScalarValue::Array(vec![to_scalar::string("a")])),
+        )
+        .await
+        .unwrap();
+    pipeline
+        .add_namespace(
+            NamespaceBuilder::new("fan_out")
+                .iterative()
+                .store_path(StorePath::from_segments(["source", "items"]))
+                .scalar_array(None),
+        )
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>("each", &probe_attrs("graph", 0, None))
+        .await
+        .unwrap();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("report"))
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>(
+            "summary",
+            &probe_attrs("graph", 0, Some("fan_out.each.order")),
+        )
+        .await
+        .unwrap();
+
+    // Available on Draft pipelines as well as compiled ones
+    let graph = pipeline.graph();
+
+    assert_eq!(graph.nodes().count(), 5);
+    let edges: Vec<&GraphEdge> = graph.edges().collect();
+    assert!(edges.contains(&&GraphEdge {
+        from: "source".to_string(),
+        to: "fan_out".to_string(),
+        kind: GraphEdgeKind::IterativeSource {
+            path: "source.items".to_string()
+        },
+    }));
+    assert!(edges.contains(&&GraphEdge {
+        from: "fan_out.each".to_string(),
+        to: "report.summary".to_string(),
+        kind: GraphEdgeKind::StorePath {
+            path: "fan_out.each.order".to_string()
+        },
+    }));
+
+    let dot = pipeline.compile().await.unwrap().graph().to_dot();
+    assert!(dot.starts_with("digraph pipeline {"));
+    assert!(dot.contains(
+        "\"fan_out.each\" -> \"report.summary\" [label=\"fan_out.each.order\", style=solid];"
+    ));
+    assert!(dot.contains("\"source\" -> \"fan_out\" [label=\"source.items\", style=dashed];"));
+
+    let mermaid = graph.to_mermaid();
+    assert!(mermaid.starts_with("flowchart LR\n"));
+    // Nodes are numbered namespaces first, then commands
+    assert!(mermaid.contains("n0 -.->|\"source.items\"| n1"));
+    assert!(mermaid.contains("n3 -->|\"fan_out.each.order\"| n4"));
+}
+
+async fn targeted_pipeline(probe: &str) -> Pipeline<Ready> {
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("load"))
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>("raw", &probe_attrs(probe, 0, None))
+        .await
+        .unwrap();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("clean"))
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>("rows", &probe_attrs(probe, 0, Some("load.raw.order")))
+        .await
+        .unwrap();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("unrelated"))
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>("work", &probe_attrs(probe, 0, None))
+        .await
+        .unwrap();
+    pipeline
+        .add_namespace(NamespaceBuilder::new("report"))
+        .await
+        .unwrap()
+        .add_command::<ProbeCommand>("summary", &probe_attrs(probe, 0, Some("clean.rows.order")))
+        .await
+        .unwrap();
+    pipeline.compile().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_execute_targets_runs_dependency_closure() {
+    init_tracing();
+
+    // A path inside a command's outputs selects that command
+    let completed = targeted_pipeline("targets")
+        .await
+        .execute_targets(&[StorePath::from_dotted("clean.rows.order")])
+        .await
+        .unwrap();
+
+    let success = Some(to_scalar::string("success"));
+    assert_eq!(scalar_at(&completed, "load.raw.status").await, success);
+    assert_eq!(scalar_at(&completed, "clean.rows.status").await, success);
+    assert_eq!(scalar_at(&completed, "unrelated.work.status").await, None);
+    assert_eq!(scalar_at(&completed, "report.summary.status").await, None);
+    assert_eq!(PROBES.lock().unwrap()["targets"].finished, 2);
+
+    let err = targeted_pipeline("targets_unknown")
+        .await
+        .execute_targets(&[StorePath::from_dotted("missing.command")])
+        .await
+        .err()
+        .expect("unknown target should be rejected");
+    assert!(err.to_string().contains("does not match"));
+}
+
+#[tokio::test]
+async fn test_execute_targets_expands_namespaces() {
+    init_tracing();
+
+    let completed = targeted_pipeline("targets_namespace")
+        .await
+        .execute_targets(&[StorePath::from_dotted("clean")])
+        .await
+        .unwrap();
+
+    let success = Some(to_scalar::string("success"));
+    assert_eq!(scalar_at(&completed, "load.raw.status").await, success);
+    assert_eq!(scalar_at(&completed, "clean.rows.status").await, success);
+    assert_eq!(scalar_at(&completed, "unrelated.work.status").await, None);
+    assert_eq!(scalar_at(&completed, "report.summary.status").await, None);
+    assert_eq!(PROBES.lock().unwrap()["targets_namespace"].finished, 2);
+}
+
+#[tokio::test]
+async fn test_execute_downstream_includes_required_inputs() {
+    init_tracing();
+
+    let completed = targeted_pipeline("downstream")
+        .await
+        .execute_downstream(&[StorePath::from_dotted("clean")])
+        .await
+        .unwrap();
+
+    let success = Some(to_scalar::string("success"));
+    assert_eq!(scalar_at(&completed, "clean.rows.status").await, success);
+    assert_eq!(
+        scalar_at(&completed, "report.summary.status").await,
+        success
+    );
+    // Upstream of the selection so its inputs exist
+    assert_eq!(scalar_at(&completed, "load.raw.status").await, success);
+    assert_eq!(scalar_at(&completed, "unrelated.work.status").await, None);
+}
+
+const PIPELINE_YAML: &str = r#"
+namespaces:
+  - name: config
+    mode: static
+    values:
+      regions: [eu, us]
+      threshold: 10
+  - name: per_region
+    mode: iterative
+    store_path: config.regions
+    iter_var: region
+    iterator:
+      type: scalar_array
+    commands:
+      - name: check
+        type: ConditionCommand
+        attrs:
+          branches:
+            - name: is_eu
+              if: region == "eu"
+              then: "{{ region }} is local"
+          default: "{{ region }} is remote"
+"#;
+
+#[tokio::test]
+async fn test_pipeline_document_loads_and_round_trips() {
+    init_tracing();
+
+    let document = PipelineDocument::parse(PIPELINE_YAML, DocumentFormat::Yaml).unwrap();
+    let pipeline = Pipeline::from_document(&document).await.unwrap();
+
+    // Serialising the loaded pipeline gives back the same document, in any format
+    assert_eq!(pipeline.to_document(), document);
+    for format in [DocumentFormat::Json, DocumentFormat::Toml] {
+        let rendered = document.render(format).unwrap();
+        assert_eq!(
+            PipelineDocument::parse(&rendered, format).unwrap(),
+            document
+        );
+    }
+
+    let completed = pipeline.compile().await.unwrap().execute().await.unwrap();
+    assert_eq!(
+        scalar_at(&completed, "per_region.check.0.result").await,
+        Some(to_scalar::string("eu is local"))
+    );
+    assert_eq!(
+        scalar_at(&completed, "per_region.check.1.result").await,
+        Some(to_scalar::string("us is remote"))
+    );
+}
+
+#[tokio::test]
+async fn test_pipeline_document_keeps_static_sources() {
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.yaml");
+    std::fs::write(&config, "db:\n  host: db.internal\n").unwrap();
+
+    // cargo sets CARGO_PKG_* for test binaries
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_namespace(
+            NamespaceBuilder::new("config")
+                .static_ns()
+                .insert("timeout", to_scalar::u64(30))
+                .load_file(&config)
+                .load_env("CARGO_PKG_"),
+        )
+        .await
+        .unwrap();
+
+    // The sources are written, not what was read from them
+    let document = pipeline.to_document();
+    let namespace = &document.namespaces[0];
+    assert_eq!(namespace.values.keys().collect::<Vec<_>>(), ["timeout"]);
+    assert_eq!(
+        namespace.sources,
+        [
+            StaticSource::File {
+                path: config.clone()
+            },
+            StaticSource::Env {
+                prefix: "CARGO_PKG_".to_string()
+            },
+        ]
+    );
+    let rendered = document.render(DocumentFormat::Yaml).unwrap();
+    assert!(!rendered.contains("db.internal"));
+    assert!(!rendered.contains(env!("CARGO_PKG_NAME")));
+
+    // Loading the document reads the sources again
+    let reloaded =
+        Pipeline::from_document(&PipelineDocument::parse(&rendered, DocumentFormat::Yaml).unwrap())
+            .await
+            .unwrap();
+    assert_eq!(reloaded.to_document(), document);
+    let completed = reloaded.compile().await.unwrap().execute().await.unwrap();
+    assert_eq!(
+        scalar_at(&completed, "config.db.host").await,
+        Some(to_scalar::string("db.internal"))
+    );
+    assert_eq!(
+        scalar_at(&completed, "config.name").await,
+        Some(to_scalar::string(env!("CARGO_PKG_NAME")))
+    );
+}
+
+#[tokio::test]
+async fn test_example_pipeline_writes_tabular_results() {
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+
+    // The example's file paths are relative to the repository root, as for the CLI
+    let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut document =
+        PipelineDocument::from_file(root.join("examples/pipelines/products.yaml")).unwrap();
+    document.namespaces[0].commands[0].attrs.insert(
+        "files".to_string(),
+        ScalarValue::Array(vec![
+            ObjectBuilder::new()
+                .insert("name", "products")
+                .insert(
+                    "file",
+                    root.join("fixtures/products.csv")
+                        .to_string_lossy()
+                        .to_string(),
+                )
+                .insert("format", "csv")
+                .build_scalar(),
+        ]),
+    );
+    let completed = Pipeline::from_document(&document)
+        .await
+        .unwrap()
+        .compile()
+        .await
+        .unwrap()
+        .execute()
+        .await
+        .unwrap();
+    let results = completed
+        .results(ResultSettings::new().with_output_path(dir.path().to_path_buf()))
+        .await
+        .unwrap();
+
+    // A FileCommand's frames are stored under `<name>.data`
+    let (path, _, rows, _) = results
+        .get_by_source(&StorePath::from_dotted("data.load"))
+        .unwrap()
+        .data_get(&StorePath::from_dotted("data.load.products.data"))
+        .and_then(ResultValue::as_tabular)
+        .expect("tabular result for the loaded file");
+    assert_eq!(path, &dir.path().join("data.load.products.data.json"));
+    assert!(path.exists());
+    assert!(rows > 0);
+}
+
+#[tokio::test]
+async fn test_pipeline_document_rejects_invalid_definitions() {
+    init_tracing();
+
+    let unknown_type =
+        r#"{"namespaces": [{"name": "a", "commands": [{"name": "x", "type": "NopeCommand"}]}]}"#;
+    let document = PipelineDocument::parse(unknown_type, DocumentFormat::Json).unwrap();
+    let err = Pipeline::from_document(&document)
+        .await
+        .err()
+        .expect("unknown command type should be rejected");
+    assert!(format!("{err:#}").contains("Unknown command type 'NopeCommand'"));
+
+    // Iterative options on a Once namespace
+    let misplaced = "[[namespaces]]\nname = \"a\"\nstore_path = \"b.c\"\n";
+    let document = PipelineDocument::parse(misplaced, DocumentFormat::Toml).unwrap();
+    assert!(Pipeline::from_document(&document).await.is_err());
+
+    // Typo'd fields are caught when parsing
+    assert!(
+        PipelineDocument::parse(
+            "namespaces:\n  - name: a\n    mdoe: static\n",
+            DocumentFormat::Yaml
+        )
+        .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_registry_adds_commands_by_type() {
+    init_tracing();
+
+    let registry = CommandRegistry::new()
+        .with_command::<ProbeCommand>()
+        .unwrap();
+    assert!(registry.contains("FileCommand"));
+    assert!(
+        registry
+            .get("ProbeCommand")
+            .unwrap()
+            .attributes()
+            .iter()
+            .any(|attr| attr.name == "probe")
+    );
+    // Registering a type twice is a mistake, not an override
+    assert!(registry.clone().with_command::<ProbeCommand>().is_err());
+
+    let mut pipeline = Pipeline::new().with_registry(registry);
+    let mut handle = pipeline
+        .add_namespace(NamespaceBuilder::new("dynamic"))
+        .await
+        .unwrap();
+    handle
+        .add_command_by_type("probe", "ProbeCommand", &probe_attrs("registry", 0, None))
+        .await
+        .unwrap();
+    let err = handle
+        .add_command_by_type("nope", "NopeCommand", &Attributes::new())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("ProbeCommand"));
+
+    // Documents resolve command types through the same registry
+    let document = PipelineDocument::parse(
+        "namespaces:\n  - name: from_doc\n    commands:\n      - name: probe\n        type: ProbeCommand\n        attrs:\n          probe: registry\n",
+        DocumentFormat::Yaml,
+    )
+    .unwrap();
+    pipeline.load_document(&document).await.unwrap();
+
+    let completed = pipeline.compile().await.unwrap().execute().await.unwrap();
+    let success = Some(to_scalar::string("success"));
+    assert_eq!(scalar_at(&completed, "dynamic.probe.status").await, success);
+    assert_eq!(
+        scalar_at(&completed, "from_doc.probe.status").await,
+        success
+    );
+}
+
+const INPUTS_YAML: &str = r#"
+inputs:
+  - name: tenant
+    type: string
+    required: true
+    hint: Tenant the report is built for
+  - name: regions
+    type: array
+    default: [eu]
+  - name: threshold
+    type: number
+    default: 10
+namespaces:
+  - name: report
+    commands:
+      - name: title
+        type: ConditionCommand
+        attrs:
+          branches:
+            - name: big
+              if: inputs.threshold > 100
+              then: "{{ inputs.tenant }} (big)"
+          default: "{{ inputs.tenant }}"
+  - name: per_region
+    mode: iterative
+    store_path: inputs.regions
+    iter_var: region
+    iterator:
+      type: scalar_array
+    commands:
+      - name: label
+        type: ConditionCommand
+        attrs:
+          branches:
+            - name: any
+              if: "true"
+              then: "{{ inputs.tenant }}/{{ region }}"
+"#;
+
+#[tokio::test]
+async fn test_resume_uses_supplied_inputs() {
+    init_tracing();
+
+    let checkpoint = tempfile::tempdir().unwrap();
+    let document = PipelineDocument::parse(INPUTS_YAML, DocumentFormat::Yaml).unwrap();
+    let ready = || async {
+        Pipeline::from_document(&document)
+            .await
+            .unwrap()
+            .compile()
+            .await
+            .unwrap()
+    };
+    ready()
+        .await
+        .with_settings(
+            ExecutionSettings::new().with_checkpoint_dir(checkpoint.path().to_path_buf()),
+        )
+        .with_inputs(attrs!("tenant" => "acme"))
+        .execute()
+        .await
+        .unwrap();
+
+    let completed = ready()
+        .await
+        .with_inputs(attrs!("tenant" => "globex"))
+        .resume_from(checkpoint.path())
+        .await
+        .unwrap();
+    assert_eq!(
+        scalar_at(&completed, "inputs.tenant").await,
+        Some(to_scalar::string("globex"))
+    );
+}
+
+#[tokio::test]
+async fn test_inputs_are_validated_and_stored() {
+    init_tracing();
+
+    let document = PipelineDocument::parse(INPUTS_YAML, DocumentFormat::Yaml).unwrap();
+    let pipeline = Pipeline::from_document(&document).await.unwrap();
+    assert_eq!(pipeline.to_document(), document);
+    assert_eq!(pipeline.inputs().len(), 3);
+    let ready = pipeline.compile().await.unwrap();
+
+    // Defaults fill in whatever isn't supplied
+    let completed = ready
+        .with_inputs(attrs!("tenant" => "acme"))
+        .execute()
+        .await
+        .unwrap();
+    assert_eq!(
+        scalar_at(&completed, "inputs.threshold").await,
+        Some(to_scalar::i64(10))
+    );
+    assert_eq!(
+        scalar_at(&completed, "report.title.result").await,
+        Some(to_scalar::string("acme"))
+    );
+    assert_eq!(
+        scalar_at(&completed, "per_region.label.0.result").await,
+        Some(to_scalar::string("acme/eu"))
+    );
+
+    // The same compiled pipeline runs again against other values
+    let regions = to_scalar::array(vec![to_scalar::string("eu"), to_scalar::string("us")]);
+    let completed = completed
+        .restart()
+        .with_inputs(attrs!("tenant" => "globex", "regions" => regions, "threshold" => 500))
+        .execute()
+        .await
+        .unwrap();
+    assert_eq!(
+        scalar_at(&completed, "report.title.result").await,
+        Some(to_scalar::string("globex (big)"))
+    );
+    assert_eq!(
+        scalar_at(&completed, "per_region.label.1.result").await,
+        Some(to_scalar::string("globex/us"))
+    );
+
+    let ready = completed.restart();
+    let err = match ready.with_inputs(attrs!("threshold" => 5)).execute().await {
+        Err(err) => err,
+        Ok(_) => panic!("a required input was missing"),
+    };
+    assert!(err.to_string().contains("Missing required input 'tenant'"));
+
+    let ready = Pipeline::from_document(&document)
+        .await
+        .unwrap()
+        .compile()
+        .await
+        .unwrap();
+    let err = match ready
+        .with_inputs(attrs!("tenant" => "acme", "threshold" => "high"))
+        .execute()
+        .await
+    {
+        Err(err) => err,
+        Ok(_) => panic!("threshold isn't a number"),
+    };
+    assert!(
+        err.to_string()
+            .contains("'inputs.threshold' expected Number")
+    );
+
+    let ready = Pipeline::from_document(&document)
+        .await
+        .unwrap()
+        .compile()
+        .await
+        .unwrap();
+    let err = match ready
+        .with_inputs(attrs!("tenant" => "acme", "tennant" => "acme"))
+        .execute()
+        .await
+    {
+        Err(err) => err,
+        Ok(_) => panic!("'tennant' isn't declared"),
+    };
+    assert!(err.to_string().contains("Unknown input 'tennant'"));
+}
+
+#[tokio::test]
+async fn test_add_input_rejects_invalid_declarations() {
+    init_tracing();
+
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .add_input(
+            AttributeSpecBuilder::new("day", TypeDef::Scalar(ScalarType::String))
+                .required()
+                .build(),
+        )
+        .unwrap();
+    assert!(
+        pipeline
+            .add_input(
+                AttributeSpecBuilder::new("day", TypeDef::Scalar(ScalarType::Number)).build()
+            )
+            .is_err()
+    );
+    assert!(
+        pipeline
+            .add_input(AttributeSpecBuilder::new("frame", TypeDef::Tabular).build())
+            .is_err()
+    );
+    let err = pipeline
+        .add_input(
+            AttributeSpecBuilder::new("limit", TypeDef::Scalar(ScalarType::Number))
+                .default_value(to_scalar::string("ten"))
+                .build(),
+        )
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("Invalid default for input 'limit'"));
+
+    // 'inputs' is only reserved once the pipeline declares inputs
+    assert!(
+        pipeline
+            .add_namespace(
+                NamespaceBuilder::new("inputs")
+                    .static_ns()
+                    .insert("day", to_scalar::string("monday"))
+            )
+            .await
+            .is_err()
+    );
+    let mut undeclared = Pipeline::new();
+    undeclared
+        .add_namespace(
+            NamespaceBuilder::new("inputs")
+                .static_ns()
+                .insert("day", to_scalar::string("monday")),
+        )
+        .await
+        .unwrap();
+    assert!(
+        undeclared
+            .add_input(
+                AttributeSpecBuilder::new("day", TypeDef::Scalar(ScalarType::String)).build()
+            )
+            .is_err()
+    );
+}
+
+const SECRETS_YAML: &str = r#"
+secrets: [api_key]
+namespaces:
+  - name: call
+    commands:
+      - name: header
+        type: ConditionCommand
+        attrs:
+          branches:
+            - name: never
+              if: "false"
+              then: none
+          default: "Bearer {{ secrets.api_key }}"
+      - name: fetch
+        type: TemplateCommand
+        attrs:
+          templates:
+            - name: request
+              content: "GET /data"
+          render: request
+          output: "/proc/{{ secrets.api_key }}/request.txt"
+"#;
+
+#[tokio::test]
+async fn test_secrets_are_usable_but_redacted() {
+    init_tracing();
+    let secret = "s3cr3t-t0ken";
+    let providers: HashMap<String, Secret> = [("api_key".to_string(), Secret::new(secret))].into();
+    let dir = tempfile::tempdir().unwrap();
+
+    let document = PipelineDocument::parse(SECRETS_YAML, DocumentFormat::Yaml).unwrap();
+    let pipeline = Pipeline::from_document(&document).await.unwrap();
+    assert_eq!(pipeline.to_document(), document);
+    let completed = pipeline
+        .compile()
+        .await
+        .unwrap()
+        .with_secret_provider(providers)
+        .with_settings(
+            ExecutionSettings::new()
+                .with_on_error(ErrorPolicy::Continue)
+                .with_checkpoint_dir(dir.path().join("checkpoint")),
+        )
+        .execute()
+        .await
+        .unwrap();
+
+    // Commands see the real value
+    assert_eq!(
+        scalar_at(&completed, "call.header.result").await,
+        Some(to_scalar::string(format!("Bearer {}", secret)))
+    );
+    // Everything surfaced from the run doesn't
+    let error = scalar_at(&completed, "call.fetch.error").await.unwrap();
+    let error = error.as_str().unwrap();
+    assert!(error.contains(REDACTED));
+    assert!(!error.contains(secret));
+    assert!(!format!("{:?}", completed.context()).contains(secret));
+    assert_eq!(
+        completed.context().redact(&format!("key={}", secret)),
+        format!("key={}", REDACTED)
+    );
+
+    let results = completed
+        .results(ResultSettings::new().with_output_path(dir.path().join("out")))
+        .await
+        .unwrap();
+    let header = results
+        .get_by_source(&StorePath::from_dotted("call.header"))
+        .unwrap();
+    let (_, value) = header
+        .data_get(&StorePath::from_dotted("call.header.result"))
+        .and_then(ResultValue::as_scalar)
+        .unwrap();
+    assert_eq!(value, &to_scalar::string(format!("Bearer {}", REDACTED)));
+
+    for entry in walk_files(&dir.path().join("checkpoint")) {
+        let bytes = std::fs::read(&entry).unwrap();
+        assert!(
+            !String::from_utf8_lossy(&bytes).contains(secret),
+            "{} holds the secret",
+            entry.display()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_resume_reruns_commands_that_read_secrets() {
+    init_tracing();
+    let secret = "s3cr3t-t0ken";
+    let dir = tempfile::tempdir().unwrap();
+    let checkpoint = dir.path().join("checkpoint");
+    let document = PipelineDocument::parse(SECRETS_YAML, DocumentFormat::Yaml).unwrap();
+    let ready = || async {
+        let providers: HashMap<String, Secret> =
+            [("api_key".to_string(), Secret::new(secret))].into();
+        Pipeline::from_document(&document)
+            .await
+            .unwrap()
+            .compile()
+            .await
+            .unwrap()
+            .with_secret_provider(providers)
+            .with_settings(
+                ExecutionSettings::new()
+                    .with_on_error(ErrorPolicy::Continue)
+                    .with_checkpoint_dir(checkpoint.clone()),
+            )
+    };
+
+    ready().await.execute().await.unwrap();
+    let manifest = std::fs::read_to_string(checkpoint.join("manifest.json")).unwrap();
+    assert!(!manifest.contains("call.header"));
+
+    // The secret-derived output is computed again rather than restored as "[REDACTED]"
+    let completed = ready().await.resume_from(&checkpoint).await.unwrap();
+    assert_eq!(
+        scalar_at(&completed, "call.header.result").await,
+        Some(to_scalar::string(format!("Bearer {}", secret)))
+    );
+}
+
+fn walk_files(dir: &std::path::Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(walk_files(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+#[tokio::test]
+async fn test_secret_sources_and_declarations() {
+    init_tracing();
+    let secret = Secret::new("hunter2");
+    assert_eq!(format!("{:?}", secret), "Secret([REDACTED])");
+    assert_eq!(secret.to_string(), REDACTED);
+    assert_eq!(secret.expose(), "hunter2");
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("db_password"), "hunter2\n").unwrap();
+    let files = FileSecrets::new(dir.path());
+    assert_eq!(files.get("db_password").await.unwrap(), Some(secret));
+    assert_eq!(files.get("missing").await.unwrap(), None);
+    // CARGO_PKG_NAME is always set under cargo test
+    let env = EnvSecrets::new("CARGO_PKG_");
+    assert_eq!(
+        env.get("name").await.unwrap().unwrap().expose(),
+        env!("CARGO_PKG_NAME")
+    );
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add_secret("db_password").unwrap();
+    assert!(pipeline.add_secret("db_password").is_err());
+    assert!(
+        pipeline
+            .add_namespace(NamespaceBuilder::new("secrets").static_ns())
+            .await
+            .is_err()
+    );
+
+    // Declared secrets have to be found before anything runs
+    let err = match pipeline.compile().await.unwrap().execute().await {
+        Err(err) => err,
+        Ok(_) => panic!("there are no secret providers"),
+    };
+    assert!(err.to_string().contains("has no secret providers"));
+    let mut pipeline = Pipeline::new();
+    pipeline.add_secret("api_key").unwrap();
+    let err = match pipeline
+        .compile()
+        .await
+        .unwrap()
+        .with_secret_provider(files)
+        .execute()
+        .await
+    {
+        Err(err) => err,
+        Ok(_) => panic!("api_key has no file"),
+    };
+    assert!(
+        err.to_string()
+            .contains("Secret 'api_key' wasn't found by any secret provider")
+    );
+}
+
+const NAMESPACE_WHEN_YAML: &str = r#"
+namespaces:
+  - name: config
+    mode: static
+    values:
+      enrich: false
+      regions: [eu, us]
+  - name: enrich
+    when: config.enrich
+    commands:
+      - name: label
+        type: ConditionCommand
+        attrs:
+          branches:
+            - name: any
+              if: "true"
+              then: enriched
+  - name: per_region
+    mode: iterative
+    when: config.enrich
+    store_path: config.regions
+    iter_var: region
+    iterator:
+      type: scalar_array
+    commands:
+      - name: label
+        type: ConditionCommand
+        attrs:
+          branches:
+            - name: any
+              if: "true"
+              then: "{{ region }}"
+  - name: report
+    when: gate.mode.result == "full"
+    commands:
+      - name: title
+        type: ConditionCommand
+        attrs:
+          branches:
+            - name: any
+              if: "true"
+              then: full report
+  - name: gate
+    commands:
+      - name: mode
+        type: ConditionCommand
+        attrs:
+          branches:
+            - name: any
+              if: "true"
+              then: full
+"#;
+
+#[tokio::test]
+async fn test_namespace_when_skips_every_command() {
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+
+    let document = PipelineDocument::parse(NAMESPACE_WHEN_YAML, DocumentFormat::Yaml).unwrap();
+    let pipeline = Pipeline::from_document(&document).await.unwrap();
+    assert_eq!(pipeline.to_document(), document);
+    let ready = pipeline.compile().await.unwrap();
+
+    // 'report' waits for the namespace its condition reads
+    let explanation = ready.explain().unwrap();
+    let report = explanation
+        .namespaces
+        .iter()
+        .find(|ns| ns.name == "report")
+        .unwrap();
+    assert_eq!(report.depends_on, ["gate"]);
+    assert!(
+        explanation
+            .to_string()
+            .contains("report [once] after gate when gate.mode.result == \"full\"")
+    );
+    assert!(ready.graph().edges().any(|edge| edge.from == "gate.mode"
+        && edge.to == "report"
+        && edge.kind
+            == GraphEdgeKind::Condition {
+                path: "gate.mode.result".to_string()
+            }));
+
+    let completed = ready.execute().await.unwrap();
+    for path in ["enrich.label.status", "per_region.label.status"] {
+        assert_eq!(
+            scalar_at(&completed, path).await,
+            Some(to_scalar::string("skipped")),
+            "{}",
+            path
+        );
+    }
+    assert_eq!(scalar_at(&completed, "enrich.label.result").await, None);
+    assert_eq!(
+        scalar_at(&completed, "per_region.label.0.status").await,
+        None
+    );
+    assert_eq!(
+        scalar_at(&completed, "report.title.result").await,
+        Some(to_scalar::string("full report"))
+    );
+
+    // Skipped iterative commands are reported once
+    let results = completed
+        .results(ResultSettings::new().with_output_path(dir.path().to_path_buf()))
+        .await
+        .unwrap();
+    let skipped = results
+        .get_by_source(&StorePath::from_dotted("per_region.label"))
+        .unwrap();
+    assert_eq!(
+        skipped.meta_get(&StorePath::from_dotted("per_region.label.status")),
+        Some(&to_scalar::string("skipped"))
+    );
+}
+
+#[tokio::test]
+async fn test_namespace_when_rejects_invalid_conditions() {
+    init_tracing();
+
+    let mut pipeline = Pipeline::new();
+    let err = match pipeline
+        .add_namespace(NamespaceBuilder::new("stage").when("stage.load.rows > 0"))
+        .await
+    {
+        Err(err) => err,
+        Ok(_) => panic!("a namespace can't be gated on its own results"),
+    };
+    assert!(err.to_string().contains("can't reference its own results"));
+    assert!(
+        pipeline
+            .add_namespace(NamespaceBuilder::new("stage").when("config.enrich =="))
+            .await
+            .is_err()
+    );
+
+    let document = PipelineDocument::parse(
+        "namespaces:\n  - name: config\n    mode: static\n    when: \"true\"\n",
+        DocumentFormat::Yaml,
+    )
+    .unwrap();
+    let err = match Pipeline::from_document(&document).await {
+        Err(err) => err,
+        Ok(_) => panic!("static namespaces have no commands to skip"),
+    };
+    assert!(err.to_string().contains("'when' is not valid for Static"));
+}
+
+#[tokio::test]
+async fn test_export_command_writes_formats_mid_pipeline() {
+    use polars::prelude::SerReader;
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+    let users = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/users.csv");
+    let yaml = format!(
+        r#"
+namespaces:
+  - name: config
+    mode: static
+    values:
+      out: "{}"
+  - name: data
+    commands:
+      - name: load
+        type: FileCommand
+        attrs:
+          files:
+            - name: users
+              file: "{}"
+              format: csv
+  - name: export
+    commands:
+      - name: csv
+        type: ExportCommand
+        attrs:
+          source: data.load.users.data
+          path: "{{{{ config.out }}}}/nested/users.csv"
+          delimiter: ";"
+          header: false
+      - name: lines
+        type: ExportCommand
+        attrs:
+          source: data.load.users.data
+          path: "{{{{ config.out }}}}/users.ndjson"
+      - name: parquet
+        type: ExportCommand
+        attrs:
+          source: data.load.users.data
+          path: "{{{{ config.out }}}}/users.bin"
+          format: parquet
+          compression: snappy
+"#,
+        dir.path().display(),
+        users.display()
+    );
+    let document = PipelineDocument::parse(&yaml, DocumentFormat::Yaml).unwrap();
+    let completed = Pipeline::from_document(&document)
+        .await
+        .unwrap()
+        .compile()
+        .await
+        .unwrap()
+        .execute()
+        .await
+        .unwrap();
+
+    let csv_path = dir.path().join("nested/users.csv");
+    assert_eq!(
+        scalar_at(&completed, "export.csv.path").await,
+        Some(to_scalar::string(csv_path.display().to_string()))
+    );
+    assert_eq!(
+        scalar_at(&completed, "export.csv.rows").await,
+        Some(to_scalar::u64(3))
+    );
+    let csv = std::fs::read_to_string(&csv_path).unwrap();
+    assert_eq!(csv.lines().next(), Some("1;Alice;alice@example.com;30"));
+    assert_eq!(
+        scalar_at(&completed, "export.csv.size").await,
+        Some(to_scalar::u64(csv.len() as u64))
+    );
+
+    let lines = std::fs::read_to_string(dir.path().join("users.ndjson")).unwrap();
+    assert_eq!(lines.lines().count(), 3);
+    assert!(lines.starts_with(r#"{"id":1,"name":"Alice""#));
+
+    let file = std::fs::File::open(dir.path().join("users.bin")).unwrap();
+    let parquet = polars::prelude::ParquetReader::new(file).finish().unwrap();
+    assert_eq!(parquet.shape(), (3, 4));
+}
+
+#[tokio::test]
+async fn test_export_command_rejects_mismatched_options() {
+    init_tracing();
+    // Options are checked when the command is built from its substituted attributes, before it reads 'source'
+    let export =
+        |attrs: Attributes| async move { command_error::<ExportCommand>("export", &attrs).await };
+    let err = export(attrs!(
+        "source" => "data.load.users.data",
+        "path" => "users.json",
+        "delimiter" => ";"
+    ))
+    .await;
+    assert!(err.contains("'delimiter' only applies to csv"));
+    let err = export(attrs!(
+        "source" => "data.load.users.data",
+        "path" => "users.csv",
+        "compression" => "zstd"
+    ))
+    .await;
+    assert!(err.contains("'compression' only applies to parquet"));
+    let err = export(attrs!("source" => "data.load.users.data", "path" => "users.xlsx")).await;
+    assert!(err.contains("Unknown tabular format 'xlsx'"));
+    let err = export(attrs!(
+        "source" => "data.load.users.data",
+        "path" => "users",
+        "format" => "csv"
+    ))
+    .await;
+    assert!(err.contains("Source 'data.load.users.data' not found"));
+}
+
+#[tokio::test]
+async fn test_file_command_reader_options() {
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+    // Latin-1, semicolon separated, single quoted, no header and a banner line to skip
+    let csv = dir.path().join("customers.csv");
+    let mut bytes = b"exported 2024-01-15\n".to_vec();
+    bytes.extend_from_slice(b"007;'Jos\xe9; Sr.';NA\n0123;Ana;2.5\n999;Zo\xeb;4\n");
+    std::fs::write(&csv, bytes).unwrap();
+    let events = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/events.json");
+    let yaml = format!(
+        r#"
+namespaces:
+  - name: data
+    commands:
+      - name: load
+        type: FileCommand
+        attrs:
+          files:
+            - name: customers
+              file: "{}"
+              format: csv
+              delimiter: ";"
+              has_header: false
+              quote_char: "'"
+              skip_rows: 1
+              null_values: ["NA"]
+              encoding: latin1
+              dtypes:
+                column_1: string
+              columns: [column_1, column_2, column_3]
+              n_rows: 2
+            - name: events
+              file: "{}"
+              format: json
+              columns: [type, event_id]
+              dtypes:
+                event_id: float64
+              n_rows: 1
+"#,
+        csv.display(),
+        events.display()
+    );
+    let document = PipelineDocument::parse(&yaml, DocumentFormat::Yaml).unwrap();
+    let completed = Pipeline::from_document(&document)
+        .await
+        .unwrap()
+        .compile()
+        .await
+        .unwrap()
+        .execute()
+        .await
+        .unwrap();
+
+    let tabular = |dotted: &str| {
+        let path = StorePath::from_dotted(dotted);
+        let context = &completed.state.context;
+        async move { context.tabular().get(&path).await.unwrap().unwrap() }
+    };
+    let customers = tabular("data.load.customers.data").await;
+    assert_eq!(customers.shape(), (2, 3));
+    let ids = customers.column("column_1").unwrap().str().unwrap();
+    assert_eq!(ids.get(0), Some("007"));
+    assert_eq!(ids.get(1), Some("0123"));
+    let names = customers.column("column_2").unwrap().str().unwrap();
+    assert_eq!(names.get(0), Some("José; Sr."));
+    let scores = customers.column("column_3").unwrap().f64().unwrap();
+    assert_eq!(scores.get(0), None);
+    assert_eq!(scores.get(1), Some(2.5));
+
+    let events = tabular("data.load.events.data").await;
+    assert_eq!(events.get_column_names(), ["type", "event_id"]);
+    assert_eq!(events.height(), 1);
+    assert_eq!(
+        events.column("event_id").unwrap().f64().unwrap().get(0),
+        Some(1.0)
+    );
+    assert_eq!(
+        scalar_at(&completed, "data.load.total_rows").await,
+        Some(to_scalar::u64(3))
+    );
+}
+
+#[tokio::test]
+async fn test_file_command_rejects_invalid_reader_options() {
+    init_tracing();
+    let users = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/users.csv");
+    let load = |file: ScalarValue| async move {
+        let attrs = attrs!("files" => ScalarValue::Array(vec![file]));
+        command_error::<FileCommand>("data", &attrs).await
+    };
+    let file = |format: &str| {
+        ObjectBuilder::new()
+            .insert("name", "users")
+            .insert("file", users.display().to_string())
+            .insert("format", format)
+    };
+
+    // Types are checked against the spec when the pipeline compiles
+    let err = load(file("csv").insert("has_header", "no").build_scalar()).await;
+    assert!(err.contains("'files[0].has_header' expected Bool"));
+    let err = load(file("json").insert("delimiter", ";").build_scalar()).await;
+    assert!(err.contains("'delimiter' only applies to csv files"));
+    let err = load(file("csv").insert("delimiter", ";;").build_scalar()).await;
+    assert!(err.contains("'delimiter' must be a single ASCII character"));
+    let dtypes = ObjectBuilder::new().insert("age", "decimal").build_scalar();
+    let err = load(file("csv").insert("dtypes", dtypes).build_scalar()).await;
+    assert!(err.contains("Unknown dtype 'decimal'"));
+    let dtypes = ObjectBuilder::new()
+        .insert("missing", "int64")
+        .build_scalar();
+    let columns = ScalarValue::Array(vec![to_scalar::string("id".to_string())]);
+    let err = load(
+        file("csv")
+            .insert("columns", columns)
+            .insert("dtypes", dtypes)
+            .build_scalar(),
+    )
+    .await;
+    assert!(err.contains("'dtypes' names column 'missing'"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_file_command_reads_ndjson_and_compressed_files() {
+    use std::io::Write;
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+    let lines = "{\"level\":\"info\",\"ms\":12}\n{\"level\":\"warn\",\"ms\":40}\n";
+    std::fs::write(dir.path().join("app.jsonl"), lines).unwrap();
+    let mut gzip = flate2::write::GzEncoder::new(
+        std::fs::File::create(dir.path().join("users.csv.gz")).unwrap(),
+        flate2::Compression::default(),
+    );
+    gzip.write_all(&std::fs::read(fixtures.join("users.csv")).unwrap())
+        .unwrap();
+    gzip.finish().unwrap();
+    std::fs::write(
+        dir.path().join("app.ndjson.zst"),
+        zstd::encode_all(lines.as_bytes(), 0).unwrap(),
+    )
+    .unwrap();
+
+    let yaml = format!(
+        r#"
+namespaces:
+  - name: data
+    commands:
+      - name: load
+        type: FileCommand
+        attrs:
+          files:
+            - name: lines
+              file: "{dir}/app.jsonl"
+              format: ndjson
+            - name: users
+              file: "{dir}/users.csv.gz"
+              format: auto
+            - name: zstd
+              file: "{dir}/app.ndjson.zst"
+              format: auto
+              n_rows: 1
+            - name: metrics
+              file: "{fixtures}/metrics.parquet"
+              format: AUTO
+"#,
+        dir = dir.path().display(),
+        fixtures = fixtures.display()
+    );
+    let document = PipelineDocument::parse(&yaml, DocumentFormat::Yaml).unwrap();
+    let completed = Pipeline::from_document(&document)
+        .await
+        .unwrap()
+        .compile()
+        .await
+        .unwrap()
+        .execute()
+        .await
+        .unwrap();
+
+    let context = &completed.state.context;
+    let lines = context
+        .tabular()
+        .get(&StorePath::from_dotted("data.load.lines.data"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(lines.shape(), (2, 2));
+    assert_eq!(
+        lines.column("level").unwrap().str().unwrap().get(1),
+        Some("warn")
+    );
+    assert_eq!(
+        scalar_at(&completed, "data.load.users.rows").await,
+        Some(to_scalar::u64(3))
+    );
+    assert_eq!(
+        scalar_at(&completed, "data.load.zstd.rows").await,
+        Some(to_scalar::u64(1))
+    );
+    // 'size' is the size on disk, not the decompressed size
+    let gz_size = std::fs::metadata(dir.path().join("users.csv.gz"))
+        .unwrap()
+        .len();
+    assert_eq!(
+        scalar_at(&completed, "data.load.users.size").await,
+        Some(to_scalar::u64(gz_size))
+    );
+    assert!(
+        scalar_at(&completed, "data.load.metrics.rows")
+            .await
+            .is_some()
+    );
+
+    // Without a recognisable extension 'auto' has nothing to go on
+    let yaml = format!(
+        r#"
+namespaces:
+  - name: data
+    commands:
+      - name: load
+        type: FileCommand
+        attrs:
+          files:
+            - name: dump
+              file: "{}/dump.gz"
+              format: auto
+"#,
+        dir.path().display()
+    );
+    std::fs::write(dir.path().join("dump.gz"), b"").unwrap();
+    let document = PipelineDocument::parse(&yaml, DocumentFormat::Yaml).unwrap();
+    let err = match Pipeline::from_document(&document)
+        .await
+        .unwrap()
+        .compile()
+        .await
+        .unwrap()
+        .execute()
+        .await
+    {
+        Err(err) => format!("{:#}", err),
+        Ok(_) => panic!("'dump' has no format extension"),
+    };
+    assert!(err.contains("Can't detect the format of"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_file_command_globs_and_partitioned_directories() {
+    use polars::prelude::{Column, DataFrame, ParquetWriter};
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("exports")).unwrap();
+    std::fs::write(dir.path().join("exports/a.csv"), "id,name\n1,Ann\n2,Bob\n").unwrap();
+    std::fs::write(dir.path().join("exports/b.csv"), "id,score\n3,1.5\n").unwrap();
+    std::fs::write(dir.path().join("exports/notes.txt"), "not matched").unwrap();
+
+    let sales = dir.path().join("sales");
+    for (partition, amount) in [("year=2023/region=eu", 10i64), ("year=2024/region=us", 20)] {
+        let partition_dir = sales.join(partition);
+        std::fs::create_dir_all(&partition_dir).unwrap();
+        let mut df =
+            DataFrame::new(vec![Column::new("amount".into(), &[amount, amount + 1])]).unwrap();
+        let file = std::fs::File::create(partition_dir.join("part-0.parquet")).unwrap();
+        ParquetWriter::new(file).finish(&mut df).unwrap();
+    }
+    std::fs::write(sales.join("_SUCCESS"), "").unwrap();
+
+    let yaml = format!(
+        r#"
+namespaces:
+  - name: config
+    mode: static
+    values:
+      dir: "{}"
+  - name: data
+    commands:
+      - name: load
+        type: FileCommand
+        attrs:
+          files:
+            - name: exports
+              glob: "{{{{ config.dir }}}}/exports/*.csv"
+              format: auto
+              source_file: true
+            - name: sales
+              file: "{{{{ config.dir }}}}/sales"
+              format: parquet
+              dtypes:
+                year: int64
+"#,
+        dir.path().display()
+    );
+    let document = PipelineDocument::parse(&yaml, DocumentFormat::Yaml).unwrap();
+    let completed = Pipeline::from_document(&document)
+        .await
+        .unwrap()
+        .compile()
+        .await
+        .unwrap()
+        .execute()
+        .await
+        .unwrap();
+    let context = &completed.state.context;
+
+    let exports = context
+        .tabular()
+        .get(&StorePath::from_dotted("data.load.exports.data"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        exports.get_column_names(),
+        ["id", "name", "source_file", "score"]
+    );
+    assert_eq!(exports.height(), 3);
+    let names = exports.column("name").unwrap().str().unwrap();
+    assert_eq!(names.get(2), None);
+    let sources = exports.column("source_file").unwrap().str().unwrap();
+    assert!(sources.get(2).unwrap().ends_with("b.csv"));
+    assert_eq!(
+        scalar_at(&completed, "data.load.exports.files_matched").await,
+        Some(to_scalar::u64(2))
+    );
+
+    let sales = context
+        .tabular()
+        .get(&StorePath::from_dotted("data.load.sales.data"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(sales.get_column_names(), ["amount", "year", "region"]);
+    assert_eq!(sales.height(), 4);
+    let years = sales.column("year").unwrap().i64().unwrap();
+    assert_eq!(years.get(3), Some(2024));
+    let regions = sales.column("region").unwrap().str().unwrap();
+    assert_eq!(regions.get(0), Some("eu"));
+    assert_eq!(
+        scalar_at(&completed, "data.load.files_matched").await,
+        Some(to_scalar::u64(4))
+    );
+}
+
+// Nested template fields are left to the command, raw templates can extend each other
+#[tokio::test]
+async fn test_template_command_raw_templates_extend() {
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+    let yaml = format!(
+        r#"
+namespaces:
+  - name: config
+    mode: static
+    values:
+      title: Report
+  - name: render
+    commands:
+      - name: page
+        type: TemplateCommand
+        attrs:
+          templates:
+            - name: base
+              content: "<h1>{{% block title %}}{{% endblock title %}}</h1>"
+            - name: page
+              content: '{{% extends "base" %}}{{% block title %}}{{{{ config.title }}}}{{% endblock title %}}'
+          render: page
+          output: "{output}"
+          capture: true
+"#,
+        output = dir.path().join("page.html").display()
+    );
+    let document = PipelineDocument::parse(&yaml, DocumentFormat::Yaml).unwrap();
+    let completed = Pipeline::from_document(&document)
+        .await
+        .unwrap()
+        .compile()
+        .await
+        .unwrap()
+        .execute()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        scalar_at(&completed, "render.page.content").await,
+        Some(to_scalar::string("<h1>Report</h1>"))
+    );
+}
+
+#[tokio::test]
+async fn test_file_command_rejects_invalid_sources() {
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+    let load = |file: ObjectBuilder| async move {
+        let attrs = attrs!("files" => ScalarValue::Array(vec![file.insert("name", "entry").build_scalar()]));
+        command_error::<FileCommand>("data", &attrs).await
+    };
+    let pattern = format!("{}/*.csv", dir.path().display());
+
+    let err = load(ObjectBuilder::new().insert("format", "csv")).await;
+    assert!(err.contains("One of 'file' or 'glob' is required"));
+    let both = ObjectBuilder::new()
+        .insert("file", "a.csv")
+        .insert("glob", pattern.as_str())
+        .insert("format", "csv");
+    assert!(
+        load(both)
+            .await
+            .contains("'file' and 'glob' can't both be set")
+    );
+    let glob = ObjectBuilder::new()
+        .insert("glob", pattern.as_str())
+        .insert("format", "csv");
+    assert!(load(glob).await.contains("No files match"));
+    let directory = ObjectBuilder::new()
+        .insert("file", dir.path().display().to_string())
+        .insert("format", "csv");
+    assert!(
+        load(directory)
+            .await
+            .contains("Path is a directory, not a file")
+    );
+    let empty = ObjectBuilder::new()
+        .insert("file", dir.path().display().to_string())
+        .insert("format", "parquet");
+    assert!(load(empty).await.contains("No Parquet files found"));
+}
+
+#[tokio::test]
+async fn test_lazy_file_sql_aggregate_chain() {
+    init_tracing();
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+    let dir = tempfile::tempdir().unwrap();
+    let yaml = format!(
+        r#"
+namespaces:
+  - name: data
+    commands:
+      - name: load
+        type: FileCommand
+        attrs:
+          files:
+            - name: products
+              file: "{fixtures}/products.csv"
+              format: csv
+              lazy: true
+              dtypes:
+                quantity: float64
+  - name: query
+    commands:
+      - name: stocked
+        type: SqlCommand
+        attrs:
+          tables:
+            - name: products
+              source: data.load.products.data
+          query: "SELECT name, price, quantity FROM products WHERE quantity > 60"
+  - name: stats
+    commands:
+      - name: stocked
+        type: AggregateCommand
+        attrs:
+          source: query.stocked.data
+          aggregations:
+            - name: row_count
+              op: count
+            - name: total_quantity
+              column: quantity
+              op: sum
+"#,
+        fixtures = fixtures.display()
+    );
+    let document = PipelineDocument::parse(&yaml, DocumentFormat::Yaml).unwrap();
+    let completed = Pipeline::from_document(&document)
+        .await
+        .unwrap()
+        .compile()
+        .await
+        .unwrap()
+        .execute()
+        .await
+        .unwrap();
+    let context = &completed.state.context;
+
+    let products = StorePath::from_dotted("data.load.products.data");
+    let stocked = StorePath::from_dotted("query.stocked.data");
+    assert!(context.tabular().is_lazy(&products).await);
+    assert!(context.tabular().is_lazy(&stocked).await);
+
+    // Counting would run the plans
+    assert_eq!(
+        scalar_at(&completed, "data.load.products.rows").await,
+        Some(to_scalar::null())
+    );
+    assert_eq!(
+        scalar_at(&completed, "query.stocked.rows").await,
+        Some(to_scalar::null())
+    );
+    assert_eq!(
+        scalar_at(&completed, "stats.stocked.row_count").await,
+        Some(to_scalar::u64(2))
+    );
+    assert_eq!(
+        scalar_at(&completed, "stats.stocked.total_quantity")
+            .await
+            .and_then(|value| value.as_f64()),
+        Some(300.0)
+    );
+
+    // Results collect lazy entries to write them
+    let results = completed
+        .results(ResultSettings::new().with_output_path(dir.path().to_path_buf()))
+        .await
+        .unwrap();
+    let data = results
+        .get_by_source(&StorePath::from_dotted("query.stocked"))
+        .unwrap()
+        .data_get(&StorePath::from_dotted("query.stocked.data"))
+        .unwrap();
+    let (_, _, rows, _) = data.as_tabular().unwrap();
+    assert_eq!(rows, 2);
+
+    // A collected entry is kept, later reads don't run the plan again
+    let df = context.tabular().get(&stocked).await.unwrap().unwrap();
+    assert_eq!(df.get_column_names(), ["name", "price", "quantity"]);
+    assert!(!context.tabular().is_lazy(&stocked).await);
+}
+
+#[tokio::test]
+async fn test_lazy_entries_stay_lazy_with_cache_and_checkpoints() {
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+    let products = dir.path().join("products.csv");
+    let yaml = format!(
+        r#"
+namespaces:
+  - name: data
+    commands:
+      - name: load
+        type: FileCommand
+        attrs:
+          files:
+            - name: products
+              file: "{products}"
+              format: csv
+              lazy: true
+  - name: stats
+    commands:
+      - name: stock
+        type: AggregateCommand
+        attrs:
+          source: data.load.products.data
+          aggregations:
+            - name: total_quantity
+              column: quantity
+              op: sum
+"#,
+        products = products.display()
+    );
+    let run = || async {
+        let document = PipelineDocument::parse(&yaml, DocumentFormat::Yaml).unwrap();
+        Pipeline::from_document(&document)
+            .await
+            .unwrap()
+            .compile()
+            .await
+            .unwrap()
+            .with_settings(
+                ExecutionSettings::new()
+                    .with_cache_dir(dir.path().join("cache"))
+                    .with_checkpoint_dir(dir.path().join("checkpoint")),
+            )
+            .execute()
+            .await
+            .unwrap()
+    };
+    let data = StorePath::from_dotted("data.load.products.data");
+
+    std::fs::write(&products, "name,quantity\nWidget,100\nGadget,50\n").unwrap();
+    let completed = run().await;
+    assert!(completed.context().tabular().is_lazy(&data).await);
+    let manifest = std::fs::read_to_string(dir.path().join("checkpoint/manifest.json")).unwrap();
+    assert!(manifest.contains("stats.stock") && !manifest.contains("data.load"));
+
+    // The plan and its file are unchanged, the aggregate comes from the cache
+    let completed = run().await;
+    assert!(completed.context().tabular().is_lazy(&data).await);
+    assert_eq!(
+        scalar_at(&completed, "data.load.cached").await,
+        Some(to_scalar::bool(false))
+    );
+    assert_eq!(
+        scalar_at(&completed, "stats.stock.cached").await,
+        Some(to_scalar::bool(true))
+    );
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    std::fs::write(&products, "name,quantity\nWidget,100\nGadget,60\n").unwrap();
+    let completed = run().await;
+    assert_eq!(
+        scalar_at(&completed, "stats.stock.cached").await,
+        Some(to_scalar::bool(false))
+    );
+    assert_eq!(
+        scalar_at(&completed, "stats.stock.total_quantity")
+            .await
+            .and_then(|value| value.as_f64()),
+        Some(160.0)
+    );
+}
+
+#[tokio::test]
+async fn test_cache_keys_follow_eager_frames_in_lazy_plans() {
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+    let products = dir.path().join("products.csv");
+    let prices = dir.path().join("prices.csv");
+    let yaml = format!(
+        r#"
+namespaces:
+  - name: data
+    commands:
+      - name: load
+        type: FileCommand
+        attrs:
+          files:
+            - name: products
+              file: "{products}"
+              format: csv
+              lazy: true
+            - name: prices
+              file: "{prices}"
+              format: csv
+  - name: query
+    commands:
+      - name: priced
+        type: SqlCommand
+        attrs:
+          tables:
+            - name: products
+              source: data.load.products.data
+            - name: prices
+              source: data.load.prices.data
+          query: "SELECT p.name, p.quantity * c.price AS value FROM products p JOIN prices c ON p.name = c.name"
+  - name: stats
+    commands:
+      - name: stock
+        type: AggregateCommand
+        attrs:
+          source: query.priced.data
+          aggregations:
+            - name: total_value
+              column: value
+              op: sum
+"#,
+        products = products.display(),
+        prices = prices.display()
+    );
+    let run = || async {
+        let document = PipelineDocument::parse(&yaml, DocumentFormat::Yaml).unwrap();
+        Pipeline::from_document(&document)
+            .await
+            .unwrap()
+            .compile()
+            .await
+            .unwrap()
+            .with_settings(ExecutionSettings::new().with_cache_dir(dir.path().join("cache")))
+            .execute()
+            .await
+            .unwrap()
+    };
+
+    std::fs::write(&products, "name,quantity\nWidget,10\nGadget,5\n").unwrap();
+    std::fs::write(&prices, "name,price\nWidget,2\nGadget,4\n").unwrap();
+    run().await;
+    let completed = run().await;
+    assert!(
+        completed
+            .context()
+            .tabular()
+            .is_lazy(&StorePath::from_dotted("query.priced.data"))
+            .await
+    );
+    assert_eq!(
+        scalar_at(&completed, "stats.stock.cached").await,
+        Some(to_scalar::bool(true))
+    );
+
+    // Only the eager table changed, the plan and the lazy file are the same
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    std::fs::write(&prices, "name,price\nWidget,3\nGadget,4\n").unwrap();
+    let completed = run().await;
+    assert_eq!(
+        scalar_at(&completed, "stats.stock.cached").await,
+        Some(to_scalar::bool(false))
+    );
+    assert_eq!(
+        scalar_at(&completed, "stats.stock.total_value")
+            .await
+            .and_then(|value| value.as_f64()),
+        Some(50.0)
+    );
+}
+
+#[tokio::test]
+async fn test_lazy_file_entry_rejects_unscannable_files() {
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+    let gzip = dir.path().join("users.csv.gz");
+    let mut encoder =
+        flate2::write::GzEncoder::new(std::fs::File::create(&gzip).unwrap(), Default::default());
+    std::io::Write::write_all(&mut encoder, b"id,name\n1,Ann\n").unwrap();
+    encoder.finish().unwrap();
+    let json = dir.path().join("events.json");
+    std::fs::write(&json, r#"[{"id": 1}]"#).unwrap();
+
+    let load = |file: &std::path::Path, format: &str| {
+        let attrs = attrs!("files" => ScalarValue::Array(vec![ObjectBuilder::new()
+            .insert("name", "entry")
+            .insert("file", file.display().to_string())
+            .insert("format", format)
+            .insert("lazy", true)
+            .build_scalar()]));
+        async move { command_error::<FileCommand>("data", &attrs).await }
+    };
+    assert!(
+        load(&gzip, "auto")
+            .await
+            .contains("'lazy' can't scan compressed files")
+    );
+    assert!(
+        load(&json, "json")
+            .await
+            .contains("json arrays can't be scanned")
+    );
+}
@@ -6,18 +6,110 @@ use super::validation::validate_attributes;
     Types:
     * CommandFactory - Factory function type for creating command instances from attributes
     * ExecutableWrapper - Wrapper around Executable trait objects to handle common attributes like 'when
+    * RetryPolicy - Parsed 'retries', 'retry_delay_ms' and 'retry_backoff' common attributes
+    * AttemptTimedOut - Error returned when an attempt exceeds 'timeout_ms' or the pipeline deadline
 */
 pub type CommandFactory = Box<dyn Fn(&Attributes) -> Result<Box<dyn Executable>>>;
 
 struct ExecutableWrapper {
     inner: Box<dyn Executable>,
     when: Option<String>,
+    retry: RetryPolicy,
+    timeout: Option<std::time::Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RetryPolicy {
+    retries: u64,
+    delay_ms: u64,
+    backoff: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            retries: 0,
+            delay_ms: 0,
+            backoff: 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct AttemptTimedOut(std::time::Duration);
+
+impl std::fmt::Display for AttemptTimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Command timed out after {}ms", self.0.as_millis())
+    }
+}
+
+impl std::error::Error for AttemptTimedOut {}
+
+impl RetryPolicy {
+    fn from_attributes(attrs: &Attributes) -> Result<Self> {
+        let mut policy = RetryPolicy::default();
+        if let Some(value) = attrs.get("retries") {
+            policy.retries = value
+                .as_u64()
+                .ok_or_else(|| anyhow::anyhow!("'retries' must be a non-negative integer"))?;
+        }
+        if let Some(value) = attrs.get("retry_delay_ms") {
+            policy.delay_ms = value.as_u64().ok_or_else(|| {
+                anyhow::anyhow!("'retry_delay_ms' must be a non-negative integer")
+            })?;
+        }
+        if let Some(value) = attrs.get("retry_backoff") {
+            policy.backoff = value
+                .as_f64()
+                .filter(|b| b.is_finite() && *b >= 1.0)
+                .ok_or_else(|| anyhow::anyhow!("'retry_backoff' must be a number >= 1.0"))?;
+        }
+        Ok(policy)
+    }
+
+    // Delay before the given retry (1-based): retry_delay_ms * retry_backoff^(retry - 1)
+    fn delay_before(&self, retry: u64) -> std::time::Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as u64) as i32;
+        let delay = self.delay_ms as f64 * self.backoff.powi(exponent);
+        std::time::Duration::from_millis(delay.min(u64::MAX as f64) as u64)
+    }
+}
+
+impl ExecutableWrapper {
+    // Runs the inner command once, bounded by 'timeout_ms' and whatever is left of the pipeline deadline
+    async fn execute_attempt(
+        &self,
+        context: &ExecutionContext,
+        output_prefix: &StorePath,
+    ) -> Result<()> {
+        let remaining = context
+            .extensions()
+            .read()
+            .await
+            .get::<PipelineDeadline>()
+            .map(|deadline| deadline.remaining());
+        let limit = match (self.timeout, remaining) {
+            (Some(timeout), Some(remaining)) => Some(timeout.min(remaining)),
+            (timeout, remaining) => timeout.or(remaining),
+        };
+        match limit {
+            Some(limit) => tokio::time::timeout(limit, self.inner.execute(context, output_prefix))
+                .await
+                .unwrap_or_else(|_| Err(AttemptTimedOut(limit).into())),
+            None => self.inner.execute(context, output_prefix).await,
+        }
+    }
 }
 
 // Wrapper to handle 'when' conditional before executing the inner command
 // ^ Again might extend later with more common functionality
 #[async_trait::async_trait]
 impl Executable for ExecutableWrapper {
+    async fn input_files(&self, context: &ExecutionContext) -> Result<Vec<PathBuf>> {
+        self.inner.input_files(context).await
+    }
+
     #[tracing::instrument(skip(self, context, output_prefix), err, fields(
         command_output = %output_prefix.to_dotted(),
     ))]
@@ -29,9 +121,7 @@ impl Executable for ExecutableWrapper {
 
         // Evaluate when condition
         if let Some(condition) = &self.when {
-            let template = format!("{{{{ {} }}}}", condition);
-            let result = context.substitute(&template).await?;
-            if !is_truthy(&parse_scalar(&result)) {
+            if !evaluate_when(condition, context).await? {
                 tracing::debug!("Skipping command - 'when' condition is false");
                 batch
                     .string("status", EXECUTION_STATUS_SKIPPED.to_string())
@@ -43,8 +133,9 @@ impl Executable for ExecutableWrapper {
             }
         }
 
-        // Check for cancellation
-        if context.extensions().is_canceled().await {
+        // Check for cancellation. A command starting right at the deadline can beat the watcher cancelling the
+        // pipeline, so a passed deadline counts too.
+        if past_deadline(context).await || context.extensions().is_canceled().await {
             tracing::debug!("Skipping command - cancelled");
             batch
                 .string("status", EXECUTION_STATUS_CANCELLED.to_string())
@@ -55,22 +146,78 @@ impl Executable for ExecutableWrapper {
             return Ok(());
         }
 
-        let result = self.inner.execute(context, output_prefix).await;
+        let mut attempts: u64 = 0;
+        let mut cancelled = false; // Cancelled while waiting for a retry
+        let result = loop {
+            attempts += 1;
+            let result = self.execute_attempt(context, output_prefix).await;
+            let Err(e) = &result else {
+                break result;
+            };
+            if attempts > self.retry.retries {
+                break result;
+            }
+            let delay = self.retry.delay_before(attempts);
+            tracing::debug!(
+                attempt = attempts,
+                retries = self.retry.retries,
+                delay_ms = delay.as_millis() as u64,
+                error = %context.redact(&e.to_string()),
+                "Command failed, retrying"
+            );
+            // Give up early if the pipeline is cancelled while waiting for the next attempt. The command then
+            // ends as it would have mid-attempt, rather than with the failed attempt's error.
+            let interrupted = match context.extensions().cancellation_token().await {
+                Some(token) => {
+                    tokio::select! {
+                        _ = token.cancelled() => true,
+                        _ = tokio::time::sleep(delay) => false,
+                    }
+                }
+                None => {
+                    tokio::time::sleep(delay).await;
+                    false
+                }
+            };
+            if interrupted || context.extensions().is_canceled().await {
+                if past_deadline(context).await {
+                    break Err(AttemptTimedOut(start_time.elapsed()).into());
+                }
+                cancelled = true;
+                break Ok(());
+            }
+        };
+        // Redacted before it's stored or logged by the span
+        let result = result.map_err(|e| context.redact_error(e));
         let duration = start_time.elapsed().as_millis() as u64;
         batch.u64("duration_ms", duration).await?;
+        batch.u64("attempts", attempts).await?;
         // Set status based on execution result
         match &result {
+            Ok(_) if cancelled => {
+                batch
+                    .string("status", EXECUTION_STATUS_CANCELLED.to_string())
+                    .await?;
+            }
             Ok(_) => {
                 batch
                     .string("status", EXECUTION_STATUS_SUCCESS.to_string())
                     .await?;
             }
+            Err(e) if e.is::<AttemptTimedOut>() => {
+                batch
+                    .string("status", EXECUTION_STATUS_TIMEOUT.to_string())
+                    .await?;
+            }
             Err(_) => {
                 batch
                     .string("status", EXECUTION_STATUS_ERROR.to_string())
                     .await?;
             }
         }
+        if let Err(e) = &result {
+            batch.string("error", format!("{:#}", e)).await?;
+        }
         tracing::debug!(
             command_output = %output_prefix.to_dotted(),
             duration_ms = duration,
@@ -81,6 +228,22 @@ impl Executable for ExecutableWrapper {
     }
 }
 
+async fn past_deadline(context: &ExecutionContext) -> bool {
+    context
+        .extensions()
+        .read()
+        .await
+        .get::<PipelineDeadline>()
+        .is_some_and(|deadline| deadline.remaining().is_zero())
+}
+
+// Renders a 'when' conditional and checks whether the result is truthy
+pub(crate) async fn evaluate_when(condition: &str, context: &ExecutionContext) -> Result<bool> {
+    let template = format!("{{{{ {} }}}}", condition);
+    let result = context.substitute(&template).await?;
+    Ok(is_truthy(&parse_scalar(&result)))
+}
+
 /*
     Consts
     * COMMON_ATTRIBUTES - Common attributes shared by all commands
@@ -88,19 +251,64 @@ impl Executable for ExecutableWrapper {
     * STATUS constants - Standardized execution status strings
 
 */
-pub const COMMON_ATTRIBUTES: &[AttributeSpec<&'static str>] = &[AttributeSpec {
-    name: "when",
-    ty: TypeDef::Scalar(ScalarType::String),
-    required: false,
-    hint: Some("Evaluates a tera conditional to determine if the command should run"),
-    default_value: None,
-    reference_kind: ReferenceKind::RuntimeTeraTemplate,
-}];
+pub const COMMON_ATTRIBUTES: &[AttributeSpec<&'static str>] = &[
+    AttributeSpec {
+        name: "when",
+        ty: TypeDef::Scalar(ScalarType::String),
+        required: false,
+        hint: Some("Evaluates a tera conditional to determine if the command should run"),
+        default_value: None,
+        reference_kind: ReferenceKind::RuntimeTeraTemplate,
+    },
+    AttributeSpec {
+        name: "on_error",
+        ty: TypeDef::Scalar(ScalarType::String),
+        required: false,
+        hint: Some(
+            "What to do when the command fails: 'fail', 'continue' or 'skip_dependents' (defaults to the pipeline setting)",
+        ),
+        default_value: None,
+        reference_kind: ReferenceKind::Unsupported,
+    },
+    AttributeSpec {
+        name: "timeout_ms",
+        ty: TypeDef::Scalar(ScalarType::Number),
+        required: false,
+        hint: Some("Maximum milliseconds a single attempt of the command may run for"),
+        default_value: None,
+        reference_kind: ReferenceKind::Unsupported,
+    },
+    AttributeSpec {
+        name: "retries",
+        ty: TypeDef::Scalar(ScalarType::Number),
+        required: false,
+        hint: Some("Number of times to retry the command after a failed attempt (default 0)"),
+        default_value: None,
+        reference_kind: ReferenceKind::Unsupported,
+    },
+    AttributeSpec {
+        name: "retry_delay_ms",
+        ty: TypeDef::Scalar(ScalarType::Number),
+        required: false,
+        hint: Some("Milliseconds to wait before the first retry (default 0)"),
+        default_value: None,
+        reference_kind: ReferenceKind::Unsupported,
+    },
+    AttributeSpec {
+        name: "retry_backoff",
+        ty: TypeDef::Scalar(ScalarType::Number),
+        required: false,
+        hint: Some("Multiplier applied to the retry delay after each retry (default 1.0)"),
+        default_value: None,
+        reference_kind: ReferenceKind::Unsupported,
+    },
+];
 
 pub const EXECUTION_STATUS_SUCCESS: &str = "success";
 pub const EXECUTION_STATUS_SKIPPED: &str = "skipped";
 pub const EXECUTION_STATUS_ERROR: &str = "error";
 pub const EXECUTION_STATUS_CANCELLED: &str = "cancelled";
+pub const EXECUTION_STATUS_TIMEOUT: &str = "timeout";
 
 pub const COMMON_RESULTS: &[ResultSpec<&'static str>] = &[
     ResultSpec::Field {
@@ -114,9 +322,31 @@ pub const COMMON_RESULTS: &[ResultSpec<&'static str>] = &[
         ty: TypeDef::Scalar(ScalarType::String),
         kind: ResultKind::Meta,
         hint: Some(
-            "Execution status of the command: 'success', 'skipped', 'error', or 'cancelled'",
+            "Execution status of the command: 'success', 'skipped', 'error', 'cancelled', or 'timeout'",
         ),
     },
+    ResultSpec::Field {
+        name: "error",
+        ty: TypeDef::Scalar(ScalarType::String),
+        kind: ResultKind::Meta,
+        hint: Some(
+            "Error message when the command failed, timed out or was skipped because of a failure",
+        ),
+    },
+    ResultSpec::Field {
+        name: "cached",
+        ty: TypeDef::Scalar(ScalarType::Bool),
+        kind: ResultKind::Meta,
+        hint: Some(
+            "Whether the outputs were restored from the result cache (only set when caching is enabled)",
+        ),
+    },
+    ResultSpec::Field {
+        name: "attempts",
+        ty: TypeDef::Scalar(ScalarType::Number),
+        kind: ResultKind::Meta,
+        hint: Some("Number of times the command was executed, including retries"),
+    },
 ];
 
 /*
@@ -149,11 +379,22 @@ pub trait FromAttributes: Sized + Descriptor {
             validate_attributes(attrs, Self::available_attributes())?;
 
             let when = attrs.get("when").and_then(|v| v.as_str()).map(String::from);
+            let retry = RetryPolicy::from_attributes(attrs)?;
+            let timeout = match attrs.get("timeout_ms") {
+                Some(value) => Some(std::time::Duration::from_millis(
+                    value.as_u64().ok_or_else(|| {
+                        anyhow::anyhow!("'timeout_ms' must be a non-negative integer")
+                    })?,
+                )),
+                None => None,
+            };
 
             let instance = Self::from_attributes(attrs)?;
             let wrapped = ExecutableWrapper {
                 inner: Box::new(instance),
                 when,
+                retry,
+                timeout,
             };
             Ok(Box::new(wrapped) as Box<dyn Executable>)
         })
@@ -171,6 +412,10 @@ pub trait Descriptor: Sized {
     fn requires_extensions() -> &'static [ExtensionKey] {
         &[]
     }
+    // Defaults - Caching (only commands whose outputs are fully determined by their inputs should opt in)
+    fn cacheable() -> bool {
+        false
+    }
     // Defaults - Attributes
     fn available_attributes() -> Vec<&'static AttributeSpec<&'static str>> {
         let mut attrs = Vec::new();
@@ -202,4 +447,9 @@ pub trait Descriptor: Sized {
 #[async_trait::async_trait]
 pub trait Executable: Send + Sync + 'static {
     async fn execute(&self, context: &ExecutionContext, output_prefix: &StorePath) -> Result<()>;
+    // Files read by the command, their modification times become part of its cache key. Resolved against
+    // the context the command is about to execute in, so paths rendered from templates can be included.
+    async fn input_files(&self, _context: &ExecutionContext) -> Result<Vec<PathBuf>> {
+        Ok(Vec::new())
+    }
 }
@@ -0,0 +1,368 @@
+use crate::imports::*;
+use crate::pipeline::{Completed, Draft, Ready};
+use polars::prelude::LazyFrame;
+
+#[cfg(test)]
+mod tests;
+
+/*
+    Consts:
+    * PROMPT - Printed before reading each line in Repl::run
+    * DEFAULT_HEAD_ROWS - Rows shown by `head` when no count is given
+    * HELP - Text printed by `help`
+    * LOST_PIPELINE - Error once the pipeline couldn't be rebuilt after a failed compile or run
+*/
+pub const PROMPT: &str = "panopticon> ";
+pub const DEFAULT_HEAD_ROWS: usize = 10;
+const LOST_PIPELINE: &str = "The session has no pipeline, it was lost to an earlier error";
+const HELP: &str = "\
+Pipeline:
+  namespace <yaml>              Add a namespace, e.g. namespace {name: config, mode: static, values: {n: 1}}
+  command <namespace> <yaml>    Add a command, e.g. command load {name: users, type: FileCommand, attrs: {...}}
+  load <file>                   Add the namespaces of a pipeline file
+  save <file>                   Write the pipeline to a file (.yaml, .yml, .json or .toml)
+  types                         List the command types that can be added
+  plan                          Show the execution plan
+  run [path ...]                Execute the pipeline, or only the given targets and their dependencies
+Context (after run):
+  get [path]                    Show a scalar value, or the top level keys
+  frames                        List the tabular store keys
+  head <path> [rows]            Show the first rows of a frame
+  schema <path>                 Show a frame's columns and types
+  eval <expression>             Evaluate a Tera expression, e.g. eval config.n + 1
+  render <template>             Render a Tera template, e.g. render Hello {{ name }}
+  sql <query>                   Query frames by dotted path (quoted) or with dots replaced by '_'
+Other:
+  help                          Show this message
+  quit                          Exit";
+
+/*
+    Types:
+    * Repl - Line-oriented session keeping a pipeline alive across edits and runs
+    * ReplPipeline - The session's pipeline, either being edited or holding the context of the last run
+*/
+pub struct Repl {
+    pipeline: Option<ReplPipeline>,
+    finished: bool,
+}
+
+enum ReplPipeline {
+    Draft(Pipeline<Draft>),
+    Completed(Pipeline<Completed>),
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::with_pipeline(Pipeline::new())
+    }
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_pipeline(pipeline: Pipeline<Draft>) -> Self {
+        Repl {
+            pipeline: Some(ReplPipeline::Draft(pipeline)),
+            finished: false,
+        }
+    }
+
+    // Set once `quit` has been evaluated
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // Reads lines until EOF or `quit`. Errors are written to `output` and don't end the session.
+    pub async fn run(
+        &mut self,
+        mut input: impl std::io::BufRead,
+        mut output: impl std::io::Write,
+    ) -> Result<()> {
+        let mut line = String::new();
+        while !self.finished {
+            write!(output, "{}", PROMPT)?;
+            output.flush()?;
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                break;
+            }
+            match self.eval(&line).await {
+                Ok(text) if text.is_empty() => {}
+                Ok(text) => writeln!(output, "{}", text)?,
+                Err(e) => writeln!(output, "Error: {:#}", e)?,
+            }
+        }
+        Ok(())
+    }
+
+    // Evaluates a single line, returning the text to show
+    #[tracing::instrument(skip(self), err)]
+    pub async fn eval(&mut self, line: &str) -> Result<String> {
+        let line = line.trim();
+        let (command, rest) = line
+            .split_once(char::is_whitespace)
+            .map(|(c, r)| (c, r.trim()))
+            .unwrap_or((line, ""));
+        match command {
+            "" => Ok(String::new()),
+            "help" => Ok(HELP.to_string()),
+            "quit" | "exit" => {
+                self.finished = true;
+                Ok(String::new())
+            }
+            "namespace" => {
+                let namespace: NamespaceDocument = parse_inline(rest)?;
+                let name = namespace.name.clone();
+                let document = PipelineDocument {
+                    namespaces: vec![namespace],
+                    ..Default::default()
+                };
+                self.draft()?.load_document(&document).await?;
+                Ok(format!("Added namespace '{}'", name))
+            }
+            "command" => {
+                let (ns_name, definition) = rest
+                    .split_once(char::is_whitespace)
+                    .context("Usage: command <namespace> <yaml>")?;
+                let command: CommandDocument = parse_inline(definition)?;
+                let draft = self.draft()?;
+                let ns_idx = draft
+                    .namespaces
+                    .iter()
+                    .position(|ns| ns.name() == ns_name)
+                    .with_context(|| format!("No namespace named '{}'", ns_name))?;
+                if matches!(draft.namespaces[ns_idx].ty(), ExecutionMode::Static { .. }) {
+                    return Err(anyhow::anyhow!(
+                        "Static namespace '{}' can't hold commands",
+                        ns_name
+                    ));
+                }
+                let attributes: Attributes = command.attrs.into_iter().collect();
+                draft
+                    .add_command_by_type(ns_idx, &command.name, &command.command_type, &attributes)
+                    .await?;
+                Ok(format!("Added command '{}.{}'", ns_name, command.name))
+            }
+            "load" => {
+                let document = PipelineDocument::from_file(rest)?;
+                self.draft()?.load_document(&document).await?;
+                Ok(format!("Loaded {} namespaces", document.namespaces.len()))
+            }
+            "save" => {
+                let document = match self.pipeline()? {
+                    ReplPipeline::Draft(p) => p.to_document(),
+                    ReplPipeline::Completed(p) => p.to_document(),
+                };
+                document.to_file(rest)?;
+                Ok(format!("Saved to {}", rest))
+            }
+            "types" => {
+                let registry = match self.pipeline()? {
+                    ReplPipeline::Draft(p) => p.registry(),
+                    ReplPipeline::Completed(p) => p.registry(),
+                };
+                Ok(registry.command_types().join("\n"))
+            }
+            "plan" => {
+                let (namespaces, commands) = match self.pipeline()? {
+                    ReplPipeline::Draft(p) => (&p.namespaces, &p.commands),
+                    ReplPipeline::Completed(p) => (&p.namespaces, &p.commands),
+                };
+                let explanation = PipelineExplanation::new(namespaces, commands)?;
+                Ok(explanation.to_string().trim_end().to_string())
+            }
+            "run" => self.execute(rest).await,
+            "get" => {
+                let context = self.context()?;
+                if rest.is_empty() {
+                    let snapshot = context.scalar().snapshot().await;
+                    let mut keys: Vec<&String> = snapshot
+                        .as_object()
+                        .map(|o| o.keys().collect())
+                        .unwrap_or_default();
+                    keys.sort();
+                    return Ok(keys
+                        .into_iter()
+                        .map(String::as_str)
+                        .collect::<Vec<_>>()
+                        .join("\n"));
+                }
+                let value = context
+                    .scalar()
+                    .get(&StorePath::from_dotted(rest))
+                    .await?
+                    .with_context(|| format!("No scalar value at '{}'", rest))?;
+                Ok(serde_json::to_string_pretty(
+                    &context.scalar().redact_value(&value),
+                )?)
+            }
+            "frames" => {
+                let mut keys = self.context()?.tabular().keys().await;
+                keys.sort();
+                Ok(keys.join("\n"))
+            }
+            "head" => {
+                let mut args = rest.split_whitespace();
+                let path = args.next().context("Usage: head <path> [rows]")?;
+                let rows = match args.next() {
+                    Some(n) => n.parse().context("Row count must be a number")?,
+                    None => DEFAULT_HEAD_ROWS,
+                };
+                Ok(self.frame(path).await?.head(Some(rows)).to_string())
+            }
+            "schema" => {
+                let df = self.frame(rest).await?;
+                Ok(df
+                    .schema()
+                    .iter()
+                    .map(|(name, dtype)| format!("{}: {}", name, dtype))
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            "eval" => {
+                let context = self.context()?;
+                let rendered = context.substitute(format!("{{{{ {} }}}}", rest)).await;
+                Ok(context.redact(&rendered.map_err(|e| context.redact_error(e))?))
+            }
+            "render" => {
+                let context = self.context()?;
+                let rendered = context.substitute(rest).await;
+                Ok(context.redact(&rendered.map_err(|e| context.redact_error(e))?))
+            }
+            "sql" => {
+                let frames = self.context()?.tabular().snapshot_lazy().await;
+                let query = rest.to_string();
+                let df = tokio::task::spawn_blocking(move || run_sql(frames, &query))
+                    .await
+                    .context("SQL task panicked")??;
+                Ok(df.to_string())
+            }
+            other => Err(anyhow::anyhow!(
+                "Unknown command '{}', type 'help' for a list",
+                other
+            )),
+        }
+    }
+
+    // Compiles and executes, restarting the last run when nothing has changed since
+    async fn execute(&mut self, targets: &str) -> Result<String> {
+        let ready = match self.pipeline.take() {
+            Some(ReplPipeline::Completed(completed)) if targets.is_empty() => completed.restart(),
+            other => {
+                self.pipeline = other;
+                self.take_ready().await?
+            }
+        };
+        let (document, blank) = (ready.to_document(), ready.blank());
+        let targets: Vec<StorePath> = targets
+            .split_whitespace()
+            .map(StorePath::from_dotted)
+            .collect();
+        let started = Instant::now();
+        let executed = if targets.is_empty() {
+            ready.execute().await
+        } else {
+            ready.execute_targets(&targets).await
+        };
+        match executed {
+            Ok(completed) => {
+                let command_count = completed.commands.len();
+                self.pipeline = Some(ReplPipeline::Completed(completed));
+                Ok(format!(
+                    "Ran {} commands in {}ms",
+                    command_count,
+                    started.elapsed().as_millis()
+                ))
+            }
+            Err(e) => {
+                // execute() consumed the pipeline, rebuild it so the session can carry on editing
+                self.pipeline = Some(ReplPipeline::Draft(rebuild(&document, blank).await?));
+                Err(e)
+            }
+        }
+    }
+
+    fn pipeline(&self) -> Result<&ReplPipeline> {
+        self.pipeline.as_ref().context(LOST_PIPELINE)
+    }
+
+    // The pipeline in Draft, moving a completed one back with edit()
+    fn draft(&mut self) -> Result<&mut Pipeline<Draft>> {
+        let draft = match self.pipeline.take() {
+            Some(ReplPipeline::Draft(draft)) => draft,
+            Some(ReplPipeline::Completed(completed)) => completed.edit(),
+            None => return Err(anyhow::anyhow!(LOST_PIPELINE)),
+        };
+        match self.pipeline.insert(ReplPipeline::Draft(draft)) {
+            ReplPipeline::Draft(draft) => Ok(draft),
+            ReplPipeline::Completed(_) => unreachable!("just inserted a draft"),
+        }
+    }
+
+    // Compiles the draft, putting an equivalent draft back if compilation fails
+    async fn take_ready(&mut self) -> Result<Pipeline<Ready>> {
+        self.draft()?;
+        let Some(ReplPipeline::Draft(draft)) = self.pipeline.take() else {
+            unreachable!("draft() leaves a draft in place")
+        };
+        let (document, blank) = (draft.to_document(), draft.blank());
+        match draft.compile().await {
+            Ok(ready) => Ok(ready),
+            Err(e) => {
+                self.pipeline = Some(ReplPipeline::Draft(rebuild(&document, blank).await?));
+                Err(e)
+            }
+        }
+    }
+
+    fn context(&self) -> Result<&ExecutionContext> {
+        match self.pipeline()? {
+            ReplPipeline::Completed(completed) => Ok(completed.context()),
+            ReplPipeline::Draft(_) => Err(anyhow::anyhow!(
+                "Nothing to inspect, the pipeline hasn't run since it was last edited"
+            )),
+        }
+    }
+
+    async fn frame(&self, path: &str) -> Result<TabularValue> {
+        self.context()?
+            .tabular()
+            .get(&StorePath::from_dotted(path))
+            .await?
+            .with_context(|| format!("No frame at '{}'", path))
+    }
+}
+
+// Loads the document into a blank copy of the pipeline it came from (see Pipeline::blank)
+async fn rebuild(
+    document: &PipelineDocument,
+    mut pipeline: Pipeline<Draft>,
+) -> Result<Pipeline<Draft>> {
+    pipeline.load_document(document).await?;
+    Ok(pipeline)
+}
+
+// Inline definitions are YAML, which also accepts JSON and flow style ({name: x, mode: static})
+fn parse_inline<T: serde::de::DeserializeOwned>(input: &str) -> Result<T> {
+    if input.is_empty() {
+        return Err(anyhow::anyhow!("Missing definition, see 'help'"));
+    }
+    serde_yaml::from_str(input).context("Invalid definition")
+}
+
+// Every frame is registered under its dotted path and again with the dots replaced by underscores
+fn run_sql(frames: HashMap<String, LazyFrame>, query: &str) -> Result<TabularValue> {
+    let mut sql_ctx = polars::sql::SQLContext::new();
+    for (key, lf) in frames {
+        sql_ctx.register(&key.replace('.', "_"), lf.clone());
+        sql_ctx.register(&key, lf);
+    }
+    sql_ctx
+        .execute(query)
+        .map_err(|e| anyhow::anyhow!("SQL execution failed: {}", e))?
+        .collect()
+        .map_err(|e| anyhow::anyhow!("Failed to collect query result: {}", e))
+}
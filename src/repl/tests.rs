@@ -0,0 +1,147 @@
+use super::*;
+use crate::test_utils::init_tracing;
+
+fn fixture(name: &str) -> String {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(name)
+        .to_string_lossy()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_repl_edits_runs_and_inspects_context() {
+    init_tracing();
+
+    let mut repl = Repl::new();
+    repl.eval("namespace {name: config, mode: static, values: {limit: 2}}")
+        .await
+        .unwrap();
+    repl.eval("namespace {name: data}").await.unwrap();
+    repl.eval(&format!(
+        "command data {{name: load, type: FileCommand, attrs: {{files: [{{name: users, file: '{}', format: csv}}]}}}}",
+        fixture("users.csv")
+    ))
+    .await
+    .unwrap();
+
+    // Nothing to inspect before the first run
+    assert!(repl.eval("get config.limit").await.is_err());
+    assert!(
+        repl.eval("plan")
+            .await
+            .unwrap()
+            .contains("load (FileCommand)")
+    );
+
+    assert!(
+        repl.eval("run")
+            .await
+            .unwrap()
+            .starts_with("Ran 1 commands")
+    );
+    assert_eq!(repl.eval("get config.limit").await.unwrap(), "2");
+    assert_eq!(repl.eval("eval config.limit * 3").await.unwrap(), "6");
+    assert_eq!(
+        repl.eval("render limit={{ config.limit }}").await.unwrap(),
+        "limit=2"
+    );
+    assert!(
+        repl.eval("frames")
+            .await
+            .unwrap()
+            .contains("data.load.users.data")
+    );
+    assert!(
+        repl.eval("schema data.load.users.data")
+            .await
+            .unwrap()
+            .contains("name: str")
+    );
+    assert!(
+        repl.eval("head data.load.users.data 1")
+            .await
+            .unwrap()
+            .contains("Alice")
+    );
+    // Frames are addressable with underscores or by their quoted dotted path
+    let counted = repl
+        .eval("sql SELECT COUNT(*) AS n FROM data_load_users_data")
+        .await
+        .unwrap();
+    assert!(counted.contains("│ n "));
+    let youngest = repl
+        .eval("sql SELECT name FROM \"data.load.users.data\" ORDER BY age LIMIT 1")
+        .await
+        .unwrap();
+    assert!(youngest.contains("shape: (1, 1)"));
+
+    // Editing a completed pipeline goes back to Draft, the next run executes everything again
+    repl.eval("command data {name: flag, type: ConditionCommand, attrs: {branches: [{name: big, if: 'config.limit > 1', then: yes}]}}")
+        .await
+        .unwrap();
+    assert!(repl.eval("frames").await.is_err());
+    assert!(
+        repl.eval("run")
+            .await
+            .unwrap()
+            .starts_with("Ran 2 commands")
+    );
+    assert_eq!(repl.eval("get data.flag.result").await.unwrap(), "\"yes\"");
+}
+
+#[tokio::test]
+async fn test_repl_keeps_session_after_errors() {
+    init_tracing();
+
+    let mut repl = Repl::new();
+    repl.eval("namespace {name: work}").await.unwrap();
+    // Missing the required 'branches' attribute, caught when compiling
+    repl.eval("command work {name: broken, type: ConditionCommand}")
+        .await
+        .unwrap();
+    assert!(repl.eval("run").await.is_err());
+    // The draft survived the failed compile
+    assert!(repl.eval("plan").await.unwrap().contains("broken"));
+    assert!(
+        repl.eval("command nowhere {name: x, type: ConditionCommand}")
+            .await
+            .is_err()
+    );
+    assert!(repl.eval("frobnicate").await.is_err());
+
+    let input = "help\nquit\nnamespace {name: never}\n";
+    let mut output = Vec::new();
+    repl.run(input.as_bytes(), &mut output).await.unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("run [path ...]"));
+    assert!(repl.is_finished());
+    assert!(!repl.eval("plan").await.unwrap().contains("never"));
+}
+
+#[derive(Clone, Default)]
+struct CompileCount(Arc<std::sync::atomic::AtomicUsize>);
+
+#[async_trait::async_trait]
+impl EventHooks for CompileCount {
+    async fn before_compile_pipeline(&self, _event: &hook_events::PipelineInfo) -> Result<()> {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_repl_rebuild_keeps_services() {
+    init_tracing();
+
+    let compiles = CompileCount::default();
+    let mut repl = Repl::with_pipeline(Pipeline::new().with_hook(compiles.clone()));
+    repl.eval("namespace {name: work}").await.unwrap();
+    repl.eval("command work {name: broken, type: ConditionCommand}")
+        .await
+        .unwrap();
+    // Each failed compile rebuilds the draft, the hook has to survive it
+    assert!(repl.eval("run").await.is_err());
+    assert!(repl.eval("run").await.is_err());
+    assert_eq!(compiles.0.load(std::sync::atomic::Ordering::SeqCst), 2);
+}
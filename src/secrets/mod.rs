@@ -0,0 +1,173 @@
+use crate::imports::*;
+
+/*
+    Consts:
+    * REDACTED - What a secret value is replaced with wherever it would be shown
+*/
+pub const REDACTED: &str = "[REDACTED]";
+
+/*
+    Types:
+    * Secret - A secret string, usable through `expose` and redacted in Debug/Display
+    * EnvSecrets - SecretProvider reading `<prefix><NAME>` environment variables
+    * FileSecrets - SecretProvider reading one file per secret from a directory (e.g. Docker/Kubernetes secrets)
+    * Redactor - The secret values of a run, replaced in text and scalar values before they're surfaced
+*/
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+#[derive(Debug, Clone)]
+pub struct EnvSecrets {
+    prefix: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileSecrets {
+    dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Redactor {
+    values: Vec<String>, // Longest first, so a secret containing another is replaced whole
+}
+
+/*
+    Service trait:
+    * SecretProvider - Looks secrets up by name. Providers are asked in the order they were added, the first
+      to return Some wins.
+*/
+#[async_trait]
+pub trait SecretProvider: Send + Sync {
+    async fn get(&self, name: &str) -> Result<Option<Secret>>;
+}
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(value.into())
+    }
+
+    // The actual value, keep it out of logs and errors
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Secret(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Secret(value.to_string())
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Secret({})", REDACTED)
+    }
+}
+
+impl std::fmt::Display for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl EnvSecrets {
+    // A secret named `api_key` is read from `<prefix>API_KEY`
+    pub fn new(prefix: &str) -> Self {
+        EnvSecrets {
+            prefix: prefix.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl SecretProvider for EnvSecrets {
+    async fn get(&self, name: &str) -> Result<Option<Secret>> {
+        let variable = format!("{}{}", self.prefix, name.to_uppercase());
+        match std::env::var(&variable) {
+            Ok(value) => Ok(Some(Secret(value))),
+            Err(std::env::VarError::NotPresent) => Ok(None),
+            Err(e) => Err(anyhow::anyhow!(
+                "Can't read secret variable '{}': {}",
+                variable,
+                e
+            )),
+        }
+    }
+}
+
+impl FileSecrets {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        FileSecrets { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl SecretProvider for FileSecrets {
+    // Trailing newlines are dropped, most tools writing secret files add one
+    async fn get(&self, name: &str) -> Result<Option<Secret>> {
+        let path = self.dir.join(name);
+        match tokio::fs::read_to_string(&path).await {
+            Ok(value) => Ok(Some(Secret(
+                value.trim_end_matches(['\r', '\n']).to_string(),
+            ))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(anyhow::anyhow!(
+                "Can't read secret file '{}': {}",
+                path.display(),
+                e
+            )),
+        }
+    }
+}
+
+// Fixed values, mostly for tests and secrets fetched some other way
+#[async_trait]
+impl SecretProvider for HashMap<String, Secret> {
+    async fn get(&self, name: &str) -> Result<Option<Secret>> {
+        Ok(HashMap::get(self, name).cloned())
+    }
+}
+
+impl Redactor {
+    pub(crate) fn add(&mut self, secret: &Secret) {
+        // Redacting an empty string would mangle everything, it can't leak anything either
+        if secret.0.is_empty() || self.values.contains(&secret.0) {
+            return;
+        }
+        self.values.push(secret.0.clone());
+        self.values
+            .sort_by_key(|value| std::cmp::Reverse(value.len()));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub(crate) fn redact(&self, text: &str) -> String {
+        self.values.iter().fold(text.to_string(), |text, value| {
+            text.replace(value.as_str(), REDACTED)
+        })
+    }
+
+    pub(crate) fn redact_value(&self, value: &ScalarValue) -> ScalarValue {
+        match value {
+            ScalarValue::String(s) => ScalarValue::String(self.redact(s)),
+            ScalarValue::Array(items) => {
+                ScalarValue::Array(items.iter().map(|item| self.redact_value(item)).collect())
+            }
+            ScalarValue::Object(fields) => ScalarValue::Object(
+                fields
+                    .iter()
+                    .map(|(key, value)| (key.clone(), self.redact_value(value)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
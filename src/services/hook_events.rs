@@ -54,6 +54,7 @@ pub struct NamespaceInfo {
     pub namespace_index: usize,
     pub namespace_name: String,
     pub command_count: usize,
+    pub iteration_count: Option<usize>, // Iterative namespaces only, resolved before the hook fires
 }
 
 #[derive(Debug)]
@@ -76,6 +77,7 @@ pub struct CommandInfo {
     pub command_name: String,
     pub command_type: String,
     pub command_count: usize,
+    pub iteration_index: Option<usize>,
 }
 
 #[derive(Debug)]
@@ -83,5 +85,7 @@ pub struct CommandExecuted {
     pub namespace_index: usize,
     pub command_name: String,
     pub command_type: String,
+    pub iteration_index: Option<usize>,
+    pub status: Option<String>, // The command's 'status' result, e.g. "success" or "skipped"
     pub executed_at: Instant,
 }
@@ -55,6 +55,7 @@ macro_rules! hook_dispatch {
 pub struct PipelineServices {
     io: Vec<Arc<dyn PipelineIO>>,
     hooks: Vec<Arc<dyn EventHooks>>,
+    secret_providers: Vec<Arc<dyn SecretProvider>>,
 }
 
 impl std::fmt::Debug for PipelineServices {
@@ -62,6 +63,7 @@ impl std::fmt::Debug for PipelineServices {
         f.debug_struct("PipelineServices")
             .field("io_count", &self.io.len())
             .field("hooks_count", &self.hooks.len())
+            .field("secret_provider_count", &self.secret_providers.len())
             .finish()
     }
 }
@@ -80,6 +82,24 @@ impl PipelineServices {
         self.hooks.push(Arc::new(hook));
     }
 
+    pub fn add_secret_provider<T: SecretProvider + 'static>(&mut self, provider: T) {
+        self.secret_providers.push(Arc::new(provider));
+    }
+
+    pub(crate) fn has_secret_providers(&self) -> bool {
+        !self.secret_providers.is_empty()
+    }
+
+    // Asks each secret provider in turn, the first to know the secret wins
+    pub async fn secret(&self, name: &str) -> Result<Option<Secret>> {
+        for provider in &self.secret_providers {
+            if let Some(secret) = provider.get(name).await? {
+                return Ok(Some(secret));
+            }
+        }
+        Ok(None)
+    }
+
     // Single notify method, applies to all registered IO services
     pub async fn notify(&self, message: &str) -> Result<()> {
         let mut errors = Vec::new();
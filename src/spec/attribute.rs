@@ -34,6 +34,24 @@ pub struct AttributeSpec<T: Into<String>> {
     pub(crate) reference_kind: super::ReferenceKind, // Only valid/evaluated when TypeDef is Scalar or Tabular
 }
 
+impl<T: Into<String>> AttributeSpec<T> {
+    pub fn name(&self) -> &T {
+        &self.name
+    }
+    pub fn type_def(&self) -> &TypeDef<T> {
+        &self.ty
+    }
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+    pub fn hint(&self) -> Option<&T> {
+        self.hint.as_ref()
+    }
+    pub fn default_value(&self) -> Option<&ScalarValue> {
+        self.default_value.as_ref()
+    }
+}
+
 impl From<AttributeSpec<&'static str>> for AttributeSpec<String> {
     fn from(attr: AttributeSpec<&'static str>) -> Self {
         AttributeSpec {
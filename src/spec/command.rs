@@ -15,6 +15,7 @@ pub struct CommandSpec {
     pub dependencies: HashSet<StorePath>,
     pub provides_extensions: Vec<ExtensionKey>,
     pub requires_extensions: Vec<ExtensionKey>,
+    pub cacheable: bool,
 }
 
 impl CommandSpec {
@@ -41,11 +42,74 @@ impl CommandSpec {
             dependencies,
             provides_extensions: T::provides_extensions().to_vec(),
             requires_extensions: T::requires_extensions().to_vec(),
+            cacheable: T::cacheable(),
         })
     }
 
     pub(crate) fn validate_attributes(&self) -> Result<()> {
         use crate::pipeline::validation::validate_attributes;
-        validate_attributes(&self.attributes, &self.exepected_attributes)
+        validate_attributes(&self.attributes, &self.exepected_attributes)?;
+        self.error_policy()
+            .with_context(|| format!("Invalid 'on_error' on command '{}'", self.name))?;
+        Ok(())
+    }
+
+    // Resolves expected_results into (field_path, kind, type_def) entries, paths relative to the command's
+    // output prefix. DerivedFromSingleAttribute over an array attribute expands to one entry per element,
+    // keyed by the element's name_field value. A derived tabular result is stored at `<name>.data`, next
+    // to the element's own meta values.
+    pub(crate) fn result_fields(&self) -> Vec<(StorePath, &ResultKind, Option<&TypeDef<String>>)> {
+        let mut entries = Vec::new();
+        for result_spec in &self.expected_results {
+            match result_spec {
+                ResultSpec::Field { name, kind, ty, .. } => {
+                    entries.push((StorePath::from_segments([name]), kind, Some(ty)));
+                }
+                ResultSpec::DerivedFromSingleAttribute {
+                    name_field,
+                    kind,
+                    ty,
+                    attribute,
+                    ..
+                } => {
+                    let names = match self.attributes.get(attribute.as_str()) {
+                        Some(ScalarValue::Array(arr)) => arr
+                            .iter()
+                            .filter_map(|elem| {
+                                elem.as_object()
+                                    .and_then(|obj| obj.get(name_field.name()))
+                                    .and_then(|v| v.as_str())
+                                    .map(|s| s.to_string())
+                            })
+                            .collect(),
+                        Some(v) => vec![
+                            v.as_str()
+                                .map(|s| s.to_string())
+                                .unwrap_or_else(|| name_field.name().clone()),
+                        ],
+                        None => vec![name_field.name().clone()],
+                    };
+                    let tabular = matches!(ty, Some(TypeDef::Tabular));
+                    entries.extend(names.into_iter().map(|name| {
+                        let path = StorePath::from_segments([name]);
+                        let path = match tabular {
+                            true => path.with_segment("data"),
+                            false => path,
+                        };
+                        (path, kind, ty.as_ref())
+                    }));
+                }
+            }
+        }
+        entries
+    }
+
+    // The command's own 'on_error' policy, if it sets one
+    pub(crate) fn error_policy(&self) -> Result<Option<ErrorPolicy>> {
+        self.attributes
+            .get("on_error")
+            .and_then(|v| v.as_str())
+            .map(str::parse)
+            .transpose()
     }
 }
@@ -29,6 +29,12 @@ impl<T: Into<String>> ResultSpec<T> {
             ResultSpec::DerivedFromSingleAttribute { ty, .. } => ty.as_ref(),
         }
     }
+    pub fn kind(&self) -> &ResultKind {
+        match self {
+            ResultSpec::Field { kind, .. } => kind,
+            ResultSpec::DerivedFromSingleAttribute { kind, .. } => kind,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Hash, Eq)]
@@ -0,0 +1,219 @@
+use crate::imports::*;
+use crate::pipeline::{Completed, Ready};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::widgets::TableState;
+use std::pin::Pin;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use view::{ResultsBrowser, RunEvent, RunView};
+
+mod render;
+#[cfg(test)]
+mod tests;
+mod view;
+
+/*
+    Consts:
+    * TICK - How often the screen redraws when nothing else happens, keeps running durations moving
+*/
+const TICK: std::time::Duration = std::time::Duration::from_millis(100);
+
+/*
+    Types:
+    * Tui - Terminal UI that executes a Ready pipeline, shows it running and then browses its results
+    * TuiHooks - EventHooks forwarding namespace and command events to the UI over a channel
+    * App - What gets drawn: the run view, the results once collected and the current screen
+    * Screen - The run tree or the results browser
+*/
+pub struct Tui {
+    pipeline: Pipeline<Ready>,
+    result_settings: ResultSettings,
+}
+
+pub(crate) struct TuiHooks {
+    sender: UnboundedSender<RunEvent>,
+}
+
+pub(crate) struct App {
+    pub(crate) view: RunView,
+    pub(crate) browser: Option<ResultsBrowser>,
+    pub(crate) screen: Screen,
+    pub(crate) tree: TableState, // Selected row of the run tree
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Screen {
+    Run,
+    Results,
+}
+
+impl Tui {
+    pub fn new(pipeline: Pipeline<Ready>) -> Self {
+        Tui {
+            pipeline,
+            result_settings: ResultSettings::default(),
+        }
+    }
+
+    // Where and how results are written once the run completes, they're browsed from the context either way
+    pub fn with_result_settings(mut self, result_settings: ResultSettings) -> Self {
+        self.result_settings = result_settings;
+        self
+    }
+
+    // Takes over the terminal until the user quits. Returns the completed pipeline, or None when the user
+    // quit before the run finished. A failed run is shown in the UI, then returned as the error.
+    pub async fn run(self) -> Result<Option<Pipeline<Completed>>> {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let pipeline = self.pipeline.with_hook(TuiHooks { sender });
+        let mut app = App::new(RunView::new(&pipeline)?);
+        let result_settings = self.result_settings;
+        // Polled alongside the terminal rather than spawned, command specs aren't Send. Dropped (cancelling
+        // the run) if the user quits first.
+        let execution = std::pin::pin!(async move {
+            let completed = pipeline.execute().await?;
+            let results = completed.results(result_settings).await?;
+            Ok((completed, results))
+        });
+
+        let mut terminal = ratatui::init();
+        let outcome = app
+            .event_loop(&mut terminal, &mut receiver, execution)
+            .await;
+        ratatui::restore();
+        outcome
+    }
+}
+
+impl App {
+    pub(crate) fn new(view: RunView) -> Self {
+        App {
+            view,
+            browser: None,
+            screen: Screen::Run,
+            tree: TableState::default(),
+        }
+    }
+
+    async fn event_loop(
+        &mut self,
+        terminal: &mut ratatui::DefaultTerminal,
+        receiver: &mut UnboundedReceiver<RunEvent>,
+        mut execution: Pin<&mut impl Future<Output = Result<(Pipeline<Completed>, ResultStore)>>>,
+    ) -> Result<Option<Pipeline<Completed>>> {
+        use futures::StreamExt;
+
+        let mut keys = EventStream::new();
+        let mut tick = tokio::time::interval(TICK);
+        let mut completed = None;
+        let mut failure = None;
+        let mut running = true;
+        loop {
+            terminal.draw(|frame| render::draw(frame, self, Instant::now()))?;
+            tokio::select! {
+                Some(event) = receiver.recv() => self.view.apply(event),
+                outcome = execution.as_mut(), if running => {
+                    running = false;
+                    // Events sent just before the run ended may still be queued
+                    while let Ok(event) = receiver.try_recv() {
+                        self.view.apply(event);
+                    }
+                    match outcome {
+                        Ok((pipeline, results)) => {
+                            self.view.finish(None, Instant::now());
+                            self.browser = Some(ResultsBrowser::new(&results, pipeline.context()).await?);
+                            completed = Some(pipeline);
+                        }
+                        Err(e) => {
+                            self.view.finish(Some(format!("{:#}", e)), Instant::now());
+                            failure = Some(e);
+                        }
+                    }
+                }
+                Some(event) = keys.next() => {
+                    if let Event::Key(key) = event?
+                        && !self.handle_key(key)
+                    {
+                        break;
+                    }
+                }
+                _ = tick.tick() => {}
+            }
+        }
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(completed),
+        }
+    }
+
+    // Returns false once the user asked to quit
+    pub(crate) fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.kind != KeyEventKind::Press {
+            return true;
+        }
+        match (key.code, self.screen) {
+            (KeyCode::Char('q') | KeyCode::Esc, _) => return false,
+            (KeyCode::Char('c'), _) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return false;
+            }
+            (KeyCode::Tab, Screen::Run) if self.browser.is_some() => self.screen = Screen::Results,
+            (KeyCode::Tab, Screen::Results) => self.screen = Screen::Run,
+            (KeyCode::Up | KeyCode::Char('k'), Screen::Run) => self.tree.select_previous(),
+            (KeyCode::Down | KeyCode::Char('j'), Screen::Run) => self.tree.select_next(),
+            (code, Screen::Results) => {
+                if let Some(browser) = &mut self.browser {
+                    match code {
+                        KeyCode::Up | KeyCode::Char('k') => browser.select_previous(),
+                        KeyCode::Down | KeyCode::Char('j') => browser.select_next(),
+                        KeyCode::PageUp => browser.page_up(),
+                        KeyCode::PageDown | KeyCode::Char(' ') => browser.page_down(),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+}
+
+// The channel is unbounded so the pipeline never waits on the UI, send only fails once the UI is gone
+#[async_trait]
+impl EventHooks for TuiHooks {
+    async fn before_execute_namespace(&self, event: &hook_events::NamespaceInfo) -> Result<()> {
+        let _ = self.sender.send(RunEvent::NamespaceStarted {
+            namespace_index: event.namespace_index,
+            iteration_count: event.iteration_count,
+            at: Instant::now(),
+        });
+        Ok(())
+    }
+
+    async fn after_execute_namespace(&self, event: &hook_events::NamespaceExecuted) -> Result<()> {
+        let _ = self.sender.send(RunEvent::NamespaceFinished {
+            namespace_index: event.namespace_index,
+            at: event.executed_at,
+        });
+        Ok(())
+    }
+
+    async fn before_execute_command(&self, event: &hook_events::CommandInfo) -> Result<()> {
+        let _ = self.sender.send(RunEvent::CommandStarted {
+            namespace_index: event.namespace_index,
+            command_name: event.command_name.clone(),
+            iteration_index: event.iteration_index,
+            at: Instant::now(),
+        });
+        Ok(())
+    }
+
+    async fn after_execute_command(&self, event: &hook_events::CommandExecuted) -> Result<()> {
+        let _ = self.sender.send(RunEvent::CommandFinished {
+            namespace_index: event.namespace_index,
+            command_name: event.command_name.clone(),
+            iteration_index: event.iteration_index,
+            status: event.status.clone(),
+            at: event.executed_at,
+        });
+        Ok(())
+    }
+}
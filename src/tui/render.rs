@@ -0,0 +1,231 @@
+use super::view::{EntryValue, PAGE_ROWS, RunStatus, RunView, frame_page};
+use super::{App, Screen};
+use crate::imports::*;
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Row, Table, Wrap};
+use std::time::Duration;
+
+/*
+    Consts:
+    * MAX_COLUMN_WIDTH - Widest a frame preview column gets, longer cells are cut off
+*/
+const MAX_COLUMN_WIDTH: usize = 30;
+
+pub(crate) fn draw(frame: &mut Frame, app: &mut App, now: Instant) {
+    let [header, body, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    frame.render_widget(Paragraph::new(status_line(&app.view, now)), header);
+    match app.screen {
+        Screen::Run => draw_run(frame, app, body, now),
+        Screen::Results => draw_results(frame, app, body),
+    }
+
+    let mut keys = vec!["q quit", "↑/↓ move"];
+    if app.browser.is_some() {
+        keys.push("tab run/results");
+    }
+    if app.screen == Screen::Results {
+        keys.push("pgup/pgdn page");
+    }
+    frame.render_widget(
+        Paragraph::new(keys.join("  ")).style(Style::new().fg(Color::DarkGray)),
+        footer,
+    );
+}
+
+// e.g. "panopticon  running  3/7 commands  1.2s"
+fn status_line(view: &RunView, now: Instant) -> Line<'static> {
+    let (finished, total) = view.progress();
+    let (state, style) = match (&view.error, view.is_finished()) {
+        (Some(error), _) => (
+            format!("failed: {}", error),
+            status_style(RunStatus::Failed),
+        ),
+        (None, true) => ("completed".to_string(), status_style(RunStatus::Succeeded)),
+        (None, false) => ("running".to_string(), status_style(RunStatus::Running)),
+    };
+    Line::from(vec![
+        Span::styled("panopticon  ", Style::new().add_modifier(Modifier::BOLD)),
+        Span::styled(state, style),
+        Span::raw(format!(
+            "  {}/{} commands  {}",
+            finished,
+            total,
+            format_duration(view.elapsed(now))
+        )),
+    ])
+}
+
+fn draw_run(frame: &mut Frame, app: &mut App, area: Rect, now: Instant) {
+    let mut rows = Vec::new();
+    for namespace in &app.view.namespaces {
+        let status = match (namespace.status(), namespace.iterations()) {
+            (RunStatus::Pending, _) => "pending".to_string(),
+            (_, Some((finished, count))) => format!("{}/{} iterations", finished, count),
+            (RunStatus::Running, None) => "running".to_string(),
+            (_, None) => "done".to_string(),
+        };
+        rows.push(
+            Row::new(vec![
+                namespace.name.clone(),
+                namespace.mode.clone(),
+                status,
+                namespace
+                    .elapsed(now)
+                    .map(format_duration)
+                    .unwrap_or_default(),
+            ])
+            .style(status_style(namespace.status()).add_modifier(Modifier::BOLD)),
+        );
+        for command in &namespace.commands {
+            rows.push(
+                Row::new(vec![
+                    format!("  {} {}", status_symbol(command.status()), command.name),
+                    command.command_type.clone(),
+                    command.status_text(),
+                    match command.status() {
+                        RunStatus::Pending => String::new(),
+                        _ => format_duration(command.elapsed(now)),
+                    },
+                ])
+                .style(status_style(command.status())),
+            );
+        }
+    }
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Percentage(35),
+            Constraint::Percentage(20),
+            Constraint::Percentage(30),
+            Constraint::Percentage(15),
+        ],
+    )
+    .header(
+        Row::new(vec!["NAME", "TYPE", "STATUS", "DURATION"])
+            .style(Style::new().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::bordered().title(" Pipeline "))
+    .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, area, &mut app.tree);
+}
+
+fn draw_results(frame: &mut Frame, app: &mut App, area: Rect) {
+    let Some(browser) = &app.browser else {
+        return;
+    };
+    let [list_area, detail_area] =
+        Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(area);
+
+    let items: Vec<ListItem> = browser
+        .entries
+        .iter()
+        .map(|entry| {
+            let summary = match &entry.value {
+                EntryValue::Frame(frame) => format!(" ({}x{})", frame.height(), frame.width()),
+                EntryValue::Scalar(_) => String::new(),
+            };
+            let style = match entry.kind {
+                ResultKind::Data => Style::new(),
+                ResultKind::Meta => Style::new().fg(Color::DarkGray),
+            };
+            ListItem::new(format!("{}{}", entry.path.to_dotted(), summary)).style(style)
+        })
+        .collect();
+    let list = List::new(items)
+        .block(Block::bordered().title(" Results "))
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(browser.selected));
+    frame.render_stateful_widget(list, list_area, &mut state);
+
+    let Some(entry) = browser.selected() else {
+        frame.render_widget(
+            Paragraph::new("No results were collected").block(Block::bordered()),
+            detail_area,
+        );
+        return;
+    };
+    match &entry.value {
+        EntryValue::Scalar(value) => {
+            let text = serde_json::to_string_pretty(value).unwrap_or_else(|e| e.to_string());
+            frame.render_widget(
+                Paragraph::new(text)
+                    .wrap(Wrap { trim: false })
+                    .block(Block::bordered().title(format!(" {} ", entry.path.to_dotted()))),
+                detail_area,
+            );
+        }
+        EntryValue::Frame(tabular) => {
+            let title = format!(
+                " {}  rows {}-{} of {} ",
+                entry.path.to_dotted(),
+                (browser.offset + 1).min(tabular.height()),
+                (browser.offset + PAGE_ROWS).min(tabular.height()),
+                tabular.height()
+            );
+            let block = Block::bordered().title(title);
+            match frame_page(tabular, browser.offset, PAGE_ROWS) {
+                Ok((headers, cells)) => {
+                    let widths: Vec<Constraint> = headers
+                        .iter()
+                        .enumerate()
+                        .map(|(column, header)| {
+                            let widest = cells
+                                .iter()
+                                .map(|row| row[column].chars().count())
+                                .chain([header.chars().count()])
+                                .max()
+                                .unwrap_or(0);
+                            Constraint::Length(widest.min(MAX_COLUMN_WIDTH) as u16)
+                        })
+                        .collect();
+                    let table = Table::new(cells.into_iter().map(Row::new), widths)
+                        .header(Row::new(headers).style(Style::new().add_modifier(Modifier::BOLD)))
+                        .block(block);
+                    frame.render_widget(table, detail_area);
+                }
+                Err(e) => frame.render_widget(
+                    Paragraph::new(format!("Can't display frame: {:#}", e)).block(block),
+                    detail_area,
+                ),
+            }
+        }
+    }
+}
+
+fn status_style(status: RunStatus) -> Style {
+    match status {
+        RunStatus::Pending => Style::new().fg(Color::DarkGray),
+        RunStatus::Running => Style::new().fg(Color::Yellow),
+        RunStatus::Succeeded => Style::new().fg(Color::Green),
+        RunStatus::Skipped => Style::new().fg(Color::Blue),
+        RunStatus::Failed => Style::new().fg(Color::Red),
+    }
+}
+
+fn status_symbol(status: RunStatus) -> &'static str {
+    match status {
+        RunStatus::Pending => "·",
+        RunStatus::Running => "▶",
+        RunStatus::Succeeded => "✓",
+        RunStatus::Skipped => "↷",
+        RunStatus::Failed => "✗",
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{}ms", duration.as_millis())
+    } else {
+        format!("{:.1}s", duration.as_secs_f64())
+    }
+}
@@ -0,0 +1,234 @@
+use super::view::{EntryValue, RunStatus, frame_page};
+use super::*;
+use crate::test_utils::init_tracing;
+use crossterm::event::KeyEvent;
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+
+// 45 rows, so a frame preview has two full pages and a partial one
+fn numbers_csv(dir: &std::path::Path) -> PathBuf {
+    let path = dir.join("numbers.csv");
+    let rows: Vec<String> = (0..45).map(|n| format!("{},\"row {}\"", n, n)).collect();
+    std::fs::write(&path, format!("n,label\n{}\n", rows.join("\n"))).unwrap();
+    path
+}
+
+async fn watched_pipeline(csv: &std::path::Path) -> Pipeline<Ready> {
+    let yaml = format!(
+        r#"
+namespaces:
+  - name: config
+    mode: static
+    values:
+      regions: [eu, us]
+      threshold: 10
+  - name: data
+    commands:
+      - name: load
+        type: FileCommand
+        attrs:
+          files:
+            - name: numbers
+              file: "{}"
+              format: csv
+      - name: never
+        type: ConditionCommand
+        attrs:
+          when: config.threshold > 100
+          branches:
+            - name: big
+              if: "true"
+              then: big
+  - name: per_region
+    mode: iterative
+    store_path: config.regions
+    iter_var: region
+    iterator:
+      type: scalar_array
+    commands:
+      - name: check
+        type: ConditionCommand
+        attrs:
+          branches:
+            - name: is_eu
+              if: region == "eu"
+              then: local
+          default: remote
+      - name: label
+        type: ConditionCommand
+        attrs:
+          branches:
+            - name: any
+              if: "true"
+              then: "{{{{ region }}}}"
+"#,
+        csv.display()
+    );
+    let document = PipelineDocument::parse(&yaml, DocumentFormat::Yaml).unwrap();
+    Pipeline::from_document(&document)
+        .await
+        .unwrap()
+        .compile()
+        .await
+        .unwrap()
+}
+
+fn press(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+fn screen_text(terminal: &Terminal<TestBackend>) -> String {
+    let buffer = terminal.backend().buffer();
+    buffer
+        .content()
+        .chunks(buffer.area.width as usize)
+        .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[tokio::test]
+async fn test_tui_hooks_drive_the_run_view() {
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+    let csv = numbers_csv(dir.path());
+
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let pipeline = watched_pipeline(&csv).await.with_hook(TuiHooks { sender });
+    let mut view = RunView::new(&pipeline).unwrap();
+    assert_eq!(view.progress(), (0, 4));
+
+    pipeline.execute().await.unwrap();
+    while let Ok(event) = receiver.try_recv() {
+        view.apply(event);
+    }
+    view.finish(None, Instant::now());
+
+    let namespace = |name: &str| {
+        view.namespaces
+            .iter()
+            .find(|namespace| namespace.name == name)
+            .unwrap()
+    };
+    let per_region = namespace("per_region");
+    assert_eq!(per_region.mode, "iterative");
+    assert_eq!(per_region.iterations(), Some((2, 2)));
+    assert_eq!(per_region.status(), RunStatus::Succeeded);
+    for command in &per_region.commands {
+        assert_eq!(command.status(), RunStatus::Succeeded);
+        assert_eq!(command.runs_finished(), 2);
+        assert_eq!(command.status_text(), "success x2");
+    }
+
+    let data = namespace("data");
+    assert_eq!(data.iterations(), None);
+    assert_eq!(data.commands[0].status_text(), "success");
+    // 'when' false, the command still runs and records that it skipped
+    assert_eq!(data.commands[1].status(), RunStatus::Skipped);
+    assert_eq!(view.progress(), (4, 4));
+    assert!(view.error.is_none());
+}
+
+#[tokio::test]
+async fn test_run_view_fails_runs_in_flight_when_the_pipeline_stops() {
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+    let pipeline = watched_pipeline(&numbers_csv(dir.path())).await;
+    let mut view = RunView::new(&pipeline).unwrap();
+    let data_index = view
+        .namespaces
+        .iter()
+        .find(|namespace| namespace.name == "data")
+        .unwrap()
+        .namespace_index;
+
+    let at = Instant::now();
+    view.apply(RunEvent::NamespaceStarted {
+        namespace_index: data_index,
+        iteration_count: None,
+        at,
+    });
+    view.apply(RunEvent::CommandStarted {
+        namespace_index: data_index,
+        command_name: "load".to_string(),
+        iteration_index: None,
+        at,
+    });
+    let data = view.namespaces.iter().find(|ns| ns.name == "data").unwrap();
+    assert_eq!(data.status(), RunStatus::Running);
+    assert_eq!(data.commands[0].status_text(), "running");
+
+    view.finish(Some("deadline passed".to_string()), Instant::now());
+    let data = view.namespaces.iter().find(|ns| ns.name == "data").unwrap();
+    assert_eq!(data.commands[0].status(), RunStatus::Failed);
+    assert_eq!(data.commands[1].status(), RunStatus::Pending);
+    assert_eq!(data.status(), RunStatus::Failed);
+    assert_eq!(view.error.as_deref(), Some("deadline passed"));
+}
+
+#[tokio::test]
+async fn test_results_browser_pages_frames_and_renders() {
+    init_tracing();
+    let dir = tempfile::tempdir().unwrap();
+    let csv = numbers_csv(dir.path());
+
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let pipeline = watched_pipeline(&csv).await.with_hook(TuiHooks { sender });
+    let mut app = App::new(RunView::new(&pipeline).unwrap());
+    let completed = pipeline.execute().await.unwrap();
+    while let Ok(event) = receiver.try_recv() {
+        app.view.apply(event);
+    }
+    let results = completed
+        .results(ResultSettings::new().with_output_path(dir.path().join("out")))
+        .await
+        .unwrap();
+    app.view.finish(None, Instant::now());
+    app.browser = Some(
+        ResultsBrowser::new(&results, completed.context())
+            .await
+            .unwrap(),
+    );
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal
+        .draw(|frame| render::draw(frame, &mut app, Instant::now()))
+        .unwrap();
+    let run_screen = screen_text(&terminal);
+    assert!(run_screen.contains("completed"));
+    assert!(run_screen.contains("2/2 iterations"));
+    assert!(run_screen.contains("success x2"));
+
+    assert!(app.handle_key(press(KeyCode::Tab)));
+    assert_eq!(app.screen, Screen::Results);
+    let browser = app.browser.as_ref().unwrap();
+    let frame_entry = browser
+        .entries
+        .iter()
+        .position(|entry| entry.path.to_dotted() == "data.load.numbers.data")
+        .unwrap();
+    for _ in 0..frame_entry {
+        app.handle_key(press(KeyCode::Down));
+    }
+    // Paging stops on the last page
+    for _ in 0..5 {
+        app.handle_key(press(KeyCode::PageDown));
+    }
+    assert_eq!(app.browser.as_ref().unwrap().offset, 40);
+
+    terminal
+        .draw(|frame| render::draw(frame, &mut app, Instant::now()))
+        .unwrap();
+    let results_screen = screen_text(&terminal);
+    assert!(results_screen.contains("rows 41-45 of 45"));
+    assert!(results_screen.contains("row 44"));
+
+    let EntryValue::Frame(frame) = &app.browser.as_ref().unwrap().entries[frame_entry].value else {
+        panic!("expected a frame entry");
+    };
+    let (headers, cells) = frame_page(frame, 43, 5).unwrap();
+    assert_eq!(headers, ["n", "label"]);
+    assert_eq!(cells, [["43", "row 43"], ["44", "row 44"]]);
+
+    assert!(!app.handle_key(press(KeyCode::Char('q'))));
+}
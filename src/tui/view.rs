@@ -0,0 +1,470 @@
+use crate::imports::*;
+use crate::pipeline::Ready;
+use crate::pipeline::traits::{
+    EXECUTION_STATUS_ERROR, EXECUTION_STATUS_SKIPPED, EXECUTION_STATUS_SUCCESS,
+};
+use polars::prelude::AnyValue;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/*
+    Consts:
+    * PAGE_ROWS - Rows a PageUp / PageDown moves a frame preview by
+*/
+pub(crate) const PAGE_ROWS: usize = 20;
+
+/*
+    Types:
+    * RunEvent - A hook call forwarded by TuiHooks, stamped with when it was made
+    * RunView - The ExecutionPlan's namespace/command tree with live status, durations and iteration progress
+    * NamespaceRow - A namespace of the tree, its mode and iteration progress
+    * CommandRow - A command of the tree, the statuses its runs recorded and the time spent running
+    * RunStatus - Summary status of a command across its runs (or of a namespace across its commands)
+*/
+#[derive(Debug, Clone)]
+pub(crate) enum RunEvent {
+    NamespaceStarted {
+        namespace_index: usize,
+        iteration_count: Option<usize>,
+        at: Instant,
+    },
+    NamespaceFinished {
+        namespace_index: usize,
+        at: Instant,
+    },
+    CommandStarted {
+        namespace_index: usize,
+        command_name: String,
+        iteration_index: Option<usize>,
+        at: Instant,
+    },
+    CommandFinished {
+        namespace_index: usize,
+        command_name: String,
+        iteration_index: Option<usize>,
+        status: Option<String>,
+        at: Instant,
+    },
+}
+
+pub(crate) struct RunView {
+    pub(crate) namespaces: Vec<NamespaceRow>, // Execution order
+    pub(crate) started_at: Instant,
+    pub(crate) finished_at: Option<Instant>,
+    pub(crate) error: Option<String>, // Why the pipeline stopped, when it failed
+}
+
+pub(crate) struct NamespaceRow {
+    pub(crate) namespace_index: usize,
+    pub(crate) name: String,
+    pub(crate) mode: String,
+    pub(crate) iteration_count: Option<usize>, // Known once the namespace starts
+    iterations_finished: usize,
+    finished_per_iteration: HashMap<usize, usize>, // Iteration index -> commands finished in it
+    started_at: Option<Instant>,
+    finished_at: Option<Instant>,
+    pub(crate) commands: Vec<CommandRow>,
+}
+
+pub(crate) struct CommandRow {
+    pub(crate) name: String,
+    pub(crate) command_type: String,
+    statuses: BTreeMap<String, usize>, // Recorded status -> number of runs that recorded it
+    in_flight: HashMap<Option<usize>, Instant>, // Iteration index -> start of the run
+    elapsed: Duration,                 // Summed over finished runs
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RunStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Skipped,
+    Failed,
+}
+
+impl RunView {
+    pub(crate) fn new(pipeline: &Pipeline<Ready>) -> Result<Self> {
+        let namespaces = pipeline
+            .explain()?
+            .namespaces
+            .into_iter()
+            .map(|namespace| {
+                let namespace_index = pipeline
+                    .namespaces
+                    .iter()
+                    .position(|ns| ns.name() == namespace.name)
+                    .with_context(|| format!("Unknown namespace '{}' in plan", namespace.name))?;
+                Ok(NamespaceRow {
+                    namespace_index,
+                    name: namespace.name,
+                    mode: namespace.mode,
+                    iteration_count: None,
+                    iterations_finished: 0,
+                    finished_per_iteration: HashMap::new(),
+                    started_at: None,
+                    finished_at: None,
+                    commands: namespace
+                        .commands
+                        .into_iter()
+                        .map(|command| CommandRow {
+                            name: command.name,
+                            command_type: command.command_type,
+                            statuses: BTreeMap::new(),
+                            in_flight: HashMap::new(),
+                            elapsed: Duration::ZERO,
+                        })
+                        .collect(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(RunView {
+            namespaces,
+            started_at: Instant::now(),
+            finished_at: None,
+            error: None,
+        })
+    }
+
+    pub(crate) fn apply(&mut self, event: RunEvent) {
+        match event {
+            RunEvent::NamespaceStarted {
+                namespace_index,
+                iteration_count,
+                at,
+            } => {
+                if let Some(namespace) = self.namespace_mut(namespace_index) {
+                    namespace.started_at = Some(at);
+                    namespace.iteration_count = iteration_count;
+                }
+            }
+            RunEvent::NamespaceFinished {
+                namespace_index,
+                at,
+            } => {
+                if let Some(namespace) = self.namespace_mut(namespace_index) {
+                    namespace.finished_at = Some(at);
+                }
+            }
+            RunEvent::CommandStarted {
+                namespace_index,
+                command_name,
+                iteration_index,
+                at,
+            } => {
+                if let Some(command) = self
+                    .namespace_mut(namespace_index)
+                    .and_then(|namespace| namespace.command_mut(&command_name))
+                {
+                    command.in_flight.insert(iteration_index, at);
+                }
+            }
+            RunEvent::CommandFinished {
+                namespace_index,
+                command_name,
+                iteration_index,
+                status,
+                at,
+            } => {
+                let Some(namespace) = self.namespace_mut(namespace_index) else {
+                    return;
+                };
+                let command_count = namespace.commands.len();
+                let Some(command) = namespace.command_mut(&command_name) else {
+                    return;
+                };
+                command.finish_run(iteration_index, status, at);
+                // An iteration is done once every command of the namespace has finished in it
+                if let Some(index) = iteration_index {
+                    let finished = namespace.finished_per_iteration.entry(index).or_default();
+                    *finished += 1;
+                    if *finished == command_count {
+                        namespace.iterations_finished += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    // Runs still in flight when the pipeline stops are counted as errors
+    pub(crate) fn finish(&mut self, error: Option<String>, at: Instant) {
+        for namespace in &mut self.namespaces {
+            if namespace.started_at.is_some() {
+                namespace.finished_at.get_or_insert(at);
+            }
+            for command in &mut namespace.commands {
+                let running: Vec<Option<usize>> = command.in_flight.keys().copied().collect();
+                for iteration_index in running {
+                    command.finish_run(
+                        iteration_index,
+                        Some(EXECUTION_STATUS_ERROR.to_string()),
+                        at,
+                    );
+                }
+            }
+        }
+        self.finished_at = Some(at);
+        self.error = error;
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished_at.is_some()
+    }
+
+    pub(crate) fn elapsed(&self, now: Instant) -> Duration {
+        self.finished_at.unwrap_or(now) - self.started_at
+    }
+
+    // (finished, total) commands, a command counts as finished once all its runs are
+    pub(crate) fn progress(&self) -> (usize, usize) {
+        let commands = self.namespaces.iter().flat_map(|ns| &ns.commands);
+        let finished = commands
+            .clone()
+            .filter(|command| !matches!(command.status(), RunStatus::Pending | RunStatus::Running))
+            .count();
+        (finished, commands.count())
+    }
+
+    fn namespace_mut(&mut self, namespace_index: usize) -> Option<&mut NamespaceRow> {
+        self.namespaces
+            .iter_mut()
+            .find(|namespace| namespace.namespace_index == namespace_index)
+    }
+}
+
+impl NamespaceRow {
+    // (finished, total) iterations of an iterative namespace that has started
+    pub(crate) fn iterations(&self) -> Option<(usize, usize)> {
+        self.iteration_count
+            .map(|count| (self.iterations_finished, count))
+    }
+
+    pub(crate) fn status(&self) -> RunStatus {
+        match (self.started_at, self.finished_at) {
+            (None, _) => RunStatus::Pending,
+            (Some(_), None) => RunStatus::Running,
+            (Some(_), Some(_)) => {
+                if self
+                    .commands
+                    .iter()
+                    .any(|command| command.status() == RunStatus::Failed)
+                {
+                    RunStatus::Failed
+                } else {
+                    RunStatus::Succeeded
+                }
+            }
+        }
+    }
+
+    pub(crate) fn elapsed(&self, now: Instant) -> Option<Duration> {
+        self.started_at
+            .map(|started| self.finished_at.unwrap_or(now) - started)
+    }
+
+    fn command_mut(&mut self, name: &str) -> Option<&mut CommandRow> {
+        self.commands
+            .iter_mut()
+            .find(|command| command.name == name)
+    }
+}
+
+impl CommandRow {
+    pub(crate) fn status(&self) -> RunStatus {
+        if !self.in_flight.is_empty() {
+            RunStatus::Running
+        } else if self.statuses.is_empty() {
+            RunStatus::Pending
+        } else if self
+            .statuses
+            .keys()
+            .any(|status| status != EXECUTION_STATUS_SUCCESS && status != EXECUTION_STATUS_SKIPPED)
+        {
+            RunStatus::Failed
+        } else if self.statuses.contains_key(EXECUTION_STATUS_SUCCESS) {
+            RunStatus::Succeeded
+        } else {
+            RunStatus::Skipped
+        }
+    }
+
+    // e.g. "success", "success x8, skipped x2" or "running (3 done)"
+    pub(crate) fn status_text(&self) -> String {
+        let recorded = || {
+            self.statuses
+                .iter()
+                .map(|(status, &count)| match count {
+                    1 if self.statuses.len() == 1 => status.clone(),
+                    _ => format!("{} x{}", status, count),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self.status() {
+            RunStatus::Pending => "pending".to_string(),
+            RunStatus::Running => match self.runs_finished() {
+                0 => "running".to_string(),
+                done => format!("running ({} done)", done),
+            },
+            _ => recorded(),
+        }
+    }
+
+    pub(crate) fn runs_finished(&self) -> usize {
+        self.statuses.values().sum()
+    }
+
+    // Time spent running, summed over runs, so parallel iterations can add up to more than wall time
+    pub(crate) fn elapsed(&self, now: Instant) -> Duration {
+        self.in_flight
+            .values()
+            .fold(self.elapsed, |total, started| total + (now - *started))
+    }
+
+    fn finish_run(&mut self, iteration_index: Option<usize>, status: Option<String>, at: Instant) {
+        if let Some(started) = self.in_flight.remove(&iteration_index) {
+            self.elapsed += at.saturating_duration_since(started);
+        }
+        *self
+            .statuses
+            .entry(status.unwrap_or_else(|| "unknown".to_string()))
+            .or_default() += 1;
+    }
+}
+
+/*
+    Types:
+    * ResultsBrowser - ResultStore entries, the selected entry and the first row of the frame page shown
+    * ResultEntry - A data or meta result, frames are taken from the context so they can be paged
+    * EntryValue - Scalar value or DataFrame of a result entry
+*/
+pub(crate) struct ResultsBrowser {
+    pub(crate) entries: Vec<ResultEntry>,
+    pub(crate) selected: usize,
+    pub(crate) offset: usize,
+}
+
+pub(crate) struct ResultEntry {
+    pub(crate) path: StorePath,
+    pub(crate) kind: ResultKind,
+    pub(crate) value: EntryValue,
+}
+
+pub(crate) enum EntryValue {
+    Scalar(ScalarValue),
+    Frame(TabularValue),
+}
+
+impl ResultsBrowser {
+    // Data results before meta results for each command, both sorted by path. Frames a command stored beyond
+    // its declared results (e.g. FileCommand's '<name>.data') are listed with its data.
+    pub(crate) async fn new(results: &ResultStore, context: &ExecutionContext) -> Result<Self> {
+        let frame_keys = context.tabular().keys().await;
+        let mut entries = Vec::new();
+        for result in results.iter() {
+            let mut data: Vec<(StorePath, EntryValue)> = Vec::new();
+            for (path, value) in result.data_iter() {
+                let value = match value {
+                    ResultValue::Scalar { value, .. } => EntryValue::Scalar(value.clone()),
+                    // The written file could be in any format, the context still holds the frame itself
+                    ResultValue::Tabular { .. } => match context.tabular().get(path).await? {
+                        Some(frame) => EntryValue::Frame(frame),
+                        None => continue,
+                    },
+                };
+                data.push((path.clone(), value));
+            }
+            let prefix = format!("{}.", result.source().to_dotted());
+            for key in frame_keys.iter().filter(|key| key.starts_with(&prefix)) {
+                let path = StorePath::from_dotted(key);
+                if result.data_get(&path).is_none()
+                    && let Some(frame) = context.tabular().get(&path).await?
+                {
+                    data.push((path, EntryValue::Frame(frame)));
+                }
+            }
+            data.sort_by_key(|(path, _)| path.to_dotted());
+            entries.extend(data.into_iter().map(|(path, value)| ResultEntry {
+                path,
+                kind: ResultKind::Data,
+                value,
+            }));
+
+            let mut meta: Vec<(&StorePath, &ScalarValue)> = result.meta_iter().collect();
+            meta.sort_by_key(|(path, _)| path.to_dotted());
+            entries.extend(meta.into_iter().map(|(path, value)| ResultEntry {
+                path: path.clone(),
+                kind: ResultKind::Meta,
+                value: EntryValue::Scalar(value.clone()),
+            }));
+        }
+        Ok(ResultsBrowser {
+            entries,
+            selected: 0,
+            offset: 0,
+        })
+    }
+
+    pub(crate) fn selected(&self) -> Option<&ResultEntry> {
+        self.entries.get(self.selected)
+    }
+
+    pub(crate) fn select_next(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+            self.offset = 0;
+        }
+    }
+
+    pub(crate) fn select_previous(&mut self) {
+        if self.selected > 0 {
+            self.selected -= 1;
+            self.offset = 0;
+        }
+    }
+
+    // Stops on the last page rather than paging past the end of the frame
+    pub(crate) fn page_down(&mut self) {
+        if let Some(EntryValue::Frame(frame)) = self.selected().map(|entry| &entry.value) {
+            let last_page = frame.height().saturating_sub(1) / PAGE_ROWS * PAGE_ROWS;
+            self.offset = (self.offset + PAGE_ROWS).min(last_page);
+        }
+    }
+
+    pub(crate) fn page_up(&mut self) {
+        self.offset = self.offset.saturating_sub(PAGE_ROWS);
+    }
+}
+
+// Column names and `rows` rows of the frame from `offset` on, as display strings
+pub(crate) fn frame_page(
+    frame: &TabularValue,
+    offset: usize,
+    rows: usize,
+) -> Result<(Vec<String>, Vec<Vec<String>>)> {
+    let page = frame.slice(offset as i64, rows);
+    let headers = page
+        .get_column_names()
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    let mut cells = Vec::with_capacity(page.height());
+    for row in 0..page.height() {
+        cells.push(
+            page.get_columns()
+                .iter()
+                .map(|column| column.get(row).map(|value| display_cell(&value)))
+                .collect::<std::result::Result<Vec<_>, _>>()?,
+        );
+    }
+    Ok((headers, cells))
+}
+
+// Strings without the quotes AnyValue's Display adds
+fn display_cell(value: &AnyValue) -> String {
+    match value {
+        AnyValue::String(s) => s.to_string(),
+        AnyValue::StringOwned(s) => s.to_string(),
+        other => other.to_string(),
+    }
+}
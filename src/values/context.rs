@@ -35,6 +35,14 @@ impl ExecutionContext {
         }
     }
 
+    // Clone of this context where `vars` are visible to reads and substitution, without touching the shared store
+    pub fn scoped(&self, vars: HashMap<String, ScalarValue>) -> Self {
+        ExecutionContext {
+            scalar_store: self.scalar_store.scoped(vars),
+            ..self.clone()
+        }
+    }
+
     pub fn scalar(&self) -> &ScalarStore {
         &self.scalar_store
     }
@@ -47,6 +55,22 @@ impl ExecutionContext {
         self.scalar_store.render_template(template.into()).await
     }
 
+    // Replaces the values of the pipeline's secrets, for anything rendered that's about to be logged or shown
+    pub fn redact(&self, text: &str) -> String {
+        self.scalar_store.redact(text)
+    }
+
+    // The error as is when it holds no secret value, otherwise a redacted copy of its message chain
+    pub(crate) fn redact_error(&self, error: anyhow::Error) -> anyhow::Error {
+        let message = format!("{:#}", error);
+        let redacted = self.redact(&message);
+        if redacted == message {
+            error
+        } else {
+            anyhow::anyhow!(redacted)
+        }
+    }
+
     pub fn extensions(&self) -> &Extensions {
         &self.extensions
     }
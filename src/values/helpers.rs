@@ -89,6 +89,18 @@ impl<'a> InsertBatch<'a> {
             .insert(&self.prefix.with_segment(segment), tabular)
             .await
     }
+
+    pub async fn lazy(
+        &self,
+        segment: &str,
+        lazy: polars::prelude::LazyFrame,
+        sources: LazySources,
+    ) -> Result<()> {
+        self.context
+            .tabular()
+            .insert_lazy(&self.prefix.with_segment(segment), lazy, sources)
+            .await
+    }
 }
 
 /*
@@ -139,6 +151,20 @@ pub(crate) fn insert_at_path(
     Ok(())
 }
 
+// Removes the value at path (below the namespace key), returning it if it existed
+pub(in crate::values) fn remove_at_path(
+    root: &mut ScalarValue,
+    path: &StorePath,
+) -> Option<ScalarValue> {
+    let segments = &path.segments();
+    let (last, parents) = segments.get(1..)?.split_last()?;
+    let mut current = root;
+    for segment in parents {
+        current = current.as_object_mut()?.get_mut(segment)?;
+    }
+    current.as_object_mut()?.remove(last)
+}
+
 pub(in crate::values) fn get_at_path<'a>(
     root: &'a ScalarValue,
     path: &StorePath,
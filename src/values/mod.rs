@@ -27,6 +27,12 @@ pub mod store_path {
             new_path.segments.push(segment.into());
             new_path
         }
+        // `other`'s segments appended, e.g. a command's output prefix and a result path relative to it
+        pub fn join(&self, other: &StorePath) -> Self {
+            let mut new_path = self.clone();
+            new_path.segments.extend(other.segments.iter().cloned());
+            new_path
+        }
         pub fn with_index(&self, index: usize) -> Self {
             self.with_segment(index.to_string())
         }
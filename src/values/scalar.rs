@@ -1,4 +1,5 @@
 use crate::imports::*;
+use crate::secrets::Redactor;
 
 /*
     Types:
@@ -9,7 +10,8 @@ use crate::imports::*;
 */
 pub type ScalarValue = tera::Value;
 
-#[derive(Debug, Clone, PartialEq, Default, Hash, Eq)]
+#[derive(Debug, Clone, PartialEq, Default, Hash, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ScalarType {
     #[default]
     Null,
@@ -49,21 +51,44 @@ impl ObjectBuilder {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct ScalarStore {
-    tera: Arc<RwLock<tera::Tera>>,
     store: Arc<RwLock<tera::Context>>,
+    scope: Option<Arc<HashMap<String, ScalarValue>>>, // Read-only overlay only visible through this handle (e.g. iteration variables)
+    secrets: Arc<std::sync::RwLock<Redactor>>, // Values inserted with insert_secret, sync so Debug can redact
 }
 
 impl Default for ScalarStore {
     fn default() -> Self {
         ScalarStore {
-            tera: Arc::new(RwLock::new(tera::Tera::default())),
             store: Arc::new(RwLock::new(tera::Context::new())),
+            scope: None,
+            secrets: Arc::default(),
         }
     }
 }
 
+// Secret values are redacted, and a store locked for writing shows as such rather than waiting
+impl std::fmt::Debug for ScalarStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("ScalarStore");
+        match self.store.try_read() {
+            Ok(ctx) => debug.field("store", &self.redact_value(&ctx.clone().into_json())),
+            Err(_) => debug.field("store", &"<locked>"),
+        };
+        debug
+            .field(
+                "scope",
+                &self.scope.as_ref().map(|scope| {
+                    self.redact_value(&ScalarValue::Object(
+                        scope.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+                    ))
+                }),
+            )
+            .finish()
+    }
+}
+
 impl ScalarStore {
     pub fn with_inputs(inputs: &HashMap<String, ScalarValue>) -> Self {
         let mut ctx = tera::Context::new();
@@ -71,13 +96,28 @@ impl ScalarStore {
             ctx.insert(key, value);
         }
         Self {
-            tera: Arc::new(RwLock::new(tera::Tera::default())),
             store: Arc::new(RwLock::new(ctx)),
+            scope: None,
+            secrets: Arc::default(),
         }
     }
     pub fn new() -> Self {
         Self::default()
     }
+    // Returns a handle sharing this store with `vars` layered on top. Scoped values shadow store values
+    // for reads and template rendering, writes still go to the shared store.
+    pub fn scoped(&self, vars: HashMap<String, ScalarValue>) -> Self {
+        let mut scope = match &self.scope {
+            Some(existing) => existing.as_ref().clone(),
+            None => HashMap::new(),
+        };
+        scope.extend(vars);
+        Self {
+            store: self.store.clone(),
+            scope: Some(Arc::new(scope)),
+            secrets: self.secrets.clone(),
+        }
+    }
     pub async fn insert<'a>(&'a self, key: &'a StorePath, value: ScalarValue) -> Result<()> {
         let store_key = key.namespace().context("StorePath has no namespace")?;
         let mut ctx = self.store.write().await;
@@ -90,6 +130,35 @@ impl ScalarStore {
         Ok(())
     }
 
+    // Inserts a secret's value for templates to use. Anything passing through `redact` has the value replaced.
+    pub async fn insert_secret(&self, key: &StorePath, secret: &Secret) -> Result<()> {
+        self.secrets
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .add(secret);
+        self.insert(key, ScalarValue::String(secret.expose().to_string()))
+            .await
+    }
+
+    // Replaces the values of secrets in this store, use on anything rendered before logging or surfacing it
+    pub fn redact(&self, text: &str) -> String {
+        self.secrets
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .redact(text)
+    }
+
+    pub(crate) fn redact_value(&self, value: &ScalarValue) -> ScalarValue {
+        let secrets = self
+            .secrets
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if secrets.is_empty() {
+            return value.clone();
+        }
+        secrets.redact_value(value)
+    }
+
     pub async fn insert_raw(&self, key: &str, value: ScalarValue) -> Result<()> {
         let mut ctx = self.store.write().await;
         ctx.insert(key, &value);
@@ -100,8 +169,13 @@ impl ScalarStore {
             .namespace()
             .context("StorePath has no namespace")
             .map(|k| k.to_string());
-        let ctx = self.store.read().await;
         let store_key = store_key?;
+        if let Some(scope) = &self.scope
+            && let Some(root_value) = scope.get(&store_key)
+        {
+            return Ok(super::helpers::get_at_path(root_value, key).cloned());
+        }
+        let ctx = self.store.read().await;
         if let Some(root_value) = ctx.get(&store_key) {
             if let Some(value) = super::helpers::get_at_path(root_value, key) {
                 Ok(Some(value.clone()))
@@ -124,20 +198,54 @@ impl ScalarStore {
         }
     }
 
+    // Removes a single nested value, leaving the rest of the namespace in place
+    pub(crate) async fn remove_at(&self, key: &StorePath) -> Result<Option<ScalarValue>> {
+        let store_key = key.namespace().context("StorePath has no namespace")?;
+        let mut ctx = self.store.write().await;
+        let Some(mut root_value) = ctx.remove(store_key) else {
+            return Ok(None);
+        };
+        let removed = super::helpers::remove_at_path(&mut root_value, key);
+        ctx.insert(store_key, &root_value);
+        Ok(removed)
+    }
+
+    // Whole store as a single JSON object, keyed by namespace
+    pub(crate) async fn snapshot(&self) -> ScalarValue {
+        self.store.read().await.clone().into_json()
+    }
+
     pub async fn render_with_tera(&self, tera: &tera::Tera, template_name: &str) -> Result<String> {
-        let ctx = self.store.read().await;
-        tera.render(template_name, &ctx).map_err(|e| {
-            anyhow::anyhow!("Template rendering failed for '{}': {}", template_name, e)
-        })
+        self.render_scoped(|ctx| tera.render(template_name, ctx))
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!("Template rendering failed for '{}': {}", template_name, e)
+            })
     }
     pub async fn render_template<T: Into<String>>(&self, template: T) -> Result<String> {
         let template_str = template.into();
-        let mut tera = self.tera.write().await;
-        tera.add_raw_template("inline_template", &template_str)?;
-        let ctx = self.store.read().await;
-        tera.render("inline_template", &ctx)
+        self.render_scoped(|ctx| tera::Tera::one_off(&template_str, ctx, false))
+            .await
             .map_err(|e| anyhow::anyhow!("Template rendering failed for '{}': {}", template_str, e))
     }
+
+    // A scoped render works on a copy of the store taken under the read lock, with the scope layered on
+    // top. Parallel iterations render at the same time and never see each other's variables.
+    async fn render_scoped(
+        &self,
+        render: impl FnOnce(&tera::Context) -> tera::Result<String>,
+    ) -> tera::Result<String> {
+        let ctx = self.store.read().await;
+        let Some(scope) = &self.scope else {
+            return render(&ctx);
+        };
+        let mut layered = ctx.clone();
+        drop(ctx);
+        for (key, value) in scope.iter() {
+            layered.insert(key.as_str(), value);
+        }
+        render(&layered)
+    }
 }
 
 /*
@@ -1,14 +1,60 @@
 use crate::imports::*;
+use polars::prelude::{DataType, IntoLazy, LazyFrame, len};
 /*
     Types:
     * TabularValue - A tabular data structure, re-export of Polars DataFrame
+    * TabularEntry - What the store holds for a path: a DataFrame, or a LazyFrame collected when first read
+      along with what its plan reads
+    * LazySources - What a lazy plan reads: the files it scans and the DataFrames embedded in it
     * TabularStore - Store for managing TabularValues, used in ExecutionContext.
 */
 pub type TabularValue = polars::prelude::DataFrame;
 
+#[derive(Clone)]
+pub(crate) enum TabularEntry {
+    Eager(TabularValue),
+    Lazy {
+        frame: Arc<LazyFrame>, // Shared so get can tell the entry it collected is still the one stored
+        sources: LazySources,
+    },
+}
+
+// A plan's rows are determined by these. The frames are in-memory inputs joined into the plan (e.g. an
+// eager table in a SqlCommand), `describe_plan` only shows their schema.
+#[derive(Clone, Debug, Default)]
+pub struct LazySources {
+    pub files: Vec<PathBuf>,
+    pub frames: Vec<TabularValue>,
+}
+
+impl LazySources {
+    pub fn extend(&mut self, other: LazySources) {
+        self.files.extend(other.files);
+        self.frames.extend(other.frames);
+    }
+}
+
+impl From<Vec<PathBuf>> for LazySources {
+    fn from(files: Vec<PathBuf>) -> Self {
+        LazySources {
+            files,
+            frames: Vec::new(),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct TabularStore {
-    store: Arc<RwLock<HashMap<String, TabularValue>>>,
+    store: Arc<RwLock<HashMap<String, TabularEntry>>>,
+}
+
+impl std::fmt::Debug for TabularEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TabularEntry::Eager(df) => df.fmt(f),
+            TabularEntry::Lazy { .. } => f.write_str("LazyFrame(not collected)"),
+        }
+    }
 }
 
 impl Default for TabularStore {
@@ -31,16 +77,70 @@ impl TabularStore {
         let key = key.to_dotted();
         let value = value.clone();
         async move {
-            self.store.write().await.insert(key, value);
+            self.store
+                .write()
+                .await
+                .insert(key, TabularEntry::Eager(value));
             Ok(())
         }
     }
+    // Nothing is read until a consumer calls get (or results need the data), get_lazy keeps building on
+    // the plan instead. `sources` are what the plan reads, cache keys hash them instead of the rows.
+    pub fn insert_lazy(
+        &self,
+        key: &StorePath,
+        value: LazyFrame,
+        sources: LazySources,
+    ) -> impl std::future::Future<Output = Result<()>> + '_ {
+        let key = key.to_dotted();
+        async move {
+            self.store.write().await.insert(
+                key,
+                TabularEntry::Lazy {
+                    frame: Arc::new(value),
+                    sources,
+                },
+            );
+            Ok(())
+        }
+    }
+    // Collects a lazy entry and keeps the result, so it's only computed once. The collect runs outside
+    // the lock, the result only replaces the entry if nothing was inserted at the key in the meantime.
     pub fn get(
         &self,
         key: &StorePath,
     ) -> impl std::future::Future<Output = Result<Option<TabularValue>>> + '_ {
         let key = key.to_dotted();
-        async move { Ok(self.store.read().await.get(&key).cloned()) }
+        async move {
+            let entry = self.store.read().await.get(&key).cloned();
+            match entry {
+                None => Ok(None),
+                Some(TabularEntry::Eager(df)) => Ok(Some(df)),
+                Some(TabularEntry::Lazy { frame, .. }) => {
+                    let df = collect(&key, (*frame).clone()).await?;
+                    let mut store = self.store.write().await;
+                    if let Some(TabularEntry::Lazy { frame: current, .. }) = store.get(&key)
+                        && Arc::ptr_eq(current, &frame)
+                    {
+                        store.insert(key, TabularEntry::Eager(df.clone()));
+                    }
+                    Ok(Some(df))
+                }
+            }
+        }
+    }
+    // Never collects, a DataFrame entry is wrapped with `.lazy()`
+    pub fn get_lazy(
+        &self,
+        key: &StorePath,
+    ) -> impl std::future::Future<Output = Result<Option<LazyFrame>>> + '_ {
+        let key = key.to_dotted();
+        async move {
+            Ok(self.store.read().await.get(&key).map(|entry| match entry {
+                TabularEntry::Eager(df) => df.clone().lazy(),
+                TabularEntry::Lazy { frame, .. } => (**frame).clone(),
+            }))
+        }
     }
     pub fn remove(
         &self,
@@ -49,10 +149,57 @@ impl TabularStore {
         let key = key.to_dotted();
         async move {
             let removed = self.store.write().await.remove(&key);
-            Ok(removed)
+            match removed {
+                Some(TabularEntry::Lazy { frame, .. }) => {
+                    collect(&key, Arc::unwrap_or_clone(frame)).await.map(Some)
+                }
+                Some(TabularEntry::Eager(df)) => Ok(Some(df)),
+                None => Ok(None),
+            }
         }
     }
     pub async fn keys(&self) -> Vec<String> {
         self.store.read().await.keys().cloned().collect()
     }
+    pub async fn is_lazy(&self, key: &StorePath) -> bool {
+        matches!(
+            self.store.read().await.get(&key.to_dotted()),
+            Some(TabularEntry::Lazy { .. })
+        )
+    }
+    // What a lazy entry's plan reads, None for DataFrames and missing entries
+    pub async fn lazy_sources(&self, key: &StorePath) -> Option<LazySources> {
+        match self.store.read().await.get(&key.to_dotted()) {
+            Some(TabularEntry::Lazy { sources, .. }) => Some(sources.clone()),
+            _ => None,
+        }
+    }
+    // Every frame as a LazyFrame, nothing is collected
+    pub(crate) async fn snapshot_lazy(&self) -> HashMap<String, LazyFrame> {
+        self.store
+            .read()
+            .await
+            .iter()
+            .map(|(key, entry)| {
+                let lf = match entry {
+                    TabularEntry::Eager(df) => df.clone().lazy(),
+                    TabularEntry::Lazy { frame, .. } => (**frame).clone(),
+                };
+                (key.clone(), lf)
+            })
+            .collect()
+    }
+}
+
+async fn collect(key: &str, lf: LazyFrame) -> Result<TabularValue> {
+    tokio::task::spawn_blocking(move || lf.collect())
+        .await
+        .context("Collect task panicked")?
+        .with_context(|| format!("Failed to collect lazy frame '{}'", key))
+}
+
+// Blocking. Runs the plan down to a row count, without keeping the rows
+pub(crate) fn count_rows(lf: LazyFrame) -> Result<u64> {
+    let counted = lf.select([len().cast(DataType::UInt64)]).collect()?;
+    Ok(counted.get_columns()[0].u64()?.get(0).unwrap_or(0))
 }